@@ -0,0 +1,144 @@
+use chrono::Duration;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use dlm::*;
+
+// 50000件の操作からなる台帳を作る
+// 1000個の貸出品を60の団体に順に貸出・返却し、ときどき編集と削除を混ぜる
+fn make_ledger(len: isize) -> Vec<LendData> {
+  let start = parse_time_str("2021/01/09 08:00").unwrap();
+  let mut lent = vec![None; 1000];
+  let mut v = Vec::new();
+  for num in 1..=len {
+    let time = start + Duration::seconds(num as i64 * 5);
+    let lend_type = if num % 250 == 0 {
+      LendType::Remove(num - 3)
+    } else if num % 100 == 0 {
+      LendType::Edit(
+        num - 5,
+        EditData {
+          product_num: None,
+          destination_num: None,
+          time: Some(time - Duration::seconds(1)),
+        },
+      )
+    } else {
+      let i = (num as usize * 7) % lent.len();
+      let product_num = format!("{:04}", i);
+      match lent[i].take() {
+        Some(destination_num) => LendType::Return(product_num, destination_num),
+        None => {
+          let destination_num = (num % 60).to_string();
+          lent[i] = Some(destination_num.clone());
+          LendType::Lend(product_num, destination_num)
+        }
+      }
+    };
+    v.push(LendData {
+      time,
+      lend_type,
+      num,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
+    })
+  }
+  v
+}
+
+fn bench_ledger(c: &mut Criterion) {
+  let lst = make_ledger(50000);
+  let state = LedgerState::new(lst.clone());
+  let next_time = lst.last().unwrap().time + Duration::seconds(5);
+  c.bench_function("organize_lend_data 50k", |b| {
+    b.iter(|| organize_lend_data(&lst))
+  });
+  c.bench_function("make_now_lend_data_lst 50k", |b| {
+    b.iter(|| make_now_lend_data_lst(&lst))
+  });
+  c.bench_function("LedgerState::new 50k", |b| {
+    b.iter(|| LedgerState::new(lst.clone()))
+  });
+  c.bench_function("LedgerState::find_lend_data 50k", |b| {
+    b.iter(|| state.find_lend_data("0123"))
+  });
+  // 1回ごとの追加では、容量の確保し直しの時間が目立ってしまうので、
+  // 時刻順で最後になる貸出と返却を1000件続けて追加する時間を測る
+  c.bench_function("LedgerState::push 1000 lend/return 50k", |b| {
+    b.iter_batched(
+      || state.clone(),
+      |mut state| {
+        for i in 0..1000 {
+          let product_num = format!("9{:03}", i / 2);
+          let lend_type = if i % 2 == 0 {
+            LendType::Lend(product_num, "1".to_string())
+          } else {
+            LendType::Return(product_num, "1".to_string())
+          };
+          state.push(LendData {
+            time: next_time + Duration::seconds(i),
+            lend_type,
+            num: 50001 + i as isize,
+            backfilled: false,
+            overridden: false,
+            condition: None,
+            note: None,
+            operator: None,
+          });
+        }
+        state
+      },
+      BatchSize::LargeInput,
+    )
+  });
+  c.bench_function("LedgerState::push remove 50k", |b| {
+    b.iter_batched(
+      || state.clone(),
+      |mut state| {
+        state.push(LendData {
+          time: next_time,
+          lend_type: LendType::Remove(49999),
+          num: 50001,
+          backfilled: false,
+          overridden: false,
+          condition: None,
+          note: None,
+          operator: None,
+        });
+        state
+      },
+      BatchSize::LargeInput,
+    )
+  });
+  // 'all --item'と'undo'の検査は、操作番号の索引から品名を引くので全体を一度たどるだけで済む
+  c.bench_function("filter_lend_data --item 50k", |b| {
+    let filter = AllPrintFilter {
+      product_num: Some("0123".to_string()),
+      ..AllPrintFilter::default()
+    };
+    b.iter(|| filter_lend_data(&state, &filter))
+  });
+  c.bench_function("find_dependent_lend_data 50k", |b| {
+    b.iter(|| find_dependent_lend_data(&state, &[49000], &[]))
+  });
+  // 後日入力では、その時刻の時点での貸出状況を一度だけ作り、品名ごとに計算し直さない
+  c.bench_function("Ledger::lend backfilled 10 items 50k", |b| {
+    let ledger = Ledger::new(lst.clone());
+    let at = lst[25000].time;
+    let items: Vec<String> = (0..10).map(|i| format!("8{:03}", i)).collect();
+    b.iter_batched(
+      || ledger.clone(),
+      |mut ledger| {
+        ledger
+          .lend(&items, "1", Some(at), None, false, None)
+          .unwrap();
+        ledger
+      },
+      BatchSize::LargeInput,
+    )
+  });
+}
+
+criterion_group!(benches, bench_ledger);
+criterion_main!(benches);
@@ -0,0 +1,233 @@
+use chrono::{FixedOffset, Utc};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// CSVファイルの自動バックアップの設定
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+  // バックアップを保存するディレクトリ
+  pub dir: PathBuf,
+  // 何回の操作ごとにバックアップを取るか（0なら操作の回数ではバックアップを取らない）
+  pub every: usize,
+  // 残しておくバックアップの数（0なら全て残す）
+  pub keep: usize,
+}
+
+// バックアップ一つ分の情報
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+  // バックアップを取った時刻から作った識別子
+  pub id: String,
+  pub path: PathBuf,
+}
+
+// 設定用のJSONファイルの"backup"タグと起動時の引数からバックアップの設定を作る
+// "backup" : { "dir" : "<ディレクトリ>", "every" : <操作の回数>, "keep" : <残す数> }
+// ディレクトリは起動時に'--backup-dir'で与えられたものを優先し、
+// どちらにも無い場合は'<CSVファイル名>.backup'とする
+pub fn make_backup_config(
+  data_file_name: &str,
+  backup_json_data: &Value,
+  dir_opt: Option<&str>,
+) -> BackupConfig {
+  let dir = match dir_opt.or_else(|| backup_json_data["dir"].as_str()) {
+    Some(dir) => PathBuf::from(dir),
+    None => PathBuf::from(format!("{}.backup", data_file_name)),
+  };
+  let every = backup_json_data["every"].as_u64().unwrap_or(10) as usize;
+  let keep = backup_json_data["keep"].as_u64().unwrap_or(30) as usize;
+  BackupConfig { dir, every, keep }
+}
+
+// バックアップのファイル名の先頭部分
+// 'data.csv'なら'data-'となる
+fn snapshot_file_name_prefix(data_file_name: &str) -> String {
+  let stem = Path::new(data_file_name)
+    .file_stem()
+    .and_then(|s| s.to_str())
+    .unwrap_or("dlm");
+  format!("{}-", stem)
+}
+
+// 識別子を時刻の部分と、同じ秒に取ったときに付ける番号に分ける
+// 'YYYYmmdd-HHMMSS'か'YYYYmmdd-HHMMSS-<番号>'の形で無い場合はNoneを返す
+// 同じディレクトリにある、名前が似た別のCSVファイルのバックアップを取り違えないようにするため
+fn parse_snapshot_id(id: &str) -> Option<(&str, usize)> {
+  let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+  let time_str = id.get(0..15)?;
+  let (date, time) = (time_str.get(0..8)?, time_str.get(9..15)?);
+  if !is_digits(date) || time_str.get(8..9)? != "-" || !is_digits(time) {
+    return None;
+  }
+  match id.get(15..)? {
+    "" => Some((time_str, 1)),
+    rest => {
+      let n = rest.strip_prefix('-').filter(|n| is_digits(n))?;
+      Some((time_str, n.parse().ok()?))
+    }
+  }
+}
+
+// 保存されているバックアップを古い順に並べて返す
+pub fn snapshot_lst(config: &BackupConfig, data_file_name: &str) -> Vec<Snapshot> {
+  let prefix = snapshot_file_name_prefix(data_file_name);
+  let mut v: Vec<Snapshot> = match fs::read_dir(&config.dir) {
+    Err(_) => Vec::new(),
+    Ok(read_dir) => read_dir
+      .filter_map(|entry| entry.ok())
+      .filter_map(|entry| {
+        let file_name = entry.file_name().into_string().ok()?;
+        let id = file_name.strip_prefix(&prefix)?.strip_suffix(".csv")?;
+        parse_snapshot_id(id)?;
+        Some(Snapshot {
+          id: id.to_string(),
+          path: entry.path(),
+        })
+      })
+      .collect(),
+  };
+  // 時刻の順に並べ、同じ秒に取ったものは付けた番号の順にする
+  // 番号は桁数が揃っていないので、文字列としては並べない
+  v.sort_by_key(|snapshot| {
+    parse_snapshot_id(&snapshot.id).map(|(time_str, n)| (time_str.to_string(), n))
+  });
+  v
+}
+
+// 識別子か、'backup list'で表示される番号からバックアップを探す
+pub fn find_snapshot(config: &BackupConfig, data_file_name: &str, id: &str) -> Option<Snapshot> {
+  let lst = snapshot_lst(config, data_file_name);
+  match lst.iter().find(|snapshot| snapshot.id == id) {
+    Some(snapshot) => Some(snapshot.clone()),
+    None => {
+      let n: usize = id.parse().ok()?;
+      lst.get(n.checked_sub(1)?).cloned()
+    }
+  }
+}
+
+// 現在のCSVファイルをバックアップ用のディレクトリにコピーする
+// 残しておく数を超えた古いバックアップは削除する
+// CSVファイルがまだ無い場合は何もせずにNoneを返す
+pub fn make_snapshot(config: &BackupConfig, data_file_name: &str) -> Option<Snapshot> {
+  if !Path::new(data_file_name).exists() {
+    return None;
+  }
+  fs::create_dir_all(&config.dir).ok()?;
+  let prefix = snapshot_file_name_prefix(data_file_name);
+  let time_str = Utc::now()
+    .with_timezone(&FixedOffset::east(9 * 3600))
+    .format("%Y%m%d-%H%M%S")
+    .to_string();
+  // 同じ秒に二回バックアップを取った場合は後ろに番号を付ける
+  let mut id = time_str.clone();
+  let mut n = 1;
+  while config.dir.join(format!("{}{}.csv", prefix, id)).exists() {
+    n += 1;
+    id = format!("{}-{}", time_str, n);
+  }
+  let path = config.dir.join(format!("{}{}.csv", prefix, id));
+  fs::copy(data_file_name, &path).ok()?;
+  // 古いものから削除する
+  if config.keep > 0 {
+    let lst = snapshot_lst(config, data_file_name);
+    let len = lst.len();
+    for snapshot in lst.iter().take(len.saturating_sub(config.keep)) {
+      fs::remove_file(&snapshot.path).ok();
+    }
+  }
+  Some(Snapshot { id, path })
+}
+
+// バックアップの中身をCSVファイルに書き戻す
+// 読み込み中の他のdlmが書きかけのファイルを読まないように、一時ファイルにコピーしてから置き換える
+pub fn restore_snapshot(snapshot: &Snapshot, data_file_name: &str) -> std::io::Result<()> {
+  let tmp_path = format!("{}.tmp", data_file_name);
+  fs::copy(&snapshot.path, &tmp_path)?;
+  fs::rename(&tmp_path, data_file_name)
+}
+
+#[cfg(test)]
+fn test_backup_config(name: &str, keep: usize) -> BackupConfig {
+  let dir = std::env::temp_dir().join(format!("dlm-backup-test-{}-{}", std::process::id(), name));
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  BackupConfig {
+    dir,
+    every: 0,
+    keep,
+  }
+}
+
+#[test]
+fn check_snapshot_lst_order() {
+  let config = test_backup_config("order", 0);
+  for id in [
+    "20201123-174000-10",
+    "20201123-174000-2",
+    "20201123-174000",
+    "20201123-173959",
+  ]
+  .iter()
+  {
+    fs::write(config.dir.join(format!("data-{}.csv", id)), "").unwrap();
+  }
+  // 'data-old.csv'のバックアップと、バックアップではないファイルは含めない
+  fs::write(config.dir.join("data-old-20201123-174001.csv"), "").unwrap();
+  fs::write(config.dir.join("data-memo.csv"), "").unwrap();
+  let id_lst: Vec<String> = snapshot_lst(&config, "data.csv")
+    .into_iter()
+    .map(|snapshot| snapshot.id)
+    .collect();
+  assert_eq!(
+    id_lst,
+    vec![
+      "20201123-173959",
+      "20201123-174000",
+      "20201123-174000-2",
+      "20201123-174000-10"
+    ]
+  );
+  // 'backup list'の番号でも探せる
+  assert_eq!(
+    find_snapshot(&config, "data.csv", "3").map(|snapshot| snapshot.id),
+    Some("20201123-174000-2".to_string())
+  );
+  assert_eq!(
+    find_snapshot(&config, "data.csv", "20201123-174000-10").map(|snapshot| snapshot.id),
+    Some("20201123-174000-10".to_string())
+  );
+  assert!(find_snapshot(&config, "data.csv", "0").is_none());
+  assert!(find_snapshot(&config, "data.csv", "5").is_none());
+}
+
+#[test]
+fn check_make_snapshot_keep() {
+  let config = test_backup_config("keep", 3);
+  for id in ["20201123-174000-10", "20201123-174000-2", "20201123-174000"].iter() {
+    fs::write(config.dir.join(format!("data-{}.csv", id)), "").unwrap();
+  }
+  fs::write(config.dir.join("data-old-20201123-174000.csv"), "").unwrap();
+  let data_file_name = config.dir.join("data.csv").to_string_lossy().to_string();
+  fs::write(&data_file_name, "new").unwrap();
+  let snapshot = make_snapshot(&config, &data_file_name).unwrap();
+  // 一番古いものだけが削除される
+  let id_lst: Vec<String> = snapshot_lst(&config, &data_file_name)
+    .into_iter()
+    .map(|snapshot| snapshot.id)
+    .collect();
+  assert_eq!(
+    id_lst,
+    vec![
+      "20201123-174000-2".to_string(),
+      "20201123-174000-10".to_string(),
+      snapshot.id.clone()
+    ]
+  );
+  // 別のCSVファイルのバックアップは削除しない
+  assert!(config.dir.join("data-old-20201123-174000.csv").exists());
+  fs::write(&data_file_name, "changed").unwrap();
+  restore_snapshot(&snapshot, &data_file_name).unwrap();
+  assert_eq!(fs::read_to_string(&data_file_name).unwrap(), "new");
+}
@@ -0,0 +1,348 @@
+use crate::message::{fill, text};
+use crate::{destination_num_to_str, product_num_to_str, ConfigData, LendData, LendType};
+use std::collections::{BTreeMap, HashMap};
+
+// 設定ファイルの"sizai"の一つの項目は、正式名称だけの文字列か、
+// {"name": 正式名称, "category": 種類, "tags": [タグ..], "attrs": {属性名: 値..}}の形のオブジェクト
+
+// 品名の正式名称
+pub fn item_name<'a>(product_num: &str, config_data: &'a ConfigData) -> Option<&'a str> {
+  let item = &config_data.sizai[product_num];
+  item.as_str().or_else(|| item["name"].as_str())
+}
+
+// 品名の種類
+// 設定ファイルで"category"が与えられていればそれを使い、
+// 無ければ正式名称から末尾の番号を除いたもの（"内リール1"なら"内リール"）にする
+// 正式名称も無い場合は品名の番号そのものにする
+pub fn item_category(product_num: &str, config_data: &ConfigData) -> String {
+  if let Some(category) = config_data.sizai[product_num]["category"].as_str() {
+    return category.to_string();
+  }
+  match item_name(product_num, config_data) {
+    None => product_num.to_string(),
+    Some(name) => {
+      let category = name.trim_end_matches(|c: char| c.is_ascii_digit() || c.is_whitespace());
+      if category.is_empty() {
+        name.to_string()
+      } else {
+        category.to_string()
+      }
+    }
+  }
+}
+
+// 品名に付けられたタグ
+pub fn item_tag_lst(product_num: &str, config_data: &ConfigData) -> Vec<String> {
+  match config_data.sizai[product_num]["tags"].as_array() {
+    None => Vec::new(),
+    Some(tag_lst) => tag_lst
+      .iter()
+      .filter_map(|tag| tag.as_str().map(|s| s.to_string()))
+      .collect(),
+  }
+}
+
+// 品名の属性（消費電力や長さなど）の(属性名, 値)のリスト
+// 値は数でも文字列でもよい
+pub fn item_attribute_lst(product_num: &str, config_data: &ConfigData) -> Vec<(String, String)> {
+  match config_data.sizai[product_num]["attrs"].as_object() {
+    None => Vec::new(),
+    Some(map) => map
+      .iter()
+      .map(|(key, value)| {
+        let value = match value.as_str() {
+          Some(s) => s.to_string(),
+          None => value.to_string(),
+        };
+        (key.clone(), value)
+      })
+      .collect(),
+  }
+}
+
+// 品名が、与えられた種類かタグに当てはまるか
+pub fn matches_category(product_num: &str, config_data: &ConfigData, category: &str) -> bool {
+  item_category(product_num, config_data) == category
+    || item_tag_lst(product_num, config_data)
+      .iter()
+      .any(|tag| tag == category)
+}
+
+// 設定ファイルにある品名の番号のリスト
+pub fn item_num_lst(config_data: &ConfigData) -> Vec<String> {
+  match config_data.sizai.as_object() {
+    None => Vec::new(),
+    Some(map) => map.keys().cloned().collect(),
+  }
+}
+
+// 貸出中の品名と貸出先の対応
+pub(crate) fn make_holder_map(now_lend_data_lst: &[LendData]) -> HashMap<&str, &str> {
+  now_lend_data_lst
+    .iter()
+    .filter_map(|lend_data| match &lend_data.lend_type {
+      LendType::Lend(product_num, destination_num) => {
+        Some((product_num.as_str(), destination_num.as_str()))
+      }
+      _ => None,
+    })
+    .collect()
+}
+
+// 紛失・使用終了した品名と、貸出状況の代わりに表示する文字列の対応
+// 同じ品名に複数の記録がある場合は最初のものにする
+fn make_retired_map(retired_lend_data_lst: &[LendData]) -> HashMap<&str, &'static str> {
+  retired_lend_data_lst
+    .iter()
+    .rev()
+    .filter_map(|lend_data| match &lend_data.lend_type {
+      LendType::Lost(product_num, _) => Some((product_num.as_str(), text("catalog.lost"))),
+      LendType::Retire(product_num, _) => Some((product_num.as_str(), text("catalog.retired"))),
+      _ => None,
+    })
+    .collect()
+}
+
+// 種類ごとの貸出状況
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategorySummary {
+  pub category: String,
+  // 貸出中の数
+  pub out: usize,
+  // 全体の数
+  pub total: usize,
+}
+
+// 種類ごとに、全体のうちいくつが貸し出されているかを数える
+// 種類が与えられた場合は、その種類かタグに当てはまる品名だけを一つにまとめて数える
+// 設定ファイルに無い品名が貸し出されている場合は、その品名も数に入れる
+// 紛失・使用終了した品名はもう貸し出せないので、全体の数に入れない
+pub fn make_category_summary_lst(
+  now_lend_data_lst: &[LendData],
+  retired_lend_data_lst: &[LendData],
+  config_data: &ConfigData,
+  category_opt: Option<&str>,
+) -> Vec<CategorySummary> {
+  let holder_map = make_holder_map(now_lend_data_lst);
+  let retired_map = make_retired_map(retired_lend_data_lst);
+  let mut product_num_lst = item_num_lst(config_data);
+  for product_num in holder_map.keys() {
+    if config_data.sizai.get(*product_num).is_none() {
+      product_num_lst.push(product_num.to_string())
+    }
+  }
+  let mut summary_map: BTreeMap<String, CategorySummary> = BTreeMap::new();
+  for product_num in product_num_lst.iter() {
+    if retired_map.contains_key(product_num.as_str()) {
+      continue;
+    }
+    let category = match category_opt {
+      None => item_category(product_num, config_data),
+      Some(category) if matches_category(product_num, config_data, category) => {
+        category.to_string()
+      }
+      Some(_) => continue,
+    };
+    let summary = summary_map
+      .entry(category.clone())
+      .or_insert_with(|| CategorySummary {
+        category,
+        out: 0,
+        total: 0,
+      });
+    summary.total += 1;
+    if holder_map.contains_key(product_num.as_str()) {
+      summary.out += 1
+    }
+  }
+  summary_map.into_values().collect()
+}
+
+// 品名の一覧を、種類・タグ・属性・貸出状況と共に表示する文字列を作る
+// retired_lend_data_lstは紛失・使用終了の操作で、その品名は貸出状況の代わりにそのことを表示する
+pub fn render_text(
+  now_lend_data_lst: &[LendData],
+  retired_lend_data_lst: &[LendData],
+  config_data: &ConfigData,
+  category_opt: Option<&str>,
+) -> String {
+  let holder_map = make_holder_map(now_lend_data_lst);
+  let retired_map = make_retired_map(retired_lend_data_lst);
+  let mut s = String::new();
+  for product_num in item_num_lst(config_data).iter() {
+    if category_opt.is_some_and(|category| !matches_category(product_num, config_data, category)) {
+      continue;
+    }
+    let status = match (
+      retired_map.get(product_num.as_str()),
+      holder_map.get(product_num.as_str()),
+    ) {
+      (Some(status), _) => status.to_string(),
+      (None, None) => text("catalog.available").to_string(),
+      (None, Some(holder)) => fill(
+        "catalog.lent",
+        &[&destination_num_to_str(holder, Some(config_data))],
+      ),
+    };
+    s.push_str(&fill(
+      "catalog.item",
+      &[
+        &product_num_to_str(product_num, Some(config_data)),
+        &item_category(product_num, config_data),
+        &status,
+      ],
+    ));
+    s.push('\n');
+    let tag_lst = item_tag_lst(product_num, config_data);
+    if !tag_lst.is_empty() {
+      s.push_str(&fill("catalog.tags", &[&tag_lst.join(", ")]));
+      s.push('\n');
+    }
+    let attribute_lst = item_attribute_lst(product_num, config_data);
+    if !attribute_lst.is_empty() {
+      let attribute_str = attribute_lst
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(", ");
+      s.push_str(&fill("catalog.attrs", &[&attribute_str]));
+      s.push('\n');
+    }
+  }
+  if s.is_empty() {
+    s.push_str(text("catalog.empty"));
+    s.push('\n');
+  }
+  s
+}
+
+#[test]
+fn check_item_category() {
+  let config_data = crate::make_config_data(
+    serde_json::json!({
+      "0001": "内リール1",
+      "0002": {"name": "延長コード", "category": "リール", "tags": ["電源"], "attrs": {"長さ": "10m", "容量": 1500}},
+      "0003": {"name": "スモークマシン", "tags": ["舞台"]},
+      "0004": {"name": "延長コード", "category": "リール", "tags": ["電源"]}
+    }),
+    serde_json::json!({}),
+    serde_json::json!({}),
+  );
+  assert_eq!(item_name("0002", &config_data), Some("延長コード"));
+  assert_eq!(item_category("0001", &config_data), "内リール");
+  assert_eq!(item_category("0002", &config_data), "リール");
+  assert_eq!(item_category("0003", &config_data), "スモークマシン");
+  assert_eq!(item_category("9999", &config_data), "9999");
+  assert!(matches_category("0002", &config_data, "電源"));
+  assert!(!matches_category("0001", &config_data, "電源"));
+  assert_eq!(
+    item_attribute_lst("0002", &config_data),
+    vec![
+      ("容量".to_string(), "1500".to_string()),
+      ("長さ".to_string(), "10m".to_string())
+    ]
+  );
+  let now_lend_data_lst = vec![crate::make_test_lend_data(
+    1,
+    "2020-11-23T09:00:00+09:00",
+    LendType::Lend("0002".to_string(), "12".to_string()),
+  )];
+  let retired_lend_data_lst = vec![crate::make_test_lend_data(
+    2,
+    "2020-11-23T09:00:00+09:00",
+    LendType::Lost("0004".to_string(), None),
+  )];
+  assert_eq!(
+    make_category_summary_lst(
+      &now_lend_data_lst,
+      &retired_lend_data_lst,
+      &config_data,
+      Some("電源")
+    ),
+    vec![CategorySummary {
+      category: "電源".to_string(),
+      out: 1,
+      total: 1
+    }]
+  );
+}
+
+#[test]
+fn check_render_text() {
+  let config_data = crate::make_config_data(
+    serde_json::json!({
+      "0001": "内リール1",
+      "0002": "内リール2",
+      "0003": "内リール3",
+      "0004": {"name": "スモークマシン", "tags": ["舞台"]}
+    }),
+    serde_json::json!({"12": "物理部"}),
+    serde_json::json!({}),
+  );
+  let data = |num: isize, lend_type: LendType| {
+    crate::make_test_lend_data(num, "2020-11-23T09:00:00+09:00", lend_type)
+  };
+  let now_lend_data_lst = vec![data(
+    1,
+    LendType::Lend("0003".to_string(), "12".to_string()),
+  )];
+  let retired_lend_data_lst = vec![
+    data(
+      2,
+      LendType::Lost("0001".to_string(), Some("12".to_string())),
+    ),
+    data(3, LendType::Retire("0002".to_string(), None)),
+  ];
+  let line_lst = |category_opt: Option<&str>| {
+    render_text(
+      &now_lend_data_lst,
+      &retired_lend_data_lst,
+      &config_data,
+      category_opt,
+    )
+    .lines()
+    .map(|line| line.to_string())
+    .collect::<Vec<String>>()
+  };
+  // 紛失・使用終了した品は、貸出状況の代わりにそのことを表示する
+  assert_eq!(
+    line_lst(Some("内リール")),
+    vec![
+      fill(
+        "catalog.item",
+        &[&"0001（内リール1）", &"内リール", &text("catalog.lost")]
+      ),
+      fill(
+        "catalog.item",
+        &[&"0002（内リール2）", &"内リール", &text("catalog.retired")]
+      ),
+      fill(
+        "catalog.item",
+        &[
+          &"0003（内リール3）",
+          &"内リール",
+          &fill("catalog.lent", &[&"12（物理部）"])
+        ]
+      ),
+    ]
+  );
+  assert_eq!(
+    line_lst(Some("舞台")),
+    vec![
+      fill(
+        "catalog.item",
+        &[
+          &"0004（スモークマシン）",
+          &"スモークマシン",
+          &text("catalog.available")
+        ]
+      ),
+      fill("catalog.tags", &[&"舞台"]),
+    ]
+  );
+  assert_eq!(
+    line_lst(Some("照明")),
+    vec![text("catalog.empty").to_string()]
+  );
+}
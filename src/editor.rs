@@ -0,0 +1,312 @@
+use dlm as lib;
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Config, Context, Editor, Helper};
+
+// 補完に使うコマンド名
+const COMMAND_NAME_LST: [&str; 30] = [
+  "help",
+  "exit",
+  "lend",
+  "l",
+  "return",
+  "r",
+  "backfill",
+  "edit",
+  "remove",
+  "undo",
+  "redo",
+  "show",
+  "all",
+  "login",
+  "logout",
+  "check",
+  "verify",
+  "migrate",
+  "backup",
+  "history",
+  "scan",
+  "labels",
+  "receipt",
+  "report",
+  "stats",
+  "catalog",
+  "repair",
+  "lost",
+  "retire",
+  "stocktake",
+];
+
+pub type DlmEditor = Editor<DlmHelper, DefaultHistory>;
+
+// 入力の補完に使う情報
+// 貸出中の品名は操作のたびに変わるので、入力を受け取る前にset_lent_lstで更新する
+#[derive(Debug, Clone, Default)]
+pub struct DlmHelper {
+  // (番号, 正式名称)
+  sizai_lst: Vec<(String, String)>,
+  sandan_lst: Vec<(String, String)>,
+  // 品名の種類とタグ
+  category_lst: Vec<String>,
+  // (貸出中の品名, 貸出先)
+  lent_lst: Vec<(String, String)>,
+}
+
+// 設定ファイルの"sizai"や"sandan"のタグから(番号, 名前)のリストを作る
+// "sizai"の項目がオブジェクトの場合は"name"を名前にする
+fn json_to_name_lst(json: &serde_json::Value) -> Vec<(String, String)> {
+  match json.as_object() {
+    None => Vec::new(),
+    Some(map) => map
+      .iter()
+      .map(|(num, name)| {
+        let name = name
+          .as_str()
+          .or_else(|| name["name"].as_str())
+          .unwrap_or("");
+        (num.clone(), name.to_string())
+      })
+      .collect(),
+  }
+}
+
+// 設定ファイルの"sizai"にある品名の種類とタグを重複無く集める
+fn make_category_lst(config_data: &lib::ConfigData) -> Vec<String> {
+  let mut category_lst: Vec<String> = lib::catalog::item_num_lst(config_data)
+    .iter()
+    .flat_map(|num| {
+      let mut lst = lib::catalog::item_tag_lst(num, config_data);
+      lst.push(lib::catalog::item_category(num, config_data));
+      lst
+    })
+    .collect();
+  category_lst.sort();
+  category_lst.dedup();
+  category_lst
+}
+
+impl DlmHelper {
+  pub fn new(config_data: &lib::ConfigData) -> Self {
+    DlmHelper {
+      sizai_lst: json_to_name_lst(&config_data.sizai),
+      sandan_lst: json_to_name_lst(&config_data.sandan),
+      category_lst: make_category_lst(config_data),
+      lent_lst: Vec::new(),
+    }
+  }
+
+  pub fn set_lent_lst(&mut self, now_lend_data_lst: &[lib::LendData]) {
+    self.lent_lst = now_lend_data_lst
+      .iter()
+      .filter_map(|data| match &data.lend_type {
+        lib::LendType::Lend(product_num, destination_num) => {
+          Some((product_num.clone(), destination_num.clone()))
+        }
+        _ => None,
+      })
+      .collect()
+  }
+
+  fn sizai_name(&self, product_num: &str) -> String {
+    self
+      .sizai_lst
+      .iter()
+      .find(|(num, _)| num == product_num)
+      .map(|(_, name)| name.clone())
+      .unwrap_or_default()
+  }
+
+  // カーソルより前の入力から、補完する単語の開始位置と候補を決める
+  // 候補は(番号, 表示する説明)のリスト
+  fn candidate_lst(&self, line: &str) -> (usize, Vec<(String, String)>) {
+    let start = line
+      .rfind(|c: char| c.is_whitespace())
+      .map(|i| i + 1)
+      .unwrap_or(0);
+    let word_lst: Vec<&str> = line[..start].split_whitespace().collect();
+    let command_name = match word_lst.first() {
+      None => {
+        let lst = COMMAND_NAME_LST
+          .iter()
+          .map(|name| (name.to_string(), String::new()))
+          .collect();
+        return (start, lst);
+      }
+      Some(name) => name.to_ascii_lowercase(),
+    };
+    let option_name = word_lst.last().filter(|s| s.starts_with('-')).cloned();
+    let lst = match (command_name.as_str(), option_name) {
+      // 時刻などは補完しない
+      (_, Some("--product")) | (_, Some("-p")) | ("all", Some("--item")) => self.sizai_lst.clone(),
+      (_, Some("--destination")) | (_, Some("-d")) => self.sandan_lst.clone(),
+      (_, Some("--category")) => self
+        .category_lst
+        .iter()
+        .map(|category| (category.clone(), String::new()))
+        .collect(),
+      ("receipt", Some("--format")) => vec![
+        ("text".to_string(), String::new()),
+        ("html".to_string(), String::new()),
+        ("pdf".to_string(), String::new()),
+      ],
+      ("return", Some("--condition")) | ("r", Some("--condition")) => vec![
+        ("ok".to_string(), String::new()),
+        ("damaged".to_string(), String::new()),
+        ("missing-parts".to_string(), String::new()),
+      ],
+      ("labels", Some("--code")) => vec![
+        ("code128".to_string(), String::new()),
+        ("qr".to_string(), String::new()),
+      ],
+      (_, Some(_)) => Vec::new(),
+      // 貸出中でない品名と貸出先
+      ("lend", _) | ("l", _) => self
+        .sizai_lst
+        .iter()
+        .filter(|(num, _)| !self.lent_lst.iter().any(|(lent, _)| lent == num))
+        .chain(self.sandan_lst.iter())
+        .cloned()
+        .collect(),
+      // 貸出中の品名と、その貸出先
+      ("return", _) | ("r", _) => {
+        let mut lst: Vec<(String, String)> = self
+          .lent_lst
+          .iter()
+          .map(|(num, _)| (num.clone(), self.sizai_name(num)))
+          .collect();
+        lst.extend(
+          self
+            .sandan_lst
+            .iter()
+            .filter(|(num, _)| self.lent_lst.iter().any(|(_, holder)| holder == num))
+            .cloned(),
+        );
+        lst
+      }
+      ("repair", _) => self.sizai_lst.clone(),
+      ("lost", _) | ("retire", _) if word_lst.len() == 1 => self.sizai_lst.clone(),
+      ("backup", _) if word_lst.len() == 1 => vec![
+        ("list".to_string(), String::new()),
+        ("restore".to_string(), String::new()),
+      ],
+      ("backfill", _) if word_lst.len() == 1 => vec![("end".to_string(), String::new())],
+      ("report", _) if word_lst.len() == 1 => vec![("final".to_string(), String::new())],
+      ("report", _) if word_lst.len() == 2 => vec![("--out".to_string(), String::new())],
+      ("scan", _) if word_lst.len() == 1 => vec![("return".to_string(), String::new())],
+      ("labels", _) if word_lst.len() == 1 => vec![
+        ("items".to_string(), String::new()),
+        ("groups".to_string(), String::new()),
+      ],
+      _ => Vec::new(),
+    };
+    (start, lst)
+  }
+}
+
+impl Completer for DlmHelper {
+  type Candidate = Pair;
+
+  fn complete(
+    &self,
+    line: &str,
+    pos: usize,
+    _ctx: &Context<'_>,
+  ) -> rustyline::Result<(usize, Vec<Pair>)> {
+    let (start, lst) = self.candidate_lst(&line[..pos]);
+    let word = &line[start..pos];
+    let pair_lst = lst
+      .into_iter()
+      .filter(|(num, _)| num.starts_with(word))
+      .map(|(num, name)| Pair {
+        display: if name.is_empty() {
+          num.clone()
+        } else {
+          format!("{}（{}）", num, name)
+        },
+        replacement: num,
+      })
+      .collect();
+    Ok((start, pair_lst))
+  }
+}
+
+impl Hinter for DlmHelper {
+  type Hint = String;
+}
+
+impl Highlighter for DlmHelper {}
+
+impl Validator for DlmHelper {}
+
+impl Helper for DlmHelper {}
+
+// 入力の履歴を保存するファイル
+// CSVファイルと同じ場所に'<CSVファイル名>.history'という名前で置く
+pub fn history_file_name(data_file_name: &str) -> String {
+  format!("{}.history", data_file_name)
+}
+
+// 補完と履歴の付いた入力を作る
+// 履歴のファイルがまだ無い場合は空の履歴から始める
+pub fn make_editor(data_file_name: &str, config_data: &lib::ConfigData) -> Option<DlmEditor> {
+  // 候補が複数ある場合は、正式名称や団体名と一緒に一覧で表示する
+  let config = Config::builder()
+    .completion_type(CompletionType::List)
+    .build();
+  let mut editor = DlmEditor::with_config(config).ok()?;
+  editor.set_helper(Some(DlmHelper::new(config_data)));
+  let _ = editor.load_history(&history_file_name(data_file_name));
+  Some(editor)
+}
+
+#[test]
+fn check_candidate_lst() {
+  let config_data = lib::make_config_data(
+    serde_json::json!({"0001": "内リール1", "0002": "内リール2"}),
+    serde_json::json!({"12": "物理部"}),
+    serde_json::json!(null),
+  );
+  let mut helper = DlmHelper::new(&config_data);
+  let nums = |(start, lst): (usize, Vec<(String, String)>)| {
+    (
+      start,
+      lst.into_iter().map(|(num, _)| num).collect::<Vec<String>>(),
+    )
+  };
+  assert_eq!(nums(helper.candidate_lst("ba")).0, 0);
+  assert_eq!(
+    nums(helper.candidate_lst("lend 0001 ")),
+    (
+      10,
+      vec!["0001".to_string(), "0002".to_string(), "12".to_string()]
+    )
+  );
+  let time = chrono::DateTime::parse_from_rfc3339("2020-11-23T17:40:00+09:00").unwrap();
+  helper.set_lent_lst(&[lib::LendData {
+    time,
+    lend_type: lib::LendType::Lend("0002".to_string(), "12".to_string()),
+    num: 1,
+    backfilled: false,
+    overridden: false,
+    condition: None,
+    note: None,
+    operator: None,
+  }]);
+  // 返却の補完は貸出中の品名とその貸出先だけ
+  assert_eq!(
+    nums(helper.candidate_lst("r ")),
+    (2, vec!["0002".to_string(), "12".to_string()])
+  );
+  assert_eq!(
+    nums(helper.candidate_lst("lend ")),
+    (5, vec!["0001".to_string(), "12".to_string()])
+  );
+  assert_eq!(
+    nums(helper.candidate_lst("edit 3 --destination ")),
+    (21, vec!["12".to_string()])
+  );
+  assert_eq!(nums(helper.candidate_lst("edit 3 --time ")).1.len(), 0);
+}
@@ -0,0 +1,472 @@
+use crate::pdf::{write_pdf, Shape};
+use crate::{catalog, get_char_len, ConfigData};
+use std::fmt::Write;
+
+// ラベルに印刷する符号の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeType {
+  Code128,
+  Qr,
+}
+
+impl CodeType {
+  pub fn from_name(name: &str) -> Option<CodeType> {
+    match name.to_ascii_lowercase().as_str() {
+      "code128" => Some(CodeType::Code128),
+      "qr" => Some(CodeType::Qr),
+      _ => None,
+    }
+  }
+}
+
+// 出力するファイルの形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelFormat {
+  Svg,
+  Pdf,
+}
+
+impl LabelFormat {
+  // ファイル名の拡張子から形式を決める
+  pub fn from_file_name(file_name: &str) -> Option<LabelFormat> {
+    let extension = std::path::Path::new(file_name)
+      .extension()?
+      .to_str()?
+      .to_ascii_lowercase();
+    match extension.as_str() {
+      "svg" => Some(LabelFormat::Svg),
+      "pdf" => Some(LabelFormat::Pdf),
+      _ => None,
+    }
+  }
+}
+
+// 用紙の大きさとラベルの並べ方（長さの単位はmm）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LabelGrid {
+  pub paper_width: f64,
+  pub paper_height: f64,
+  pub columns: usize,
+  pub rows: usize,
+}
+
+impl Default for LabelGrid {
+  // A4用紙に3列8行（24面）
+  fn default() -> Self {
+    LabelGrid {
+      paper_width: 210.0,
+      paper_height: 297.0,
+      columns: 3,
+      rows: 8,
+    }
+  }
+}
+
+// 'A4-3x8'や'A4'、'3x8'のような文字列から用紙とラベルの並べ方を作る
+// 省略した部分はA4と3x8になる
+pub fn parse_label_grid(s: &str) -> Option<LabelGrid> {
+  let s = s.to_ascii_lowercase();
+  let (paper_opt, size_opt) = match s.split_once('-') {
+    Some((paper, size)) => (Some(paper), Some(size)),
+    None if s.contains('x') => (None, Some(s.as_str())),
+    None => (Some(s.as_str()), None),
+  };
+  let mut grid = LabelGrid::default();
+  if let Some(paper) = paper_opt {
+    let (width, height) = match paper {
+      "a3" => (297.0, 420.0),
+      "a4" => (210.0, 297.0),
+      "a5" => (148.0, 210.0),
+      "b5" => (182.0, 257.0),
+      "letter" => (215.9, 279.4),
+      _ => return None,
+    };
+    grid.paper_width = width;
+    grid.paper_height = height;
+  }
+  if let Some(size) = size_opt {
+    let (columns, rows) = size.split_once('x')?;
+    grid.columns = columns.parse().ok().filter(|n| *n > 0)?;
+    grid.rows = rows.parse().ok().filter(|n| *n > 0)?;
+  }
+  Some(grid)
+}
+
+// 'labels'で作るラベルの種類と出力の方法
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelOption {
+  // 品名のラベルを作るかどうか
+  pub items: bool,
+  // 団体のカードを作るかどうか
+  pub groups: bool,
+  pub code_type: CodeType,
+  pub grid: LabelGrid,
+  pub format: LabelFormat,
+  // 出力するファイル名
+  pub out: String,
+}
+
+impl Default for LabelOption {
+  fn default() -> Self {
+    LabelOption {
+      items: true,
+      groups: true,
+      code_type: CodeType::Code128,
+      grid: LabelGrid::default(),
+      format: LabelFormat::Pdf,
+      out: "labels.pdf".to_string(),
+    }
+  }
+}
+
+// ラベル一枚分の内容
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+  // 符号にする番号
+  pub code: String,
+  // 番号の上に書く名前
+  pub name: String,
+  // 番号の下に書く補足（参団の場所など）
+  pub note: Option<String>,
+}
+
+// 設定ファイルの"sizai"から品名のラベルを作る
+pub fn item_label_lst(config_data: &ConfigData) -> Vec<Label> {
+  catalog::item_num_lst(config_data)
+    .into_iter()
+    .map(|num| Label {
+      name: catalog::item_name(&num, config_data)
+        .unwrap_or("")
+        .to_string(),
+      code: num,
+      note: None,
+    })
+    .collect()
+}
+
+// 設定ファイルの"sandan"から団体のカードを作る
+// "room"に場所があれば一緒に書く
+pub fn group_label_lst(config_data: &ConfigData) -> Vec<Label> {
+  match config_data.sandan.as_object() {
+    None => Vec::new(),
+    Some(map) => map
+      .iter()
+      .map(|(num, name)| Label {
+        code: num.clone(),
+        name: name.as_str().unwrap_or("").to_string(),
+        note: config_data.room[num].as_str().map(|s| s.to_string()),
+      })
+      .collect(),
+  }
+}
+
+// Code128の各値に対応するバーとスペースの幅（最後は終了記号）
+const CODE128_PATTERN_LST: [&str; 107] = [
+  "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212",
+  "221213", "221312", "231212", "112232", "122132", "122231", "113222", "123122", "123221",
+  "223211", "221132", "221231", "213212", "223112", "312131", "311222", "321122", "321221",
+  "312212", "322112", "322211", "212123", "212321", "232121", "111323", "131123", "131321",
+  "112313", "132113", "132311", "211313", "231113", "231311", "112133", "112331", "132131",
+  "113123", "113321", "133121", "313121", "211331", "231131", "213113", "213311", "213131",
+  "311123", "311321", "331121", "312113", "312311", "332111", "314111", "221411", "431111",
+  "111224", "111422", "121124", "121421", "141122", "141221", "112214", "112412", "122114",
+  "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111", "111242",
+  "121142", "121241", "114212", "124112", "124211", "411212", "421112", "421211", "212141",
+  "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311",
+  "113141", "114131", "311141", "411131", "211412", "211214", "211232", "2331112",
+];
+
+const CODE128_START_B: usize = 104;
+const CODE128_STOP: usize = 106;
+
+// Code128（コードセットB）で符号化した値の列
+// 始めの記号・チェック用の値・終了記号を含む
+// ASCIIの表示できる文字以外が含まれている場合はNone
+fn code128_value_lst(code: &str) -> Option<Vec<usize>> {
+  let mut value_lst = vec![CODE128_START_B];
+  for c in code.chars() {
+    if !(' '..='~').contains(&c) {
+      return None;
+    }
+    value_lst.push(c as usize - 32);
+  }
+  let check = value_lst
+    .iter()
+    .enumerate()
+    .map(|(i, value)| i.max(1) * value)
+    .sum::<usize>()
+    % 103;
+  value_lst.push(check);
+  value_lst.push(CODE128_STOP);
+  Some(value_lst)
+}
+
+// Code128の模様を、1モジュールごとに黒ならtrueとした列にする
+pub fn code128_module_lst(code: &str) -> Option<Vec<bool>> {
+  let mut module_lst = Vec::new();
+  for value in code128_value_lst(code)? {
+    for (i, width) in CODE128_PATTERN_LST[value].chars().enumerate() {
+      let width = width.to_digit(10).unwrap_or(0) as usize;
+      module_lst.extend(std::iter::repeat_n(i % 2 == 0, width));
+    }
+  }
+  Some(module_lst)
+}
+
+// QRコードの模様を、一辺のモジュール数と、行ごとに黒ならtrueとした列にする
+fn qr_module_lst(code: &str) -> Option<(usize, Vec<bool>)> {
+  let qr = qrcode::QrCode::new(code.as_bytes()).ok()?;
+  let module_lst = qr
+    .to_colors()
+    .into_iter()
+    .map(|color| color == qrcode::Color::Dark)
+    .collect();
+  Some((qr.width(), module_lst))
+}
+
+// 符号を描く大きさの上限
+const QUIET_ZONE_CODE128: f64 = 10.0;
+const QUIET_ZONE_QR: f64 = 4.0;
+const MAX_MODULE_WIDTH_CODE128: f64 = 0.5;
+const PADDING: f64 = 2.0;
+
+// 文字列が枠に収まるような文字の大きさ
+fn fit_text_size(s: &str, width: f64, max_size: f64) -> f64 {
+  // ASCII文字は全角の半分の幅として計算する
+  let len = get_char_len(s) as f64 / 2.0;
+  if len * max_size <= width {
+    max_size
+  } else {
+    width / len
+  }
+}
+
+// 一つのラベルを、左上が(x, y)で幅w・高さhの枠に描く
+// 符号にできない番号の場合はErrで番号を返す
+fn layout_label(
+  label: &Label,
+  code_type: CodeType,
+  x: f64,
+  y: f64,
+  w: f64,
+  h: f64,
+) -> Result<Vec<Shape>, String> {
+  let mut shape_lst = Vec::new();
+  let inner_w = w - PADDING * 2.0;
+  let center = x + w / 2.0;
+  // 名前・番号・補足の文字の大きさは枠の高さから決める
+  let text_size = (h / 9.0).min(4.0);
+  let mut top = y + PADDING;
+  let mut bottom = y + h - PADDING;
+  if !label.name.is_empty() {
+    let size = fit_text_size(&label.name, inner_w, text_size);
+    top += size;
+    shape_lst.push(Shape::Text(center, top, size, label.name.clone()));
+    top += size * 0.3;
+  }
+  if let Some(note) = &label.note {
+    let size = fit_text_size(note, inner_w, text_size * 0.8);
+    shape_lst.push(Shape::Text(center, bottom, size, note.clone()));
+    bottom -= size * 1.2;
+  }
+  let code_size = fit_text_size(&label.code, inner_w, text_size * 0.8);
+  shape_lst.push(Shape::Text(center, bottom, code_size, label.code.clone()));
+  bottom -= code_size * 1.2;
+  let symbol_h = bottom - top;
+  match code_type {
+    CodeType::Code128 => {
+      let module_lst = code128_module_lst(&label.code).ok_or_else(|| label.code.clone())?;
+      let module_count = module_lst.len() as f64 + QUIET_ZONE_CODE128 * 2.0;
+      let module_w = (inner_w / module_count).min(MAX_MODULE_WIDTH_CODE128);
+      let left = center - module_w * module_lst.len() as f64 / 2.0;
+      for (start, len) in run_lst(&module_lst) {
+        shape_lst.push(Shape::Rect(
+          left + module_w * start as f64,
+          top,
+          module_w * len as f64,
+          symbol_h,
+        ))
+      }
+    }
+    CodeType::Qr => {
+      let (width, module_lst) = qr_module_lst(&label.code).ok_or_else(|| label.code.clone())?;
+      let module_count = width as f64 + QUIET_ZONE_QR * 2.0;
+      let module_w = symbol_h.min(inner_w) / module_count;
+      let left = center - module_w * width as f64 / 2.0;
+      let symbol_top = top + (symbol_h - module_w * width as f64) / 2.0;
+      for (row, line) in module_lst.chunks(width).enumerate() {
+        for (start, len) in run_lst(line) {
+          shape_lst.push(Shape::Rect(
+            left + module_w * start as f64,
+            symbol_top + module_w * row as f64,
+            module_w * len as f64,
+            module_w,
+          ))
+        }
+      }
+    }
+  }
+  Ok(shape_lst)
+}
+
+// 黒いモジュールが続く部分の(開始位置, 長さ)のリスト
+fn run_lst(module_lst: &[bool]) -> Vec<(usize, usize)> {
+  let mut lst = Vec::new();
+  let mut start_opt = None;
+  for (i, is_dark) in module_lst.iter().chain(std::iter::once(&false)).enumerate() {
+    match (start_opt, is_dark) {
+      (None, true) => start_opt = Some(i),
+      (Some(start), false) => {
+        lst.push((start, i - start));
+        start_opt = None
+      }
+      _ => (),
+    }
+  }
+  lst
+}
+
+// ラベルの一覧をページごとに並べる
+// 一覧ごとに新しいページから始めるので、品名のラベルと団体のカードが同じページに混ざらない
+fn layout_page_lst(
+  label_lst_lst: &[Vec<Label>],
+  code_type: CodeType,
+  grid: &LabelGrid,
+) -> Result<Vec<Vec<Shape>>, String> {
+  let cell_w = grid.paper_width / grid.columns as f64;
+  let cell_h = grid.paper_height / grid.rows as f64;
+  let per_page = grid.columns * grid.rows;
+  let mut page_lst = Vec::new();
+  for label_lst in label_lst_lst.iter() {
+    for chunk in label_lst.chunks(per_page) {
+      let mut page = Vec::new();
+      for (i, label) in chunk.iter().enumerate() {
+        let x = cell_w * (i % grid.columns) as f64;
+        let y = cell_h * (i / grid.columns) as f64;
+        page.extend(layout_label(label, code_type, x, y, cell_w, cell_h)?);
+      }
+      page_lst.push(page)
+    }
+  }
+  Ok(page_lst)
+}
+
+pub(crate) fn escape_xml(s: &str) -> String {
+  s.replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+// ページごとにSVGの文字列を作る
+// 符号にできない番号がある場合はErrでその番号を返す
+pub fn render_svg(
+  label_lst_lst: &[Vec<Label>],
+  code_type: CodeType,
+  grid: &LabelGrid,
+) -> Result<Vec<String>, String> {
+  let page_lst = layout_page_lst(label_lst_lst, code_type, grid)?;
+  let svg_lst = page_lst
+    .iter()
+    .map(|page| {
+      let mut s = String::new();
+      let _ = writeln!(
+        s,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}mm\" height=\"{h}mm\" viewBox=\"0 0 {w} {h}\">",
+        w = grid.paper_width,
+        h = grid.paper_height
+      );
+      for shape in page.iter() {
+        let _ = match shape {
+          Shape::Rect(x, y, w, h) => writeln!(
+            s,
+            "<rect x=\"{:.3}\" y=\"{:.3}\" width=\"{:.3}\" height=\"{:.3}\"/>",
+            x, y, w, h
+          ),
+          Shape::Text(x, y, size, text) => writeln!(
+            s,
+            "<text x=\"{:.3}\" y=\"{:.3}\" font-size=\"{:.3}\" text-anchor=\"middle\" font-family=\"sans-serif\">{}</text>",
+            x,
+            y,
+            size,
+            escape_xml(text)
+          ),
+          Shape::LeftText(x, y, size, text) => writeln!(
+            s,
+            "<text x=\"{:.3}\" y=\"{:.3}\" font-size=\"{:.3}\" font-family=\"sans-serif\">{}</text>",
+            x,
+            y,
+            size,
+            escape_xml(text)
+          ),
+        };
+      }
+      s.push_str("</svg>\n");
+      s
+    })
+    .collect();
+  Ok(svg_lst)
+}
+
+// 全てのページを一つのPDFにする
+// 符号にできない番号がある場合はErrでその番号を返す
+pub fn render_pdf(
+  label_lst_lst: &[Vec<Label>],
+  code_type: CodeType,
+  grid: &LabelGrid,
+) -> Result<Vec<u8>, String> {
+  let page_lst = layout_page_lst(label_lst_lst, code_type, grid)?;
+  Ok(write_pdf(&page_lst, grid.paper_width, grid.paper_height))
+}
+
+#[test]
+fn check_code128() {
+  // 全ての記号は11モジュール（終了記号は13モジュール）
+  for (i, pattern) in CODE128_PATTERN_LST.iter().enumerate() {
+    let width: u32 = pattern.chars().filter_map(|c| c.to_digit(10)).sum();
+    assert_eq!(width, if i == CODE128_STOP { 13 } else { 11 });
+  }
+  // 'A'のチェック用の値は(104 + 33) % 103 = 34
+  assert_eq!(
+    code128_value_lst("A"),
+    Some(vec![CODE128_START_B, 33, 34, CODE128_STOP])
+  );
+  assert_eq!(code128_module_lst("0001").unwrap().len(), 11 * 6 + 13);
+  assert_eq!(code128_module_lst("内リール"), None);
+  assert_eq!(run_lst(&[true, true, false, true]), vec![(0, 2), (3, 1)]);
+}
+
+#[test]
+fn check_parse_label_grid() {
+  assert_eq!(parse_label_grid("A4-3x8"), Some(LabelGrid::default()));
+  assert_eq!(
+    parse_label_grid("a5"),
+    Some(LabelGrid {
+      paper_width: 148.0,
+      paper_height: 210.0,
+      ..LabelGrid::default()
+    })
+  );
+  assert_eq!(
+    parse_label_grid("2x5").map(|grid| (grid.columns, grid.rows)),
+    Some((2, 5))
+  );
+  assert_eq!(parse_label_grid("A4-0x8"), None);
+  assert_eq!(parse_label_grid("B4"), None);
+  let config_data = crate::make_config_data(
+    serde_json::json!({"0001": "内リール1", "0002": "内リール2"}),
+    serde_json::json!({"12": "物理部"}),
+    serde_json::json!({"12": "1-1"}),
+  );
+  let label_lst_lst = vec![item_label_lst(&config_data), group_label_lst(&config_data)];
+  let grid = parse_label_grid("A4-1x1").unwrap();
+  // 品名のラベルと団体のカードは別のページになる
+  assert_eq!(
+    render_svg(&label_lst_lst, CodeType::Qr, &grid)
+      .unwrap()
+      .len(),
+    3
+  );
+  let pdf = render_pdf(&label_lst_lst, CodeType::Code128, &grid).unwrap();
+  assert!(pdf.starts_with(b"%PDF-1.4"));
+}
@@ -0,0 +1,936 @@
+use crate::OutstandingIndex;
+use crate::*;
+use chrono::Utc;
+use std::fmt;
+
+// 貸出と返却の記録を操作するための型
+// 二重貸出の検査や次の操作番号の決定など、操作を記録するときの決まりごとはここにまとめる
+// 読み込みや書き出しは行わないので、CSVファイル以外から作った操作のリストにも使える
+#[derive(Debug, Clone, Default)]
+pub struct Ledger {
+  state: LedgerState,
+}
+
+// 操作を記録できなかった理由
+// ライブラリはこれを返すだけで、利用者への表示は呼び出した側（対話環境など）で行う
+#[derive(Debug, Clone, PartialEq)]
+pub enum LedgerError {
+  // 未来の時刻で操作を記録しようとした
+  FutureTime(DateTime<FixedOffset>),
+  // 既に貸し出されている品を貸し出そうとした
+  AlreadyLent {
+    item: String,
+    holder: String,
+  },
+  // 貸し出されていない品を返却しようとした
+  NotLent {
+    item: String,
+  },
+  // 貸出先と返却先が一致しない
+  WrongReturner {
+    item: String,
+    expected: String,
+    actual: String,
+  },
+  // 後日入力した操作が、その時刻より後の操作と矛盾する
+  ConflictsWithLater {
+    item: String,
+    next: Box<LendData>,
+  },
+  // 存在しない操作番号を指定した
+  UnknownOperation(isize),
+  // まだ行われていない操作番号を指定した
+  FutureOperation(isize),
+  // 'edit'や'remove'で行った操作を編集しようとした
+  CannotEditEdit(isize),
+  // 取り消そうとした操作を前提とする操作が、他の端末で行われている
+  HasDependent {
+    num: isize,
+    dependent: Box<LendData>,
+  },
+  // 壊れた状態などで返されて、修理を待っている品を貸し出そうとした
+  NeedsRepair {
+    item: String,
+    returned: Box<LendData>,
+  },
+  // 修理を待っていない品を修理済みにしようとした
+  NotDamaged {
+    item: String,
+  },
+  // 紛失・使用終了した品を貸し出したり、返却したり、もう一度紛失・使用終了にしようとした
+  OutOfCirculation {
+    item: String,
+    closed: Box<LendData>,
+  },
+}
+
+impl LedgerError {
+  // エラーの種類を表す変わらない文字列
+  // 表示される文章は変わることがあるので、スクリプトなどで種類を見分けるときはこちらを使う
+  pub fn code(&self) -> &'static str {
+    match self {
+      LedgerError::FutureTime(_) => "future-time",
+      LedgerError::AlreadyLent { .. } => "already-lent",
+      LedgerError::NotLent { .. } => "not-lent",
+      LedgerError::WrongReturner { .. } => "wrong-returner",
+      LedgerError::ConflictsWithLater { .. } => "conflicts-with-later",
+      LedgerError::UnknownOperation(_) => "unknown-operation",
+      LedgerError::FutureOperation(_) => "future-operation",
+      LedgerError::CannotEditEdit(_) => "cannot-edit-edit",
+      LedgerError::HasDependent { .. } => "has-dependent",
+      LedgerError::NeedsRepair { .. } => "needs-repair",
+      LedgerError::NotDamaged { .. } => "not-damaged",
+      LedgerError::OutOfCirculation { .. } => "out-of-circulation",
+    }
+  }
+
+  // エラーに関わる品名（品名に関わらないエラーはNone）
+  pub fn item(&self) -> Option<&str> {
+    match self {
+      LedgerError::AlreadyLent { item, .. }
+      | LedgerError::NotLent { item }
+      | LedgerError::WrongReturner { item, .. }
+      | LedgerError::ConflictsWithLater { item, .. }
+      | LedgerError::NeedsRepair { item, .. }
+      | LedgerError::NotDamaged { item }
+      | LedgerError::OutOfCirculation { item, .. } => Some(item),
+      _ => None,
+    }
+  }
+}
+
+// 設定ファイルを使わない簡単な表示
+// 対話環境では団体名なども付けて表示するので、print_messageの方を使う
+impl fmt::Display for LedgerError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let s = match self {
+      LedgerError::FutureTime(time) => fill(
+        "ledger_error.future_time",
+        &[&time.format("%Y/%m/%d %H:%M")],
+      ),
+      LedgerError::AlreadyLent { item, holder } => {
+        fill("ledger_error.already_lent", &[item, holder])
+      }
+      LedgerError::NotLent { item } => fill("ledger_error.not_lent", &[item]),
+      LedgerError::WrongReturner {
+        item,
+        expected,
+        actual,
+      } => fill("ledger_error.wrong_returner", &[item, expected, actual]),
+      LedgerError::ConflictsWithLater { item, next } => {
+        fill("ledger_error.conflicts_with_later", &[item, &next.num])
+      }
+      LedgerError::UnknownOperation(num) => fill("ledger_error.unknown_operation", &[num]),
+      LedgerError::FutureOperation(num) => fill("ledger_error.future_operation", &[num]),
+      LedgerError::CannotEditEdit(num) => fill("ledger_error.cannot_edit_edit", &[num]),
+      LedgerError::HasDependent { num, dependent } => {
+        fill("ledger_error.has_dependent", &[num, &dependent.num])
+      }
+      LedgerError::NeedsRepair { item, returned } => {
+        fill("ledger_error.needs_repair", &[item, &returned.num])
+      }
+      LedgerError::NotDamaged { item } => fill("ledger_error.not_damaged", &[item]),
+      LedgerError::OutOfCirculation { item, closed } => {
+        fill("ledger_error.out_of_circulation", &[item, &closed.num])
+      }
+    };
+    write!(f, "{}", s)
+  }
+}
+
+impl std::error::Error for LedgerError {}
+
+// 日本時間での現在時刻
+fn now() -> DateTime<FixedOffset> {
+  Utc::now().with_timezone(&FixedOffset::east(9 * 3600))
+}
+
+// 貸出と返却の共通処理で、どちらを記録するか
+enum RecordKind {
+  // 制限を超えて貸し出すかどうか
+  Lend { overridden: bool },
+  // 返却されたときの品の状態
+  Return { condition: Option<ReturnCondition> },
+}
+
+impl Ledger {
+  pub fn new(lend_data_lst: Vec<LendData>) -> Self {
+    Ledger {
+      state: LedgerState::new(lend_data_lst),
+    }
+  }
+
+  // 全ての操作と貸出状況
+  pub fn state(&self) -> &LedgerState {
+    &self.state
+  }
+
+  // 全ての操作（記録された順）
+  pub fn lend_data_lst(&self) -> &[LendData] {
+    self.state.lend_data_lst()
+  }
+
+  // 現在貸し出されている貸出品のリスト（貸し出された時刻順）
+  pub fn outstanding(&self) -> Vec<LendData> {
+    self.state.now_lend_data_lst()
+  }
+
+  // 次に記録する操作の操作番号
+  pub fn next_num(&self) -> isize {
+    self.state.max_num() + 1
+  }
+
+  // その品名を貸し出している操作を探す
+  // 後日入力では、その時刻の時点での貸出状況（outstanding_at）から探す
+  // pendingはまだ記録していない、同じコマンドで先に検査を通った操作
+  // pendingの操作番号は記録済みのどの操作よりも大きく、時刻も同じなので、見つかればそれが最後の操作になる
+  fn find_lend_data_at(
+    &self,
+    pending: &[LendData],
+    product_num: &str,
+    outstanding_at: Option<&OutstandingIndex>,
+  ) -> Option<LendData> {
+    let is_product = |data: &LendData| match &data.lend_type {
+      LendType::Lend(n, _) | LendType::Return(n, _) => n == product_num,
+      _ => false,
+    };
+    match pending.iter().rev().find(|data| is_product(data)) {
+      Some(data) => match data.lend_type {
+        LendType::Lend(_, _) => Some(data.clone()),
+        _ => None,
+      },
+      None => match outstanding_at {
+        None => self.state.find_lend_data(product_num).cloned(),
+        Some(outstanding) => outstanding.find_lend_data(product_num).cloned(),
+      },
+    }
+  }
+
+  // 後日入力した操作の後に行われた、同じ品名についての最初の操作
+  // pendingの操作は後日入力した時刻と同じ時刻なので、後に行われた操作にはならない
+  fn find_next_lend_data(
+    &self,
+    product_num: &str,
+    at_opt: Option<DateTime<FixedOffset>>,
+  ) -> Option<LendData> {
+    at_opt.and_then(|at| self.state.find_next_lend_data(product_num, at).cloned())
+  }
+
+  // 貸出と返却の共通処理
+  // 全ての品について検査を通ったときだけ記録し、一つでも通らなければ何も記録しない
+  // 記録した操作の操作番号のリストを返す
+  fn record_lend_or_return(
+    &mut self,
+    product_num_lst: &[String],
+    destination_num: &str,
+    at_opt: Option<DateTime<FixedOffset>>,
+    operator: Option<&str>,
+    kind: RecordKind,
+    note: Option<&str>,
+  ) -> Result<Vec<isize>, LedgerError> {
+    let is_lend = matches!(kind, RecordKind::Lend { .. });
+    if let Some(at) = at_opt.filter(|at| *at > Utc::now()) {
+      return Err(LedgerError::FutureTime(at));
+    }
+    let time = at_opt.unwrap_or_else(now);
+    // 後日入力では、その時刻の時点での貸出状況を一度だけ作っておく
+    let outstanding_at = at_opt.map(|at| self.state.outstanding_at(at));
+    let mut pending: Vec<LendData> = Vec::new();
+    for product_num in product_num_lst.iter() {
+      // 後日入力では、その時刻の時点で紛失・使用終了していたかを調べる
+      let closed_opt = match &outstanding_at {
+        None => self.state.find_retired(product_num),
+        Some(outstanding) => outstanding.retired.get(product_num),
+      };
+      if let Some(closed) = closed_opt {
+        return Err(LedgerError::OutOfCirculation {
+          item: product_num.clone(),
+          closed: Box::new(closed.clone()),
+        });
+      }
+      let lend_data_opt = self.find_lend_data_at(&pending, product_num, outstanding_at.as_ref());
+      let next_lend_data_opt = self.find_next_lend_data(product_num, at_opt);
+      let lend_type = if is_lend {
+        if let Some(LendType::Lend(_, holder)) = lend_data_opt.map(|data| data.lend_type) {
+          return Err(LedgerError::AlreadyLent {
+            item: product_num.clone(),
+            holder,
+          });
+        }
+        // 後日入力では、その時刻の時点で修理を待っていたかを調べる
+        let returned_opt = match &outstanding_at {
+          None => self.state.find_damaged(product_num),
+          Some(outstanding) => outstanding.damaged.get(product_num),
+        };
+        if let Some(returned) = returned_opt {
+          return Err(LedgerError::NeedsRepair {
+            item: product_num.clone(),
+            returned: Box::new(returned.clone()),
+          });
+        }
+        // 後日入力した貸出の次には、同じ貸出先からの返却が来ていないとおかしい
+        let lend_type = LendType::Lend(product_num.clone(), destination_num.to_string());
+        let expected = LendType::Return(product_num.clone(), destination_num.to_string());
+        if let Some(next) = next_lend_data_opt.filter(|data| data.lend_type != expected) {
+          return Err(LedgerError::ConflictsWithLater {
+            item: product_num.clone(),
+            next: Box::new(next),
+          });
+        }
+        lend_type
+      } else {
+        match lend_data_opt.map(|data| data.lend_type) {
+          Some(LendType::Lend(_, holder)) if holder != destination_num => {
+            return Err(LedgerError::WrongReturner {
+              item: product_num.clone(),
+              expected: holder,
+              actual: destination_num.to_string(),
+            })
+          }
+          Some(_) => (),
+          None => {
+            return Err(LedgerError::NotLent {
+              item: product_num.clone(),
+            })
+          }
+        }
+        // 後日入力した返却の次には、貸出が来ていないとおかしい
+        if let Some(next) =
+          next_lend_data_opt.filter(|data| !matches!(data.lend_type, LendType::Lend(_, _)))
+        {
+          return Err(LedgerError::ConflictsWithLater {
+            item: product_num.clone(),
+            next: Box::new(next),
+          });
+        }
+        LendType::Return(product_num.clone(), destination_num.to_string())
+      };
+      let (overridden, condition) = match &kind {
+        RecordKind::Lend { overridden } => (*overridden, None),
+        RecordKind::Return { condition } => (false, condition.clone()),
+      };
+      pending.push(LendData {
+        time,
+        lend_type,
+        num: self.next_num() + pending.len() as isize,
+        backfilled: at_opt.is_some(),
+        overridden,
+        condition,
+        note: note.map(|s| s.to_string()),
+        operator: operator.map(|s| s.to_string()),
+      });
+    }
+    let num_lst = pending.iter().map(|data| data.num).collect();
+    self.state.extend(pending);
+    Ok(num_lst)
+  }
+
+  // 品を貸し出す
+  // 時刻が与えられた場合は、その時刻に行われた操作として記録する（後日入力）
+  // 団体ごとの貸出の制限は設定ファイルに基づいて呼び出し側で検査し、
+  // 制限を超えて貸し出す場合はoverriddenをtrueにして記録に残す
+  // 備考が与えられた場合は、貸し出した全ての品の記録に付ける
+  pub fn lend(
+    &mut self,
+    product_num_lst: &[String],
+    destination_num: &str,
+    at_opt: Option<DateTime<FixedOffset>>,
+    operator: Option<&str>,
+    overridden: bool,
+    note: Option<&str>,
+  ) -> Result<Vec<isize>, LedgerError> {
+    self.record_lend_or_return(
+      product_num_lst,
+      destination_num,
+      at_opt,
+      operator,
+      RecordKind::Lend { overridden },
+      note,
+    )
+  }
+
+  // 品を返却する
+  // 返されたときの品の状態を記録し、修理が必要な状態であれば'repair'するまで貸し出せなくする
+  pub fn return_items(
+    &mut self,
+    product_num_lst: &[String],
+    destination_num: &str,
+    at_opt: Option<DateTime<FixedOffset>>,
+    operator: Option<&str>,
+    condition: Option<ReturnCondition>,
+    note: Option<&str>,
+  ) -> Result<Vec<isize>, LedgerError> {
+    self.record_lend_or_return(
+      product_num_lst,
+      destination_num,
+      at_opt,
+      operator,
+      RecordKind::Return { condition },
+      note,
+    )
+  }
+
+  // 修理を待っていた品を修理済みにして、再び貸し出せるようにする
+  // 全ての品が修理を待っていたときだけ記録し、記録した操作の操作番号のリストを返す
+  pub fn repair(
+    &mut self,
+    product_num_lst: &[String],
+    operator: Option<&str>,
+  ) -> Result<Vec<isize>, LedgerError> {
+    for (i, product_num) in product_num_lst.iter().enumerate() {
+      if self.state.find_damaged(product_num).is_none()
+        || product_num_lst[..i].contains(product_num)
+      {
+        return Err(LedgerError::NotDamaged {
+          item: product_num.clone(),
+        });
+      }
+    }
+    let time = now();
+    let mut num_lst = Vec::new();
+    for product_num in product_num_lst.iter() {
+      let lend_num = self.next_num();
+      self.state.push(LendData {
+        time,
+        lend_type: LendType::Repair(product_num.clone()),
+        num: lend_num,
+        backfilled: false,
+        overridden: false,
+        condition: None,
+        note: None,
+        operator: operator.map(|s| s.to_string()),
+      });
+      num_lst.push(lend_num)
+    }
+    Ok(num_lst)
+  }
+
+  // 修理を待っている品名の、その品名が返された返却の操作（品名順）
+  pub fn damaged(&self) -> Vec<LendData> {
+    self.state.damaged_lst().into_iter().cloned().collect()
+  }
+
+  // 紛失と使用終了の共通処理
+  // 貸し出されていればその貸出先を記録して貸出を終わらせ、それ以降は貸し出せなくする
+  fn close_item(
+    &mut self,
+    product_num: &str,
+    is_lost: bool,
+    note: Option<&str>,
+    operator: Option<&str>,
+  ) -> Result<isize, LedgerError> {
+    if let Some(closed) = self.state.find_retired(product_num) {
+      return Err(LedgerError::OutOfCirculation {
+        item: product_num.to_string(),
+        closed: Box::new(closed.clone()),
+      });
+    }
+    let holder = match self.state.find_lend_data(product_num) {
+      Some(LendData {
+        lend_type: LendType::Lend(_, destination_num),
+        ..
+      }) => Some(destination_num.clone()),
+      _ => None,
+    };
+    let lend_type = if is_lost {
+      LendType::Lost(product_num.to_string(), holder)
+    } else {
+      LendType::Retire(product_num.to_string(), holder)
+    };
+    let lend_num = self.next_num();
+    self.state.push(LendData {
+      time: now(),
+      lend_type,
+      num: lend_num,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: note.map(|s| s.to_string()),
+      operator: operator.map(|s| s.to_string()),
+    });
+    Ok(lend_num)
+  }
+
+  // 品を紛失したことを記録する
+  // 貸し出されていた場合は、その貸出先が紛失したものとして記録する
+  pub fn lost(
+    &mut self,
+    product_num: &str,
+    note: Option<&str>,
+    operator: Option<&str>,
+  ) -> Result<isize, LedgerError> {
+    self.close_item(product_num, true, note, operator)
+  }
+
+  // 品を使わなくなったことを記録する
+  pub fn retire(
+    &mut self,
+    product_num: &str,
+    operator: Option<&str>,
+  ) -> Result<isize, LedgerError> {
+    self.close_item(product_num, false, None, operator)
+  }
+
+  // 紛失・使用終了した品名の、その操作（品名順）
+  pub fn retired(&self) -> Vec<LendData> {
+    self.state.retired_lst().into_iter().cloned().collect()
+  }
+
+  // 編集や削除の対象にできる操作かを調べ、その操作を返す
+  fn find_target(&self, num: isize) -> Result<&LendData, LedgerError> {
+    if num > self.state.max_num() {
+      return Err(LedgerError::FutureOperation(num));
+    }
+    self
+      .state
+      .get(num)
+      .ok_or(LedgerError::UnknownOperation(num))
+  }
+
+  // 編集する前の確認に使う
+  // 編集の対象にできる操作であれば、記録されたままの操作と、以前の編集を反映させた現在の内容を返す
+  pub fn check_edit(&self, num: isize) -> Result<(LendData, LendData), LedgerError> {
+    let data = self.find_target(num)?;
+    match data.lend_type {
+      LendType::Edit(_, _) | LendType::Remove(_) => Err(LedgerError::CannotEditEdit(num)),
+      _ => {
+        let now_data = self
+          .state
+          .get_organized(num)
+          .cloned()
+          .unwrap_or_else(|| data.clone());
+        Ok((data.clone(), now_data))
+      }
+    }
+  }
+
+  // 操作を編集する
+  // 記録した'edit'操作の操作番号を返す
+  pub fn edit(
+    &mut self,
+    num: isize,
+    edit_data: EditData,
+    operator: Option<&str>,
+    note: Option<&str>,
+  ) -> Result<isize, LedgerError> {
+    self.check_edit(num)?;
+    let lend_num = self.next_num();
+    self.state.push(LendData {
+      time: now(),
+      lend_type: LendType::Edit(num, edit_data),
+      num: lend_num,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: note.map(|s| s.to_string()),
+      operator: operator.map(|s| s.to_string()),
+    });
+    Ok(lend_num)
+  }
+
+  // 削除する前の確認に使う
+  // 削除の対象にできる操作であれば、その操作を返す
+  pub fn check_remove(&self, num: isize) -> Result<LendData, LedgerError> {
+    self.find_target(num).cloned()
+  }
+
+  // 操作を無かったことにする
+  // 記録した'remove'操作の操作番号を返す
+  pub fn remove(
+    &mut self,
+    num: isize,
+    operator: Option<&str>,
+    note: Option<&str>,
+  ) -> Result<isize, LedgerError> {
+    self.check_remove(num)?;
+    let lend_num = self.next_num();
+    self.state.push(LendData {
+      time: now(),
+      lend_type: LendType::Remove(num),
+      num: lend_num,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: note.map(|s| s.to_string()),
+      operator: operator.map(|s| s.to_string()),
+    });
+    Ok(lend_num)
+  }
+
+  // 取り消す前の確認に使う
+  // 自分で行った操作（own_num_lst）以外に、対象の操作を前提とする操作が無ければ対象の操作のリストを返す
+  pub fn check_undo(
+    &self,
+    num_lst: &[isize],
+    own_num_lst: &[isize],
+  ) -> Result<Vec<LendData>, LedgerError> {
+    let data_lst = num_lst
+      .iter()
+      .map(|num| self.find_target(*num).cloned())
+      .collect::<Result<Vec<LendData>, LedgerError>>()?;
+    match find_dependent_lend_data(&self.state, num_lst, own_num_lst) {
+      None => Ok(data_lst),
+      Some(dependent) => {
+        // 前提とされている操作を探す
+        let num = num_lst
+          .iter()
+          .cloned()
+          .find(|num| {
+            find_dependent_lend_data(&self.state, &[*num], own_num_lst).as_ref() == Some(&dependent)
+          })
+          .unwrap_or(num_lst[0]);
+        Err(LedgerError::HasDependent {
+          num,
+          dependent: Box::new(dependent),
+        })
+      }
+    }
+  }
+
+  // 'remove'操作を記録することで、自分で行った操作を取り消す
+  // 全ての操作が取り消せるときだけ記録し、記録した'remove'操作の操作番号のリストを返す
+  pub fn undo(
+    &mut self,
+    num_lst: &[isize],
+    own_num_lst: &[isize],
+    operator: Option<&str>,
+  ) -> Result<Vec<isize>, LedgerError> {
+    self.check_undo(num_lst, own_num_lst)?;
+    let mut lend_num_lst = Vec::new();
+    for num in num_lst.iter() {
+      lend_num_lst.push(self.remove(*num, operator, None)?);
+    }
+    Ok(lend_num_lst)
+  }
+
+  // 編集と削除を反映させた貸出と返却を時刻順に行い、二重貸出や貸し出していないものの返却を探す
+  // 見つかったものを見つかった順に返す
+  pub fn check(&self) -> Result<(), Vec<LedgerError>> {
+    let mut holder_map: HashMap<&str, &str> = HashMap::new();
+    // 修理を待っている品名と、その品名が返された返却の操作
+    let mut damaged_map: HashMap<&str, &LendData> = HashMap::new();
+    // 紛失・使用終了した品名と、その操作
+    let mut retired_map: HashMap<&str, &LendData> = HashMap::new();
+    let mut error_lst = Vec::new();
+    for lend_data in self.state.organized_lend_data_lst().iter() {
+      match &lend_data.lend_type {
+        LendType::Lend(product_num, destination_num) => {
+          if let Some(holder) = holder_map.insert(product_num, destination_num) {
+            error_lst.push(LedgerError::AlreadyLent {
+              item: product_num.clone(),
+              holder: holder.to_string(),
+            })
+          }
+          if let Some(returned) = damaged_map.get(product_num.as_str()) {
+            error_lst.push(LedgerError::NeedsRepair {
+              item: product_num.clone(),
+              returned: Box::new((*returned).clone()),
+            })
+          }
+          if let Some(closed) = retired_map.get(product_num.as_str()) {
+            error_lst.push(LedgerError::OutOfCirculation {
+              item: product_num.clone(),
+              closed: Box::new((*closed).clone()),
+            })
+          }
+        }
+        LendType::Return(product_num, _) => {
+          let is_lent = holder_map.remove(product_num.as_str()).is_some();
+          if !is_lent {
+            error_lst.push(LedgerError::NotLent {
+              item: product_num.clone(),
+            })
+          }
+          if lend_data
+            .condition
+            .as_ref()
+            .is_some_and(|condition| condition.kind.needs_repair())
+          {
+            damaged_map.insert(product_num, lend_data);
+          }
+        }
+        LendType::Repair(product_num) => {
+          damaged_map.remove(product_num.as_str());
+        }
+        LendType::Lost(product_num, _) | LendType::Retire(product_num, _) => {
+          holder_map.remove(product_num.as_str());
+          damaged_map.remove(product_num.as_str());
+          if let Some(closed) = retired_map.insert(product_num, lend_data) {
+            error_lst.push(LedgerError::OutOfCirculation {
+              item: product_num.clone(),
+              closed: Box::new(closed.clone()),
+            })
+          }
+        }
+        _ => (),
+      }
+    }
+    if error_lst.is_empty() {
+      Ok(())
+    } else {
+      Err(error_lst)
+    }
+  }
+}
+
+#[test]
+fn check_ledger_lend_and_return() {
+  let items = |lst: &[&str]| lst.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+  let mut ledger = Ledger::new(Vec::new());
+  assert_eq!(
+    ledger.lend(&items(&["0001", "0002"]), "12", None, None, false, None),
+    Ok(vec![1, 2])
+  );
+  // 同じコマンドの中での二重貸出も見つけ、何も記録しない
+  assert_eq!(
+    ledger.lend(&items(&["0003", "0003"]), "5", None, None, false, None),
+    Err(LedgerError::AlreadyLent {
+      item: "0003".to_string(),
+      holder: "5".to_string()
+    })
+  );
+  assert_eq!(
+    ledger.lend(&items(&["0001"]), "5", None, Some("suzuki"), false, None),
+    Err(LedgerError::AlreadyLent {
+      item: "0001".to_string(),
+      holder: "12".to_string()
+    })
+  );
+  assert_eq!(
+    ledger.return_items(&items(&["0001"]), "5", None, None, None, None),
+    Err(LedgerError::WrongReturner {
+      item: "0001".to_string(),
+      expected: "12".to_string(),
+      actual: "5".to_string()
+    })
+  );
+  assert_eq!(
+    ledger.return_items(&items(&["0001", "0001"]), "12", None, None, None, None),
+    Err(LedgerError::NotLent {
+      item: "0001".to_string()
+    })
+  );
+  assert_eq!(
+    ledger.return_items(&items(&["0001"]), "12", None, None, None, None),
+    Ok(vec![3])
+  );
+  assert_eq!(ledger.check_edit(3).map(|(data, _)| data.num), Ok(3));
+  assert_eq!(
+    ledger.edit(4, EditData::default(), None, None),
+    Err(LedgerError::FutureOperation(4))
+  );
+  assert_eq!(ledger.remove(3, None, Some("返却の記録違い")), Ok(4));
+  assert_eq!(
+    ledger.lend_data_lst()[3].note.as_deref(),
+    Some("返却の記録違い")
+  );
+  assert_eq!(
+    ledger.edit(4, EditData::default(), None, None),
+    Err(LedgerError::CannotEditEdit(4))
+  );
+  // 返却を削除したので0001は貸し出されたままになる
+  assert_eq!(
+    ledger
+      .outstanding()
+      .iter()
+      .map(|data| data.num)
+      .collect::<Vec<isize>>(),
+    vec![1, 2]
+  );
+  assert_eq!(ledger.check(), Ok(()));
+}
+
+#[test]
+fn check_ledger_undo() {
+  let items = |lst: &[&str]| lst.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+  let mut ledger = Ledger::new(Vec::new());
+  let own = ledger
+    .lend(&items(&["0001", "0002"]), "12", None, None, false, None)
+    .unwrap();
+  // 他の端末で0002が返却された
+  ledger
+    .return_items(&items(&["0002"]), "12", None, None, None, None)
+    .unwrap();
+  let err = ledger.undo(&own, &own, None).unwrap_err();
+  assert_eq!(err.code(), "has-dependent");
+  assert!(matches!(err, LedgerError::HasDependent { num: 2, .. }));
+  assert_eq!(ledger.next_num(), 4);
+  assert_eq!(ledger.undo(&[1], &own, None), Ok(vec![4]));
+  assert_eq!(
+    ledger.undo(&[9], &own, None),
+    Err(LedgerError::FutureOperation(9))
+  );
+}
+
+#[test]
+fn check_ledger_repair() {
+  let items = |lst: &[&str]| lst.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+  let mut ledger = Ledger::new(Vec::new());
+  ledger
+    .lend(&items(&["0001"]), "12", None, None, false, None)
+    .unwrap();
+  let condition = ReturnCondition {
+    kind: ConditionKind::Damaged,
+    note: Some("ケーブルの断線".to_string()),
+  };
+  assert_eq!(
+    ledger.return_items(&items(&["0001"]), "12", None, None, Some(condition), None),
+    Ok(vec![2])
+  );
+  // 修理するまでは貸し出せない
+  let err = ledger
+    .lend(&items(&["0001"]), "13", None, None, false, None)
+    .unwrap_err();
+  assert!(matches!(err, LedgerError::NeedsRepair { ref returned, .. } if returned.num == 2));
+  assert_eq!(ledger.damaged().len(), 1);
+  assert_eq!(
+    ledger.repair(&items(&["0002"]), None),
+    Err(LedgerError::NotDamaged {
+      item: "0002".to_string()
+    })
+  );
+  assert_eq!(ledger.repair(&items(&["0001"]), None), Ok(vec![3]));
+  assert!(ledger.damaged().is_empty());
+  assert_eq!(
+    ledger.lend(&items(&["0001"]), "13", None, None, false, None),
+    Ok(vec![4])
+  );
+  assert_eq!(ledger.check(), Ok(()));
+}
+
+#[test]
+fn check_ledger_lost_and_retire() {
+  let items = |lst: &[&str]| lst.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+  let mut ledger = Ledger::new(Vec::new());
+  ledger
+    .lend(&items(&["0001", "0002"]), "12", None, None, false, None)
+    .unwrap();
+  // 貸し出されていた品の紛失は、そのときの貸出先が紛失したものとして貸出を終わらせる
+  assert_eq!(ledger.lost("0001", Some("会場で紛失"), None), Ok(3));
+  assert_eq!(ledger.retire("0003", None), Ok(4));
+  assert_eq!(
+    ledger
+      .retired()
+      .iter()
+      .map(|data| data.lend_type.clone())
+      .collect::<Vec<LendType>>(),
+    vec![
+      LendType::Lost("0001".to_string(), Some("12".to_string())),
+      LendType::Retire("0003".to_string(), None)
+    ]
+  );
+  assert_eq!(
+    ledger
+      .outstanding()
+      .iter()
+      .map(|data| data.num)
+      .collect::<Vec<isize>>(),
+    vec![2]
+  );
+  let err = ledger
+    .lend(&items(&["0003"]), "13", None, None, false, None)
+    .unwrap_err();
+  assert!(matches!(err, LedgerError::OutOfCirculation { ref closed, .. } if closed.num == 4));
+  assert_eq!(
+    ledger
+      .return_items(&items(&["0001"]), "12", None, None, None, None)
+      .map_err(|err| err.code()),
+    Err("out-of-circulation")
+  );
+  assert_eq!(
+    ledger.lost("0001", None, None).map_err(|err| err.code()),
+    Err("out-of-circulation")
+  );
+  assert_eq!(ledger.check(), Ok(()));
+  // 紛失を取り消すと、再び貸し出されていることになる
+  assert_eq!(ledger.remove(3, None, None), Ok(5));
+  assert_eq!(ledger.outstanding().len(), 2);
+}
+
+#[test]
+fn check_ledger_backfill_after_lost() {
+  let items = |lst: &[&str]| lst.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+  let time = |s: &str| DateTime::parse_from_rfc3339(s).unwrap();
+  let mut ledger = Ledger::new(vec![crate::make_test_lend_data(
+    1,
+    "2020-11-23T12:00:00+09:00",
+    LendType::Lost("0001".to_string(), None),
+  )]);
+  // 紛失した後の時刻には貸し出せない
+  let err = ledger
+    .lend(
+      &items(&["0001"]),
+      "12",
+      Some(time("2020-11-23T13:00:00+09:00")),
+      None,
+      false,
+      None,
+    )
+    .unwrap_err();
+  assert!(matches!(err, LedgerError::OutOfCirculation { ref closed, .. } if closed.num == 1));
+  // 紛失する前の時刻であれば貸し出せる
+  assert_eq!(
+    ledger.lend(
+      &items(&["0001"]),
+      "12",
+      Some(time("2020-11-23T10:00:00+09:00")),
+      None,
+      false,
+      None,
+    ),
+    Ok(vec![2])
+  );
+}
+
+#[test]
+fn check_ledger_backfill_before_repair() {
+  let items = |lst: &[&str]| lst.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+  let time = |s: &str| DateTime::parse_from_rfc3339(s).unwrap();
+  let mut ledger = Ledger::new(vec![
+    crate::make_test_lend_data(
+      1,
+      "2020-11-23T09:00:00+09:00",
+      LendType::Lend("0001".to_string(), "12".to_string()),
+    ),
+    LendData {
+      condition: Some(ReturnCondition {
+        kind: ConditionKind::Damaged,
+        note: None,
+      }),
+      ..crate::make_test_lend_data(
+        2,
+        "2020-11-23T10:00:00+09:00",
+        LendType::Return("0001".to_string(), "12".to_string()),
+      )
+    },
+    crate::make_test_lend_data(
+      3,
+      "2020-11-23T12:00:00+09:00",
+      LendType::Repair("0001".to_string()),
+    ),
+  ]);
+  // 壊れて返却されてから修理されるまでの時刻には貸し出せない
+  let err = ledger
+    .lend(
+      &items(&["0001"]),
+      "13",
+      Some(time("2020-11-23T11:00:00+09:00")),
+      None,
+      false,
+      None,
+    )
+    .unwrap_err();
+  assert!(matches!(err, LedgerError::NeedsRepair { ref returned, .. } if returned.num == 2));
+  // 修理した後の時刻であれば貸し出せる
+  assert_eq!(
+    ledger.lend(
+      &items(&["0001"]),
+      "13",
+      Some(time("2020-11-23T13:00:00+09:00")),
+      None,
+      false,
+      None,
+    ),
+    Ok(vec![4])
+  );
+}
@@ -1,6 +1,20 @@
-use chrono::FixedOffset;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
 use regex::Regex;
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+pub mod catalog;
+pub mod label;
+mod ledger;
+pub mod message;
+mod pdf;
+pub mod policy;
+pub mod receipt;
+pub mod report;
+pub mod stats;
+pub mod stocktake;
+pub use ledger::{Ledger, LedgerError};
+use message::{fill, text};
 
 #[derive(Debug, Clone)]
 pub struct ConfigData {
@@ -30,10 +44,89 @@ pub enum LendType {
   Lend(String, String),
   // 返却：「返された品名」と「返却先」
   Return(String, String),
-  // 編集：「編集する操作対象に付けられた通し番号」と「編集後の品名」と「編集後の貸出先」
-  Edit(isize, String, String),
+  // 編集：「編集する操作対象に付けられた通し番号」と「編集内容」
+  Edit(isize, EditData),
   // 削除：「削除する操作対象に付けられた通し番号」
   Remove(isize),
+  // 修理：「修理が済んで再び貸し出せるようになった品名」
+  Repair(String),
+  // 紛失：「紛失した品名」と「そのときの貸出先（貸し出されていなかった場合はNone）」
+  Lost(String, Option<String>),
+  // 使用終了：「使わなくなった品名」と「そのときの貸出先（貸し出されていなかった場合はNone）」
+  Retire(String, Option<String>),
+}
+
+// 返却された品の状態の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConditionKind {
+  Ok,
+  Damaged,
+  MissingParts,
+}
+
+impl ConditionKind {
+  // 'return --condition'やCSVファイルで使う名前から決める
+  pub fn from_name(name: &str) -> Option<ConditionKind> {
+    match name.to_ascii_lowercase().as_str() {
+      "ok" => Some(ConditionKind::Ok),
+      "damaged" => Some(ConditionKind::Damaged),
+      "missing-parts" => Some(ConditionKind::MissingParts),
+      _ => None,
+    }
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      ConditionKind::Ok => "ok",
+      ConditionKind::Damaged => "damaged",
+      ConditionKind::MissingParts => "missing-parts",
+    }
+  }
+
+  // 'repair'するまで貸し出せない状態かどうか
+  pub fn needs_repair(&self) -> bool {
+    !matches!(self, ConditionKind::Ok)
+  }
+
+  pub fn to_message(&self) -> &'static str {
+    match self {
+      ConditionKind::Ok => text("condition.ok"),
+      ConditionKind::Damaged => text("condition.damaged"),
+      ConditionKind::MissingParts => text("condition.missing_parts"),
+    }
+  }
+}
+
+// 返却されたときの品の状態と、その備考
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturnCondition {
+  pub kind: ConditionKind,
+  pub note: Option<String>,
+}
+
+impl ReturnCondition {
+  // 操作の表示の末尾に付ける文字列
+  fn to_message(&self) -> String {
+    match &self.note {
+      None => fill("lend_data.condition", &[&self.kind.to_message()]),
+      Some(note) => fill(
+        "lend_data.condition_with_note",
+        &[&self.kind.to_message(), note],
+      ),
+    }
+  }
+}
+
+// 編集内容
+// 変更しない項目はNoneにしておく
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EditData {
+  // 編集後の品名
+  pub product_num: Option<String>,
+  // 編集後の貸出先
+  pub destination_num: Option<String>,
+  // 編集後の時刻
+  pub time: Option<chrono::DateTime<FixedOffset>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -44,6 +137,32 @@ pub struct LendData {
   pub lend_type: LendType,
   // 操作番号（編集や削除するときに使う）
   pub num: isize,
+  // 紙に記録していた操作などを、後から時刻を指定して入力したものかどうか
+  pub backfilled: bool,
+  // 団体ごとの貸出の制限を超えて、担当者の判断で貸し出したものかどうか
+  pub overridden: bool,
+  // 返却されたときの品の状態（返却以外と、状態を記録しなかった返却ではNone）
+  pub condition: Option<ReturnCondition>,
+  // 操作に付けた備考
+  pub note: Option<String>,
+  // 操作を行った担当者の名前（記録されていない場合はNone）
+  pub operator: Option<String>,
+}
+
+// テスト用に、操作番号と時刻（RFC 3339の文字列）と操作の種類だけを与えた操作を作る
+// 他の項目はCSVファイルの古い形式で読み込んだときと同じく、記録されていないものとする
+#[cfg(test)]
+pub(crate) fn make_test_lend_data(num: isize, time: &str, lend_type: LendType) -> LendData {
+  LendData {
+    time: DateTime::parse_from_rfc3339(time).unwrap(),
+    lend_type,
+    num,
+    backfilled: false,
+    overridden: false,
+    condition: None,
+    note: None,
+    operator: None,
+  }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -61,14 +180,15 @@ impl PartialOrd for LendData {
     match (self.clone().lend_type, other.clone().lend_type) {
       (LendType::Remove(_), LendType::Remove(_)) => Some(self.num.cmp(&other.num)),
       (LendType::Remove(_), _) => Some(Ordering::Greater),
-      (LendType::Edit(_, _, _), LendType::Edit(_, _, _)) => Some(self.num.cmp(&other.num)),
-      (LendType::Edit(_, _, _), LendType::Remove(_)) => Some(Ordering::Less),
-      (LendType::Edit(_, _, _), _) => Some(Ordering::Greater),
+      (LendType::Edit(_, _), LendType::Edit(_, _)) => Some(self.num.cmp(&other.num)),
+      (LendType::Edit(_, _), LendType::Remove(_)) => Some(Ordering::Less),
+      (LendType::Edit(_, _), _) => Some(Ordering::Greater),
       _ => Some(self.num.cmp(&other.num)),
     }
   }
 }
 
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for LendData {
   fn to_string(&self) -> String {
     let time = self.time;
@@ -77,21 +197,120 @@ impl ToString for LendData {
     let lend_type = self.clone().lend_type;
     let lend_type_str = match lend_type {
       LendType::Lend(product_num, destination_num) => {
-        format!("{}を{}へ貸出", product_num, destination_num)
+        fill("lend_data.lend", &[&product_num, &destination_num])
       }
       LendType::Return(product_num, destination_num) => {
-        format!("{}を{}が返却", product_num, destination_num)
+        fill("lend_data.return", &[&product_num, &destination_num])
       }
-      LendType::Edit(num, new_product_num, new_destination_num) => {
-        format!(
-          "{}番目の操作の品名を\"{}\"に、相手を\"{}\"に修正する",
-          num, new_product_num, new_destination_num
-        )
+      LendType::Edit(num, edit_data) => fill(
+        "lend_data.edit",
+        &[
+          &num,
+          &edit_data_to_str_lst(&edit_data, None).join(text("list_separator")),
+        ],
+      ),
+      LendType::Remove(num) => fill("lend_data.remove", &[&num]),
+      LendType::Repair(product_num) => fill("lend_data.repair", &[&product_num]),
+      LendType::Lost(product_num, None) => fill("lend_data.lost", &[&product_num]),
+      LendType::Lost(product_num, Some(holder)) => {
+        fill("lend_data.lost_by", &[&product_num, &holder])
+      }
+      LendType::Retire(product_num, None) => fill("lend_data.retire", &[&product_num]),
+      LendType::Retire(product_num, Some(holder)) => {
+        fill("lend_data.retire_from", &[&product_num, &holder])
       }
-      LendType::Remove(num) => format!("{}番目の操作を無かったことにする", num),
     };
     let num_str = format!("({})", num);
-    format!("{}  {}  \"{}\"", num_str, time_str, lend_type_str)
+    let backfilled_str = if self.backfilled {
+      text("lend_data.backfilled")
+    } else {
+      ""
+    };
+    let overridden_str = if self.overridden {
+      text("lend_data.overridden")
+    } else {
+      ""
+    };
+    let condition_str = match &self.condition {
+      None => String::new(),
+      Some(condition) => condition.to_message(),
+    };
+    let note_str = match &self.note {
+      None => String::new(),
+      Some(note) => fill("lend_data.note", &[note]),
+    };
+    let operator_str = match &self.operator {
+      None => String::new(),
+      Some(operator) => fill("lend_data.operator", &[operator]),
+    };
+    format!(
+      "{}  {}  \"{}\"{}{}{}{}{}",
+      num_str,
+      time_str,
+      lend_type_str,
+      condition_str,
+      note_str,
+      backfilled_str,
+      overridden_str,
+      operator_str
+    )
+  }
+}
+
+// 操作の内容を、品名や団体名を付けた文字列にする
+pub fn lend_type_to_message_with_config_data(
+  lend_type: &LendType,
+  config_data: &ConfigData,
+) -> String {
+  match lend_type.clone() {
+    LendType::Lend(product_num, destination_num) => fill(
+      "lend_data.lend_quoted",
+      &[
+        &product_num_to_str(&product_num, Some(config_data)),
+        &destination_num_to_str(&destination_num, Some(config_data)),
+      ],
+    ),
+    LendType::Return(product_num, destination_num) => fill(
+      "lend_data.return_quoted",
+      &[
+        &product_num_to_str(&product_num, Some(config_data)),
+        &destination_num_to_str(&destination_num, Some(config_data)),
+      ],
+    ),
+    LendType::Edit(num, edit_data) => fill(
+      "lend_data.edit",
+      &[
+        &num,
+        &edit_data_to_str_lst(&edit_data, Some(config_data)).join(text("list_separator")),
+      ],
+    ),
+    LendType::Remove(num) => fill("lend_data.remove", &[&num]),
+    LendType::Repair(product_num) => fill(
+      "lend_data.repair_quoted",
+      &[&product_num_to_str(&product_num, Some(config_data))],
+    ),
+    LendType::Lost(product_num, None) => fill(
+      "lend_data.lost_quoted",
+      &[&product_num_to_str(&product_num, Some(config_data))],
+    ),
+    LendType::Lost(product_num, Some(holder)) => fill(
+      "lend_data.lost_by_quoted",
+      &[
+        &product_num_to_str(&product_num, Some(config_data)),
+        &destination_num_to_str(&holder, Some(config_data)),
+      ],
+    ),
+    LendType::Retire(product_num, None) => fill(
+      "lend_data.retire_quoted",
+      &[&product_num_to_str(&product_num, Some(config_data))],
+    ),
+    LendType::Retire(product_num, Some(holder)) => fill(
+      "lend_data.retire_from_quoted",
+      &[
+        &product_num_to_str(&product_num, Some(config_data)),
+        &destination_num_to_str(&holder, Some(config_data)),
+      ],
+    ),
   }
 }
 
@@ -102,82 +321,160 @@ pub fn lend_data_to_message_with_config_data(
   let time = lend_data.time;
   let time_str = time.format("%Y/%m/%d %H:%M").to_string();
   let num = lend_data.num;
-  let lend_type = lend_data.clone().lend_type;
-  let lend_str = match lend_type {
-    LendType::Lend(product_num, destination_num) => {
-      let (sizai_str, sandan_str) = match (
-        config_data
-          .sizai
-          .get(&product_num)
-          .map(|j| j.as_str().unwrap()),
-        config_data
-          .sandan
-          .get(&destination_num)
-          .map(|j| j.as_str().unwrap()),
-      ) {
-        (None, None) => (String::new(), String::new()),
-        (Some(s1), None) => (format!("（{}）", s1), String::new()),
-        (None, Some(s2)) => (String::new(), format!("（{}）", s2)),
-        (Some(s1), Some(s2)) => (format!("（{}）", s1), format!("（{}）", s2)),
-      };
-      format!(
-        "\"{}{}\"を\"{}{}\"へ貸出",
-        product_num, sizai_str, destination_num, sandan_str
-      )
-    }
-    LendType::Return(product_num, destination_num) => {
-      let (sizai_str, sandan_str) = match (
-        config_data
-          .sizai
-          .get(&product_num)
-          .map(|j| j.as_str().unwrap()),
-        config_data
-          .sandan
-          .get(&destination_num)
-          .map(|j| j.as_str().unwrap()),
-      ) {
-        (None, None) => (String::new(), String::new()),
-        (Some(s1), None) => (format!("（{}）", s1), String::new()),
-        (None, Some(s2)) => (String::new(), format!("（{}）", s2)),
-        (Some(s1), Some(s2)) => (format!("（{}）", s1), format!("（{}）", s2)),
-      };
-      format!(
-        "\"{}{}\"を\"{}{}\"が返却",
-        product_num, sizai_str, destination_num, sandan_str
-      )
-    }
-    LendType::Edit(num, new_product_num, new_destination_num) => {
-      let (sizai_str, sandan_str) = match (
-        config_data
-          .sizai
-          .get(&new_product_num)
-          .map(|j| j.as_str().unwrap()),
-        config_data
-          .sandan
-          .get(&new_destination_num)
-          .map(|j| j.as_str().unwrap()),
-      ) {
-        (None, None) => (String::new(), String::new()),
-        (Some(s1), None) => (format!("（{}）", s1), String::new()),
-        (None, Some(s2)) => (String::new(), format!("（{}）", s2)),
-        (Some(s1), Some(s2)) => (format!("（{}）", s1), format!("（{}）", s2)),
-      };
-      format!(
-        "{}番目の操作の品名を\"{}{}\"に、相手を\"{}{}\"に修正する",
-        num, new_product_num, sizai_str, new_destination_num, sandan_str
-      )
-    }
-    LendType::Remove(num) => format!("{}番目の操作を無かったことにする", num),
-  };
+  let lend_str = lend_type_to_message_with_config_data(&lend_data.lend_type, config_data);
   format!(
-    "{num:>5}: {time} 「{lend_str}」",
+    "{num:>5}: {time} {lend_str}{condition}{note}{backfilled}{overridden}{operator}",
     num = format!("({})", num),
     time = time_str,
-    lend_str = lend_str
+    lend_str = fill("lend_data.quote", &[&lend_str]),
+    backfilled = if lend_data.backfilled {
+      text("lend_data.backfilled")
+    } else {
+      ""
+    },
+    condition = match &lend_data.condition {
+      None => String::new(),
+      Some(condition) => condition.to_message(),
+    },
+    note = match &lend_data.note {
+      None => String::new(),
+      Some(note) => fill("lend_data.note", &[note]),
+    },
+    overridden = if lend_data.overridden {
+      text("lend_data.overridden")
+    } else {
+      ""
+    },
+    operator = match &lend_data.operator {
+      None => String::new(),
+      Some(operator) => fill("lend_data.operator", &[operator]),
+    }
   )
 }
 
+// 'check'で見つかった問題を、品名に正式名称を付けた文字列にする
+pub fn check_error_to_message_with_config_data(
+  err: &LedgerError,
+  config_data: &ConfigData,
+) -> String {
+  match err {
+    LedgerError::AlreadyLent { item, .. } => fill(
+      "check.already_lent",
+      &[&product_num_to_str(item, Some(config_data))],
+    ),
+    LedgerError::NotLent { item } => fill(
+      "check.not_lent",
+      &[&product_num_to_str(item, Some(config_data))],
+    ),
+    LedgerError::NeedsRepair { item, returned } => fill(
+      "check.needs_repair",
+      &[&product_num_to_str(item, Some(config_data)), &returned.num],
+    ),
+    LedgerError::OutOfCirculation { item, closed } => fill(
+      "check.out_of_circulation",
+      &[&product_num_to_str(item, Some(config_data)), &closed.num],
+    ),
+    _ => err.to_string(),
+  }
+}
+
+// 品名の番号に、設定ファイルにある正式名称を付け加えた文字列を作る
+pub fn product_num_to_str(product_num: &str, config_data_opt: Option<&ConfigData>) -> String {
+  match config_data_opt.and_then(|config_data| catalog::item_name(product_num, config_data)) {
+    None => product_num.to_string(),
+    Some(s) => format!("{}（{}）", product_num, s),
+  }
+}
+
+// 貸出先の番号に、設定ファイルにある団体名を付け加えた文字列を作る
+pub fn destination_num_to_str(
+  destination_num: &str,
+  config_data_opt: Option<&ConfigData>,
+) -> String {
+  match config_data_opt.and_then(|config_data| config_data.sandan[destination_num].as_str()) {
+    None => destination_num.to_string(),
+    Some(s) => format!("{}（{}）", destination_num, s),
+  }
+}
+
+// 編集内容を「品名を"0001"に」のような、編集された項目ごとの文字列のリストにする
+fn edit_data_to_str_lst(edit_data: &EditData, config_data_opt: Option<&ConfigData>) -> Vec<String> {
+  let mut v = Vec::new();
+  if let Some(product_num) = &edit_data.product_num {
+    v.push(fill(
+      "edit_data.product",
+      &[&product_num_to_str(product_num, config_data_opt)],
+    ))
+  }
+  if let Some(destination_num) = &edit_data.destination_num {
+    v.push(fill(
+      "edit_data.destination",
+      &[&destination_num_to_str(destination_num, config_data_opt)],
+    ))
+  }
+  if let Some(time) = &edit_data.time {
+    v.push(fill("edit_data.time", &[&time.format("%Y/%m/%d %H:%M")]))
+  }
+  v
+}
+
+// 'edit'の確認の際に表示する、編集前と編集後の差分を作る
+// 編集される項目だけを「品名：編集前 → 編集後」の形で並べる
+pub fn make_edit_diff_str(
+  lend_data: &LendData,
+  edit_data: &EditData,
+  config_data: &ConfigData,
+) -> String {
+  let (product_num, destination_num) = match &lend_data.lend_type {
+    LendType::Lend(product_num, destination_num) => (product_num.clone(), destination_num.clone()),
+    LendType::Return(product_num, destination_num) => {
+      (product_num.clone(), destination_num.clone())
+    }
+    _ => (String::new(), String::new()),
+  };
+  let mut s = String::new();
+  if let Some(new_product_num) = &edit_data.product_num {
+    s.push_str(&fill(
+      "edit_diff.product",
+      &[
+        &product_num_to_str(&product_num, Some(config_data)),
+        &product_num_to_str(new_product_num, Some(config_data)),
+      ],
+    ))
+  }
+  if let Some(new_destination_num) = &edit_data.destination_num {
+    s.push_str(&fill(
+      "edit_diff.destination",
+      &[
+        &destination_num_to_str(&destination_num, Some(config_data)),
+        &destination_num_to_str(new_destination_num, Some(config_data)),
+      ],
+    ))
+  }
+  if let Some(new_time) = &edit_data.time {
+    s.push_str(&fill(
+      "edit_diff.time",
+      &[
+        &lend_data.time.format("%Y/%m/%d %H:%M"),
+        &new_time.format("%Y/%m/%d %H:%M"),
+      ],
+    ))
+  }
+  s
+}
+
+// '2020/11/23 17:40'のような日本時間での時刻の文字列を読み取る
+pub fn parse_time_str(s: &str) -> Option<DateTime<FixedOffset>> {
+  let naive_date_time = NaiveDateTime::parse_from_str(s, "%Y/%m/%d %H:%M")
+    .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y/%m/%d %H:%M:%S"))
+    .ok()?;
+  FixedOffset::east(9 * 3600)
+    .from_local_datetime(&naive_date_time)
+    .single()
+}
+
 #[test]
+#[allow(clippy::redundant_field_names)]
 fn check_sort_lend_data() {
   use chrono::Utc;
   // removeが一番前に来るかの検査
@@ -188,21 +485,41 @@ fn check_sort_lend_data() {
       time: time,
       lend_type: LendType::Lend(String::new(), String::new()),
       num: 1,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
     },
     LendData {
       time: time,
-      lend_type: LendType::Edit(1, String::new(), String::new()),
+      lend_type: LendType::Edit(1, EditData::default()),
       num: 2,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
     },
     LendData {
       time: time,
       lend_type: LendType::Remove(1),
       num: 3,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
     },
     LendData {
       time: time,
       lend_type: LendType::Lend(String::new(), String::new()),
       num: 4,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
     },
   ];
   lst.sort_by(|a, b| b.partial_cmp(a).unwrap());
@@ -211,95 +528,216 @@ fn check_sort_lend_data() {
       time: time,
       lend_type: LendType::Remove(1),
       num: 3,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
     },
     LendData {
       time: time,
-      lend_type: LendType::Edit(1, String::new(), String::new()),
+      lend_type: LendType::Edit(1, EditData::default()),
       num: 2,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
     },
     LendData {
       time: time,
       lend_type: LendType::Lend(String::new(), String::new()),
       num: 4,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
     },
     LendData {
       time: time,
       lend_type: LendType::Lend(String::new(), String::new()),
       num: 1,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
     },
   ];
   assert_eq!(lst, lst2);
 }
 
+// 操作に編集内容を反映させる
+// 編集されていない項目はそのままにする
+// EditとRemoveは編集できないのでそのまま返す
+fn apply_edit_data(data: &LendData, edit_data: &EditData) -> LendData {
+  let product_num_or = |product_num: &String| {
+    edit_data
+      .product_num
+      .clone()
+      .unwrap_or_else(|| product_num.clone())
+  };
+  let destination_num_or = |destination_num: &String| {
+    edit_data
+      .destination_num
+      .clone()
+      .unwrap_or_else(|| destination_num.clone())
+  };
+  let lend_type = match &data.lend_type {
+    LendType::Lend(product_num, destination_num) => LendType::Lend(
+      product_num_or(product_num),
+      destination_num_or(destination_num),
+    ),
+    LendType::Return(product_num, destination_num) => LendType::Return(
+      product_num_or(product_num),
+      destination_num_or(destination_num),
+    ),
+    LendType::Repair(product_num) => LendType::Repair(product_num_or(product_num)),
+    LendType::Lost(product_num, holder) => LendType::Lost(
+      product_num_or(product_num),
+      edit_data.destination_num.clone().or_else(|| holder.clone()),
+    ),
+    LendType::Retire(product_num, holder) => LendType::Retire(
+      product_num_or(product_num),
+      edit_data.destination_num.clone().or_else(|| holder.clone()),
+    ),
+    LendType::Edit(_, _) | LendType::Remove(_) => return data.clone(),
+  };
+  LendData {
+    time: edit_data.time.unwrap_or(data.time),
+    lend_type,
+    num: data.num,
+    backfilled: data.backfilled,
+    overridden: data.overridden,
+    condition: data.condition.clone(),
+    note: data.note.clone(),
+    operator: data.operator.clone(),
+  }
+}
+
 // データからremoveやeditを反映させ、綺麗なデータを作る
 pub fn organize_lend_data(lend_data_lst: &[LendData]) -> Vec<LendData> {
-  let mut sort_lend_data_lst = lend_data_lst.to_owned();
-  // 大きい順に並び変えることで、removeとeditを先にし、最初に処理を行う
-  sort_lend_data_lst.sort_by(|a, b| b.partial_cmp(a).unwrap());
-  loop {
-    // 先頭がRemoveでもEditでも、自分自身を削除して
-    // sort_lend_data_lstを更新するので、常に先頭を取り続けて良い
-    let next = sort_lend_data_lst.get(0);
-    match next {
-      None => break,
-      Some(lend_data) => {
-        let next_id = lend_data.clone().num;
-        match lend_data.clone().lend_type {
-          LendType::Remove(num) => {
-            // 番号が一致するデータ以外のリストを作成し、上書きする
-            let new_sort_lend_data_lst: Vec<LendData> = sort_lend_data_lst
-              .iter()
-              // remove自身も削除する
-              .filter(|data| !(data.num == num || data.num == next_id))
-              .cloned()
-              .collect();
-            sort_lend_data_lst = new_sort_lend_data_lst;
-          }
-          LendType::Edit(num, new_product_num, new_destination_num_opt) => {
-            // 番号が一致するデータを上書きする
-            let new_sort_lend_data_lst: Vec<LendData> = sort_lend_data_lst
-              .iter()
-              .map(|data| {
-                if data.num == num {
-                  match data.lend_type {
-                    LendType::Lend(_, _) => LendData {
-                      time: data.time,
-                      num: data.num,
-                      lend_type: LendType::Lend(
-                        new_product_num.clone(),
-                        new_destination_num_opt.clone(),
-                      ),
-                    },
-                    LendType::Return(_, _) => LendData {
-                      time: data.time,
-                      num: data.num,
-                      lend_type: LendType::Return(
-                        new_product_num.clone(),
-                        new_destination_num_opt.clone(),
-                      ),
-                    },
-                    LendType::Edit(_, _, _) => data.clone(),
-                    LendType::Remove(_) => data.clone(),
-                  }
-                } else {
-                  data.clone()
-                }
-              })
-              // 自分自身を削除する
-              .filter(|data| data.num != next_id)
-              .collect();
-            sort_lend_data_lst = new_sort_lend_data_lst;
-          }
-          // RemoveとEditが先に並んでいるはずなので、どちらかに到達したらその時点で終了しても大丈夫
-          LendType::Lend(_, _) => break,
-          LendType::Return(_, _) => break,
-        }
+  // Removeは新しいものから反映させる
+  // 新しいRemoveによって削除されたRemoveは反映させないので、削除された操作番号を記録しながら進める
+  let mut remove_lst: Vec<&LendData> = lend_data_lst
+    .iter()
+    .filter(|data| matches!(data.lend_type, LendType::Remove(_)))
+    .collect();
+  remove_lst.sort_by_key(|data| std::cmp::Reverse(data.num));
+  let mut removed_num_set: HashSet<isize> = HashSet::new();
+  for data in remove_lst.iter() {
+    if let LendType::Remove(num) = data.lend_type {
+      if !removed_num_set.contains(&data.num) {
+        removed_num_set.insert(num);
       }
     }
   }
-  // 操作番号が大きい（後に行った）ものが前になるように並べていたので、反転して通常に戻す
-  sort_lend_data_lst.reverse();
-  sort_lend_data_lst
+  // 削除されなかった貸出と返却を取り出し、操作番号から位置を引けるようにしておく
+  let mut organized_lst: Vec<LendData> = lend_data_lst
+    .iter()
+    .filter(|data| {
+      matches!(
+        data.lend_type,
+        LendType::Lend(_, _)
+          | LendType::Return(_, _)
+          | LendType::Repair(_)
+          | LendType::Lost(_, _)
+          | LendType::Retire(_, _)
+      )
+    })
+    .filter(|data| !removed_num_set.contains(&data.num))
+    .cloned()
+    .collect();
+  let index: HashMap<isize, usize> = organized_lst
+    .iter()
+    .enumerate()
+    .map(|(i, data)| (data.num, i))
+    .collect();
+  // Editは古いものから順に反映させる
+  let mut edit_lst: Vec<&LendData> = lend_data_lst
+    .iter()
+    .filter(|data| matches!(data.lend_type, LendType::Edit(_, _)))
+    .filter(|data| !removed_num_set.contains(&data.num))
+    .collect();
+  edit_lst.sort_by_key(|data| data.num);
+  for data in edit_lst.iter() {
+    if let LendType::Edit(num, edit_data) = &data.lend_type {
+      if let Some(i) = index.get(num) {
+        organized_lst[*i] = apply_edit_data(&organized_lst[*i], edit_data);
+      }
+    }
+  }
+  // 後日入力された操作や時刻を編集された操作があるので、
+  // 操作番号順ではなく時刻順（同じ時刻なら操作番号順）に並べ直す
+  organized_lst.sort_by_key(|data| (data.time, data.num));
+  organized_lst
+}
+
+#[test]
+fn check_organize_lend_data_edit() {
+  let time = parse_time_str("2020/11/23 17:40").unwrap();
+  let new_time = parse_time_str("2020/11/23 17:30").unwrap();
+  let lst = vec![
+    LendData {
+      time,
+      lend_type: LendType::Lend("0001".to_string(), "1".to_string()),
+      num: 1,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
+    },
+    LendData {
+      time,
+      lend_type: LendType::Edit(
+        1,
+        EditData {
+          product_num: Some("0002".to_string()),
+          destination_num: Some("2".to_string()),
+          time: None,
+        },
+      ),
+      num: 2,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
+    },
+    LendData {
+      time,
+      lend_type: LendType::Edit(
+        1,
+        EditData {
+          product_num: None,
+          destination_num: Some("3".to_string()),
+          time: Some(new_time),
+        },
+      ),
+      num: 3,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
+    },
+  ];
+  // 編集は古いものから順に反映され、編集されていない項目は前の編集の内容が残る
+  assert_eq!(
+    organize_lend_data(&lst),
+    vec![LendData {
+      time: new_time,
+      lend_type: LendType::Lend("0002".to_string(), "3".to_string()),
+      num: 1,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
+    }]
+  );
 }
 
 // データにeditやremoveを適用し、
@@ -307,33 +745,514 @@ pub fn organize_lend_data(lend_data_lst: &[LendData]) -> Vec<LendData> {
 // 「現在貸し出されている貸出品」のリストを作り出す
 // もし、重複貸し出しや重複返却があったらNoneを返す
 pub fn make_now_lend_data_lst(lend_data_lst: &[LendData]) -> Vec<LendData> {
-  let mut lend_data_lst = organize_lend_data(lend_data_lst);
-  // 操作番号が小さい方が最初になるように並び替える
-  lend_data_lst.sort_by(|a, b| a.num.cmp(&b.num));
-  // 貸したものを登録し、返却があったら削除する
-  // (時間, 品名番号, Option<貸出先番号>, 操作番号)
-  let mut lend_vec: Vec<LendData> = Vec::new();
-  for lend_data in lend_data_lst.iter() {
-    let lend_type = &lend_data.lend_type;
-    match lend_type {
-      LendType::Lend(_, _) => lend_vec.push(lend_data.clone()),
-      // 貸出品の番号が一致していたら削除
-      // filterを使うので、「一致していたらfalseを返す」ようにしないといけない
+  replay_lend_data(&organize_lend_data(lend_data_lst))
+}
+
+// 「指定した時刻の時点で貸し出されていた貸出品」のリストを作り出す
+// 後日入力の際に、その時刻での状態と矛盾しないかを調べるのに使う
+pub fn make_lend_data_lst_at(
+  lend_data_lst: &[LendData],
+  time: DateTime<FixedOffset>,
+) -> Vec<LendData> {
+  let lend_data_lst: Vec<LendData> = organize_lend_data(lend_data_lst)
+    .into_iter()
+    .filter(|data| data.time <= time)
+    .collect();
+  replay_lend_data(&lend_data_lst)
+}
+
+// 貸し出されている貸出品の索引
+// 貸出の操作を(時刻, 操作番号)の順に並べて持ち、品名と貸出先からも引けるようにしておく
+// 壊れた状態などで返されて、修理を待っている品名や、紛失・使用終了した品名もここで管理する
+#[derive(Debug, Clone, Default)]
+struct OutstandingIndex {
+  lend: BTreeMap<(DateTime<FixedOffset>, isize), LendData>,
+  by_product: HashMap<String, Vec<(DateTime<FixedOffset>, isize)>>,
+  by_destination: HashMap<String, BTreeSet<(DateTime<FixedOffset>, isize)>>,
+  // 修理を待っている品名と、その品名が返された返却の操作
+  damaged: BTreeMap<String, LendData>,
+  // 紛失・使用終了した品名と、その操作
+  retired: BTreeMap<String, LendData>,
+}
+
+impl OutstandingIndex {
+  // 貸出と返却を一つ反映させる
+  // editとremoveは反映し終わっているはずなので考慮しない
+  fn apply(&mut self, lend_data: &LendData) {
+    let key = (lend_data.time, lend_data.num);
+    match &lend_data.lend_type {
+      LendType::Lend(product_num, destination_num) => {
+        self.lend.insert(key, lend_data.clone());
+        self
+          .by_product
+          .entry(product_num.clone())
+          .or_default()
+          .push(key);
+        self
+          .by_destination
+          .entry(destination_num.clone())
+          .or_default()
+          .insert(key);
+      }
       LendType::Return(product_num, _) => {
-        lend_vec = lend_vec
-          .iter()
-          .filter(|data| match &data.lend_type {
-            LendType::Lend(lend_product_num, _) => lend_product_num != product_num,
-            _ => true,
-          })
-          .cloned()
-          .collect();
-      }
-      // editとremoveは反映し終わっているはずなので考慮しない
+        if lend_data
+          .condition
+          .as_ref()
+          .is_some_and(|condition| condition.kind.needs_repair())
+        {
+          self.damaged.insert(product_num.clone(), lend_data.clone());
+        }
+        self.remove_lend(product_num)
+      }
+      LendType::Repair(product_num) => {
+        self.damaged.remove(product_num);
+      }
+      // 貸し出されていれば貸出を終わらせ、それ以降は貸し出せなくする
+      LendType::Lost(product_num, _) | LendType::Retire(product_num, _) => {
+        self.remove_lend(product_num);
+        self.damaged.remove(product_num);
+        self.retired.insert(product_num.clone(), lend_data.clone());
+      }
       _ => (),
     }
   }
-  lend_vec
+
+  // その品名が貸し出されていれば、その貸出の操作を返す
+  fn find_lend_data(&self, product_num: &str) -> Option<&LendData> {
+    self
+      .by_product
+      .get(product_num)
+      .and_then(|key_lst| key_lst.first())
+      .and_then(|key| self.lend.get(key))
+  }
+
+  // 貸出品の番号が一致する貸出を全て削除
+  fn remove_lend(&mut self, product_num: &str) {
+    for key in self.by_product.remove(product_num).unwrap_or_default() {
+      if let Some(LendData {
+        lend_type: LendType::Lend(_, destination_num),
+        ..
+      }) = self.lend.remove(&key)
+      {
+        if let Some(set) = self.by_destination.get_mut(&destination_num) {
+          set.remove(&key);
+          if set.is_empty() {
+            self.by_destination.remove(&destination_num);
+          }
+        }
+      }
+    }
+  }
+}
+
+// editとremoveを反映し終わって時刻順に並んだデータについて、
+// 貸出と返却を実行して貸し出されている貸出品のリストを作る
+fn replay_lend_data(lend_data_lst: &[LendData]) -> Vec<LendData> {
+  let mut outstanding = OutstandingIndex::default();
+  for lend_data in lend_data_lst.iter() {
+    outstanding.apply(lend_data)
+  }
+  outstanding.lend.into_values().collect()
+}
+
+// CSVファイルから読み込んだ全ての操作と、それを反映させた貸出状況をまとめたもの
+// 一度作った後は、操作を追加するたびに差分だけを反映させる
+// 時刻順で最後になる貸出と返却の追加であれば、全体を計算し直さずに済む
+#[derive(Debug, Clone, Default)]
+pub struct LedgerState {
+  // 全ての操作（CSVファイルに書かれている順）
+  lend_data_lst: Vec<LendData>,
+  // 操作番号からlend_data_lstの位置を引くための索引
+  index: HashMap<isize, usize>,
+  // editとremoveを反映させて時刻順に並べた貸出と返却
+  organized_lst: Vec<LendData>,
+  // 操作番号からorganized_lstの位置を引くための索引
+  organized_index: HashMap<isize, usize>,
+  // 品名からorganized_lstの位置（時刻順）を引くための索引
+  organized_by_product: HashMap<String, Vec<usize>>,
+  // 現在貸し出されている貸出品
+  outstanding: OutstandingIndex,
+}
+
+// 貸出や返却などの、品名を一つ扱う操作の品名
+fn product_num_of(lend_type: &LendType) -> Option<&String> {
+  match lend_type {
+    LendType::Lend(product_num, _)
+    | LendType::Return(product_num, _)
+    | LendType::Repair(product_num)
+    | LendType::Lost(product_num, _)
+    | LendType::Retire(product_num, _) => Some(product_num),
+    LendType::Edit(_, _) | LendType::Remove(_) => None,
+  }
+}
+
+impl LedgerState {
+  pub fn new(lend_data_lst: Vec<LendData>) -> Self {
+    let mut state = LedgerState {
+      index: lend_data_lst
+        .iter()
+        .enumerate()
+        .map(|(i, data)| (data.num, i))
+        .collect(),
+      lend_data_lst,
+      ..Default::default()
+    };
+    state.rebuild();
+    state
+  }
+
+  // editとremoveを含む操作が追加されたときなどに、貸出状況を全て計算し直す
+  fn rebuild(&mut self) {
+    self.organized_lst = organize_lend_data(&self.lend_data_lst);
+    self.reindex();
+  }
+
+  // organized_lstから索引と貸出状況を作り直す
+  fn reindex(&mut self) {
+    self.organized_index = HashMap::new();
+    self.organized_by_product = HashMap::new();
+    self.outstanding = OutstandingIndex::default();
+    for i in 0..self.organized_lst.len() {
+      self.add_organized_index(i);
+      self.outstanding.apply(&self.organized_lst[i])
+    }
+  }
+
+  // organized_lstのi番目を索引に加える
+  fn add_organized_index(&mut self, i: usize) {
+    let lend_data = &self.organized_lst[i];
+    self.organized_index.insert(lend_data.num, i);
+    if let Some(product_num) = product_num_of(&lend_data.lend_type) {
+      self
+        .organized_by_product
+        .entry(product_num.clone())
+        .or_default()
+        .push(i);
+    }
+  }
+
+  // 操作を一つ追加する
+  pub fn push(&mut self, lend_data: LendData) {
+    self.extend(vec![lend_data])
+  }
+
+  // 操作をまとめて追加する
+  // 時刻順で最後になる貸出と返却は、そのまま末尾に反映させる
+  // 後日入力された貸出と返却は、まだ編集も削除もされていないので、時刻順の位置に差し込んで索引だけを作り直す
+  // editとremoveは途中の状態を変えるので、全て追加してから一度だけ計算し直す
+  pub fn extend(&mut self, lend_data_lst: Vec<LendData>) {
+    let mut needs_reindex = false;
+    let mut needs_rebuild = false;
+    for lend_data in lend_data_lst.into_iter() {
+      self.index.insert(lend_data.num, self.lend_data_lst.len());
+      self.lend_data_lst.push(lend_data.clone());
+      if needs_rebuild {
+        continue;
+      }
+      let key = (lend_data.time, lend_data.num);
+      let is_last = self
+        .organized_lst
+        .last()
+        .is_none_or(|last| (last.time, last.num) <= key);
+      match lend_data.lend_type {
+        LendType::Edit(_, _) | LendType::Remove(_) => needs_rebuild = true,
+        _ if is_last && !needs_reindex => {
+          self.outstanding.apply(&lend_data);
+          self.organized_lst.push(lend_data);
+          self.add_organized_index(self.organized_lst.len() - 1);
+        }
+        _ => {
+          let i = self
+            .organized_lst
+            .partition_point(|data| (data.time, data.num) <= key);
+          self.organized_lst.insert(i, lend_data);
+          needs_reindex = true
+        }
+      }
+    }
+    if needs_rebuild {
+      self.rebuild()
+    } else if needs_reindex {
+      self.reindex()
+    }
+  }
+
+  // 全ての操作
+  pub fn lend_data_lst(&self) -> &[LendData] {
+    &self.lend_data_lst
+  }
+
+  // editとremoveを反映させて時刻順に並べた貸出と返却
+  pub fn organized_lend_data_lst(&self) -> &[LendData] {
+    &self.organized_lst
+  }
+
+  // 操作番号から操作を取り出す
+  pub fn get(&self, num: isize) -> Option<&LendData> {
+    self.index.get(&num).map(|i| &self.lend_data_lst[*i])
+  }
+
+  // 操作番号から、editを反映させた貸出と返却を取り出す
+  pub fn get_organized(&self, num: isize) -> Option<&LendData> {
+    self
+      .organized_index
+      .get(&num)
+      .map(|i| &self.organized_lst[*i])
+  }
+
+  // 操作番号の操作が関わっている品名のリストを作る
+  // editの場合は編集前と編集後の品名の両方を、removeの場合は削除対象の操作が関わる品名を返す
+  pub fn related_product_num_lst(&self, num: isize) -> Vec<String> {
+    match self.get(num).map(|data| &data.lend_type) {
+      None => Vec::new(),
+      Some(LendType::Edit(num, edit_data)) => {
+        let mut v = self.related_product_num_lst(*num);
+        if let Some(new_product_num) = &edit_data.product_num {
+          v.push(new_product_num.clone())
+        }
+        v
+      }
+      Some(LendType::Remove(num)) => self.related_product_num_lst(*num),
+      Some(lend_type) => product_num_of(lend_type).cloned().into_iter().collect(),
+    }
+  }
+
+  // 指定した時刻の時点で貸し出されていた貸出品の索引
+  // organized_lstは時刻順に並んでいるので、その時刻までの操作だけを反映させればよい
+  fn outstanding_at(&self, time: DateTime<FixedOffset>) -> OutstandingIndex {
+    let mut outstanding = OutstandingIndex::default();
+    for lend_data in self
+      .organized_lst
+      .iter()
+      .take_while(|data| data.time <= time)
+    {
+      outstanding.apply(lend_data)
+    }
+    outstanding
+  }
+
+  // 指定した時刻より後に行われた、その品名についての最初の貸出か返却を探す
+  pub fn find_next_lend_data(
+    &self,
+    product_num: &str,
+    time: DateTime<FixedOffset>,
+  ) -> Option<&LendData> {
+    let position_lst = self.organized_by_product.get(product_num)?;
+    let start = position_lst.partition_point(|i| self.organized_lst[*i].time <= time);
+    position_lst[start..]
+      .iter()
+      .map(|i| &self.organized_lst[*i])
+      .find(|data| {
+        matches!(
+          data.lend_type,
+          LendType::Lend(_, _) | LendType::Return(_, _)
+        )
+      })
+  }
+
+  // 最大の操作番号（操作が無ければ0）
+  pub fn max_num(&self) -> isize {
+    self.index.keys().max().cloned().unwrap_or(0)
+  }
+
+  // 現在貸し出されている貸出品のリスト（貸し出された時刻順）
+  pub fn now_lend_data_lst(&self) -> Vec<LendData> {
+    self.outstanding.lend.values().cloned().collect()
+  }
+
+  // その品名が現在貸し出されていれば、その貸出の操作を返す
+  pub fn find_lend_data(&self, product_num: &str) -> Option<&LendData> {
+    self.outstanding.find_lend_data(product_num)
+  }
+
+  // 修理を待っている品名の、その品名が返された返却の操作（品名順）
+  pub fn damaged_lst(&self) -> Vec<&LendData> {
+    self.outstanding.damaged.values().collect()
+  }
+
+  // その品名が修理を待っていれば、その品名が返された返却の操作を返す
+  pub fn find_damaged(&self, product_num: &str) -> Option<&LendData> {
+    self.outstanding.damaged.get(product_num)
+  }
+
+  // 紛失・使用終了した品名の、その操作（品名順）
+  pub fn retired_lst(&self) -> Vec<&LendData> {
+    self.outstanding.retired.values().collect()
+  }
+
+  // その品名が紛失・使用終了していれば、その操作を返す
+  pub fn find_retired(&self, product_num: &str) -> Option<&LendData> {
+    self.outstanding.retired.get(product_num)
+  }
+
+  // その貸出先に現在貸し出されている貸出品のリスト（貸し出された時刻順）
+  pub fn now_lend_data_lst_by_destination(&self, destination_num: &str) -> Vec<&LendData> {
+    match self.outstanding.by_destination.get(destination_num) {
+      None => Vec::new(),
+      Some(key_lst) => key_lst
+        .iter()
+        .filter_map(|key| self.outstanding.lend.get(key))
+        .collect(),
+    }
+  }
+}
+
+#[test]
+fn check_ledger_state_push() {
+  let data = |lend_type, time: &str, num| LendData {
+    time: parse_time_str(time).unwrap(),
+    lend_type,
+    num,
+    backfilled: false,
+    overridden: false,
+    condition: None,
+    note: None,
+    operator: None,
+  };
+  let lend = |p: &str, d: &str| LendType::Lend(p.to_string(), d.to_string());
+  let ret = |p: &str, d: &str| LendType::Return(p.to_string(), d.to_string());
+  let mut state = LedgerState::new(vec![data(lend("0001", "1"), "2020/11/23 12:00", 1)]);
+  let mut lst = state.lend_data_lst().to_vec();
+  let push_lst = vec![
+    data(lend("0002", "1"), "2020/11/23 12:10", 2),
+    data(lend("0003", "2"), "2020/11/23 12:20", 3),
+    data(ret("0001", "1"), "2020/11/23 12:30", 4),
+    // 後日入力された、時刻が前の返却
+    data(ret("0002", "1"), "2020/11/23 12:15", 5),
+    data(LendType::Remove(4), "2020/11/23 12:40", 6),
+    data(
+      LendType::Edit(
+        3,
+        EditData {
+          product_num: None,
+          destination_num: Some("1".to_string()),
+          time: None,
+        },
+      ),
+      "2020/11/23 12:50",
+      7,
+    ),
+  ];
+  // まとめて追加したものとも一致する
+  let mut extended_state = state.clone();
+  extended_state.extend(push_lst.clone());
+  // 一つずつ追加したものと、まとめて計算し直したものが一致する
+  for lend_data in push_lst.into_iter() {
+    state.push(lend_data.clone());
+    lst.push(lend_data);
+    assert_eq!(state.now_lend_data_lst(), make_now_lend_data_lst(&lst));
+  }
+  assert_eq!(
+    extended_state.organized_lend_data_lst(),
+    organize_lend_data(&lst).as_slice()
+  );
+  assert_eq!(
+    extended_state.now_lend_data_lst(),
+    state.now_lend_data_lst()
+  );
+  assert_eq!(state.max_num(), 7);
+  assert_eq!(state.find_lend_data("0001").map(|data| data.num), Some(1));
+  assert_eq!(state.find_lend_data("0002"), None);
+  assert_eq!(
+    state
+      .now_lend_data_lst_by_destination("1")
+      .iter()
+      .map(|data| data.num)
+      .collect::<Vec<isize>>(),
+    vec![1, 3]
+  );
+}
+
+// 指定した時刻より後に行われた、その品名についての最初の貸出か返却を探す
+// 後日入力した操作が、その後の操作と矛盾しないかを調べるのに使う
+pub fn find_next_lend_data(
+  lend_data_lst: &[LendData],
+  product_num: &str,
+  time: DateTime<FixedOffset>,
+) -> Option<LendData> {
+  organize_lend_data(lend_data_lst)
+    .into_iter()
+    .filter(|data| data.time > time)
+    .find(|data| match &data.lend_type {
+      LendType::Lend(n, _) | LendType::Return(n, _) => n == product_num,
+      _ => false,
+    })
+}
+
+#[test]
+fn check_make_lend_data_lst_at() {
+  let lend = |product_num: &str, time: &str, num| LendData {
+    time: parse_time_str(time).unwrap(),
+    lend_type: LendType::Lend(product_num.to_string(), "1".to_string()),
+    num,
+    backfilled: false,
+    overridden: false,
+    condition: None,
+    note: None,
+    operator: None,
+  };
+  let lst = vec![
+    lend("0001", "2020/11/23 12:00", 1),
+    LendData {
+      time: parse_time_str("2020/11/23 13:00").unwrap(),
+      lend_type: LendType::Return("0001".to_string(), "1".to_string()),
+      num: 2,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
+    },
+    // 後から入力された、操作番号は大きいが時刻は前の貸出
+    LendData {
+      backfilled: true,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
+      ..lend("0002", "2020/11/23 10:00", 3)
+    },
+  ];
+  let at = |time| {
+    make_lend_data_lst_at(&lst, parse_time_str(time).unwrap())
+      .iter()
+      .map(|data| data.num)
+      .collect::<Vec<isize>>()
+  };
+  assert_eq!(at("2020/11/23 09:00"), Vec::<isize>::new());
+  assert_eq!(at("2020/11/23 12:30"), vec![3, 1]);
+  assert_eq!(at("2020/11/23 14:00"), vec![3]);
+  assert_eq!(
+    find_next_lend_data(&lst, "0001", parse_time_str("2020/11/23 11:00").unwrap())
+      .map(|data| data.num),
+    Some(1)
+  );
+  // 索引から引いたものと、全体から計算したものが一致する
+  let state = LedgerState::new(lst.clone());
+  for time in [
+    "2020/11/23 09:00",
+    "2020/11/23 12:00",
+    "2020/11/23 12:30",
+    "2020/11/23 14:00",
+  ]
+  .iter()
+  {
+    let time = parse_time_str(time).unwrap();
+    assert_eq!(
+      state
+        .outstanding_at(time)
+        .lend
+        .into_values()
+        .collect::<Vec<LendData>>(),
+      make_lend_data_lst_at(&lst, time)
+    );
+    for product_num in ["0001", "0002"].iter() {
+      assert_eq!(
+        state.find_next_lend_data(product_num, time).cloned(),
+        find_next_lend_data(&lst, product_num, time)
+      );
+    }
+  }
 }
 
 // 操作番号から操作番号の種類を取り出す
@@ -341,8 +1260,275 @@ pub fn get_lend_data(lend_data: &[LendData], n: isize) -> Option<LendData> {
   lend_data.iter().find(|data| data.num == n).cloned()
 }
 
+// 取り消そうとしている操作の上に、このセッション以外（他の端末）で行われた操作が積み重なっていないかを調べる
+// 取り消す対象より後に行われた他の端末での操作のうち、
+// 対象を編集・削除したものか、対象と同じ品名を扱ったものがあれば最初の一つを返す
+// 品名は操作番号の索引から引くので、全体を一度たどるだけで済む
+pub fn find_dependent_lend_data(
+  state: &LedgerState,
+  target_num_lst: &[isize],
+  session_num_lst: &[isize],
+) -> Option<LendData> {
+  let session_num_set: HashSet<isize> = session_num_lst.iter().cloned().collect();
+  let target_lst: Vec<(isize, Vec<String>)> = target_num_lst
+    .iter()
+    .map(|num| (*num, state.related_product_num_lst(*num)))
+    .collect();
+  state
+    .lend_data_lst()
+    .iter()
+    .filter(|data| !session_num_set.contains(&data.num))
+    .find(|data| {
+      let mut product_num_lst_opt = None;
+      target_lst
+        .iter()
+        .any(|(target_num, target_product_num_lst)| {
+          if data.num <= *target_num {
+            return false;
+          }
+          match &data.lend_type {
+            LendType::Edit(num, _) | LendType::Remove(num) if num == target_num => true,
+            _ => product_num_lst_opt
+              .get_or_insert_with(|| state.related_product_num_lst(data.num))
+              .iter()
+              .any(|product_num| target_product_num_lst.contains(product_num)),
+          }
+        })
+    })
+    .cloned()
+}
+
+#[test]
+fn check_find_dependent_lend_data() {
+  use chrono::Utc;
+  let time = Utc::now().with_timezone(&FixedOffset::east(9 * 3600));
+  let lst = vec![
+    LendData {
+      time,
+      lend_type: LendType::Lend("0001".to_string(), "1".to_string()),
+      num: 1,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
+    },
+    LendData {
+      time,
+      lend_type: LendType::Lend("0002".to_string(), "1".to_string()),
+      num: 2,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
+    },
+    LendData {
+      time,
+      lend_type: LendType::Return("0001".to_string(), "1".to_string()),
+      num: 3,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
+    },
+    LendData {
+      time,
+      lend_type: LendType::Remove(2),
+      num: 4,
+      backfilled: false,
+      overridden: false,
+      condition: None,
+      note: None,
+      operator: None,
+    },
+  ];
+  let lst = LedgerState::new(lst);
+  // 自分の操作しか積み重なっていなければ取り消せる
+  assert_eq!(find_dependent_lend_data(&lst, &[1], &[1, 3]), None);
+  // 他の端末で同じ品名が返却されていたら取り消せない
+  assert_eq!(
+    find_dependent_lend_data(&lst, &[1], &[1]).map(|data| data.num),
+    Some(3)
+  );
+  // 他の端末で削除されていたら取り消せない
+  assert_eq!(
+    find_dependent_lend_data(&lst, &[2], &[2]).map(|data| data.num),
+    Some(4)
+  );
+}
+
+// ハッシュチェーンの検査に使う、CSVファイルの一行分の生のデータ
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainRecord {
+  // CSVファイルの何行目か
+  pub line: u64,
+  // 操作番号の欄の値
+  pub num: String,
+  // ハッシュを計算する対象の欄の値
+  pub fields: Vec<String>,
+  // 記録されている直前の行のハッシュ
+  pub prev_hash: String,
+  // 記録されているこの行のハッシュ
+  pub hash: String,
+}
+
+// ハッシュチェーンが途切れている箇所とその理由
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChainBreak {
+  // ハッシュが記録されていない
+  MissingHash(ChainRecord),
+  // 記録されている直前の行のハッシュが、実際の直前の行のハッシュと違う
+  // 間の行が削除されたか、行が挿入されたか、順番が入れ替えられている
+  LinkBroken(ChainRecord),
+  // 行の中身から計算したハッシュが、記録されているハッシュと違う
+  ContentChanged(ChainRecord),
+}
+
+// ハッシュチェーンを記録するようになったとき（形式のバージョン3）の欄の数
+const HASH_BASE_FIELD_COUNT: usize = 11;
+
+// 直前の行のハッシュと行の中身からハッシュを計算する
+// 欄の区切りには欄の値に現れない制御文字を使う
+// バージョン3より後に加えた欄は、空欄の場合は計算に含めない
+// 欄を加える前に記録された行のハッシュを、そのまま使い続けられるようにするため
+pub fn hash_record(prev_hash: &str, fields: &[String]) -> String {
+  use sha2::{Digest, Sha256};
+  let mut hasher = Sha256::new();
+  hasher.update(prev_hash.as_bytes());
+  for (i, field) in fields.iter().enumerate() {
+    if i < HASH_BASE_FIELD_COUNT {
+      hasher.update(b"\x1f");
+    } else if field.is_empty() {
+      continue;
+    } else {
+      // どの欄の値かが分かるように、欄の位置も含める
+      hasher.update(format!("\x1e{}\x1f", i).as_bytes());
+    }
+    hasher.update(field.as_bytes());
+  }
+  hasher
+    .finalize()
+    .iter()
+    .map(|b| format!("{:02x}", b))
+    .collect()
+}
+
+// ハッシュチェーンを先頭から検査し、最初に途切れている箇所を返す
+// 先頭の行の直前の行のハッシュは空文字列とする
+pub fn verify_hash_chain(chain: &[ChainRecord]) -> Option<ChainBreak> {
+  let mut prev_hash = "";
+  for record in chain.iter() {
+    if record.hash.is_empty() {
+      return Some(ChainBreak::MissingHash(record.clone()));
+    }
+    if record.prev_hash != prev_hash {
+      return Some(ChainBreak::LinkBroken(record.clone()));
+    }
+    if hash_record(&record.prev_hash, &record.fields) != record.hash {
+      return Some(ChainBreak::ContentChanged(record.clone()));
+    }
+    prev_hash = &record.hash;
+  }
+  None
+}
+
+#[test]
+fn check_verify_hash_chain() {
+  let make_chain = |fields_lst: &[&[&str]]| {
+    let mut prev_hash = String::new();
+    let mut v = Vec::new();
+    for (i, fields) in fields_lst.iter().enumerate() {
+      let fields: Vec<String> = fields.iter().map(|s| s.to_string()).collect();
+      let hash = hash_record(&prev_hash, &fields);
+      v.push(ChainRecord {
+        line: i as u64 + 3,
+        num: (i + 1).to_string(),
+        fields,
+        prev_hash: prev_hash.clone(),
+        hash: hash.clone(),
+      });
+      prev_hash = hash;
+    }
+    v
+  };
+  let chain = make_chain(&[
+    &["Lend", "0001", "12"],
+    &["Return", "0001", "12"],
+    &["Lend", "0002", "3"],
+  ]);
+  assert_eq!(verify_hash_chain(&chain), None);
+  // 中身を書き換えた場合
+  let mut changed = chain.clone();
+  changed[1].fields[2] = "13".to_string();
+  assert_eq!(
+    verify_hash_chain(&changed),
+    Some(ChainBreak::ContentChanged(changed[1].clone()))
+  );
+  // 行を削除した場合
+  let mut removed = chain.clone();
+  removed.remove(1);
+  assert_eq!(
+    verify_hash_chain(&removed),
+    Some(ChainBreak::LinkBroken(removed[1].clone()))
+  );
+  // ハッシュごと書き換えた場合は次の行で途切れる
+  let mut rehashed = chain.clone();
+  rehashed[0].fields[2] = "13".to_string();
+  rehashed[0].hash = hash_record("", &rehashed[0].fields);
+  assert_eq!(
+    verify_hash_chain(&rehashed),
+    Some(ChainBreak::LinkBroken(rehashed[1].clone()))
+  );
+}
+
+// 'all'で表示する操作を絞り込むための条件
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AllPrintFilter {
+  // 担当者の名前
+  pub operator: Option<String>,
+  // 品名（その品名についての貸出・返却と、それを編集・削除した操作を表示する）
+  pub product_num: Option<String>,
+  // 団体ごとの貸出の制限を超えて行った貸出だけを表示するかどうか
+  pub overridden: bool,
+  // 備考（操作の備考か返却時の状態の備考にこの文字列を含むものを表示する）
+  pub note: Option<String>,
+}
+
+// 'all'で表示する操作を条件に従って絞り込む
+pub fn filter_lend_data(state: &LedgerState, filter: &AllPrintFilter) -> Vec<LendData> {
+  state
+    .lend_data_lst()
+    .iter()
+    .filter(|data| match &filter.operator {
+      None => true,
+      Some(operator) => data.operator.as_ref() == Some(operator),
+    })
+    .filter(|data| match &filter.product_num {
+      None => true,
+      Some(product_num) => state
+        .related_product_num_lst(data.num)
+        .contains(product_num),
+    })
+    .filter(|data| !filter.overridden || data.overridden)
+    .filter(|data| match &filter.note {
+      None => true,
+      Some(s) => {
+        let condition_note = data.condition.as_ref().and_then(|c| c.note.as_ref());
+        data
+          .note
+          .iter()
+          .chain(condition_note)
+          .any(|note| note.contains(s.as_str()))
+      }
+    })
+    .cloned()
+    .collect()
+}
+
 // ASCII文字なら1、それ以外なら2として長さを計算する
-fn get_char_len(s: &str) -> usize {
+pub fn get_char_len(s: &str) -> usize {
   let char_vec = s.chars();
   let mut len = 0;
   for c in char_vec {
@@ -367,7 +1553,7 @@ fn show_lend_data_to_string(
   let time = show_lend_data.time;
   let time_str = time.format("%Y/%m/%d %H:%M").to_string();
   let product_num = &show_lend_data.product_num;
-  let product_name = match config_data.sizai[product_num].as_str() {
+  let product_name = match catalog::item_name(product_num, config_data) {
     None => String::new(),
     Some(s) => format!("（{}）", s),
   };
@@ -398,40 +1584,33 @@ fn show_lend_data_to_string(
 type LendDataStringWithSize = (String, (String, usize), (String, usize));
 
 // 貸出中の品を表示するための文字列を作る
+// 受け取るのは現在貸し出されている貸出品のリスト
 pub fn make_lend_data_str(
-  lend_data_lst: Vec<LendData>,
-  config_data: ConfigData,
+  now_lend_data_lst: &[LendData],
+  config_data: &ConfigData,
+  category_opt: Option<&str>,
   re_opt: Option<(Regex, Regex)>,
 ) -> (String, usize) {
-  let mut lend_data_lst = organize_lend_data(&lend_data_lst);
-  // 操作番号が小さい方が最初になるように並び替える
-  lend_data_lst.sort_by(|a, b| a.num.cmp(&b.num));
-  // 貸したものを登録し、返却があったら削除する
-  // (時間, 品名番号, Option<貸出先番号>, 操作番号)
-  let mut lend_vec: Vec<ShowLendData> = Vec::new();
-  for lend_data in lend_data_lst.iter() {
-    let lend_type = &lend_data.lend_type;
-    match lend_type {
-      LendType::Lend(product_num, destination_num) => lend_vec.push(ShowLendData {
+  // (時間, 品名番号, 貸出先番号, 操作番号)
+  let lend_vec: Vec<ShowLendData> = now_lend_data_lst
+    .iter()
+    .filter_map(|lend_data| match &lend_data.lend_type {
+      LendType::Lend(product_num, destination_num) => Some(ShowLendData {
         time: lend_data.time,
         product_num: product_num.clone(),
         destination_num: destination_num.clone(),
         num: lend_data.num,
       }),
-      // 貸出品の番号が一致していたら削除
-      LendType::Return(product_num, _) => {
-        lend_vec = lend_vec
-          .iter()
-          .filter(|data| &data.product_num != product_num)
-          .cloned()
-          .collect();
-      }
-      // editとremoveは反映し終わっているはずなので考慮しない
-      _ => (),
-    }
-  }
+      _ => None,
+    })
+    .collect();
   let lend_str_vec: Vec<LendDataStringWithSize> = lend_vec
     .iter()
+    .filter(|lend_data| {
+      category_opt.is_none_or(|category| {
+        catalog::matches_category(&lend_data.product_num, config_data, category)
+      })
+    })
     .filter(|lend_data| match &re_opt {
       None => true,
       Some((product_re, destination_re)) => {
@@ -439,14 +1618,16 @@ pub fn make_lend_data_str(
           && destination_re.is_match(&lend_data.destination_num)
       }
     })
-    .map(|show_lend_data| show_lend_data_to_string(show_lend_data, &config_data))
+    .map(|show_lend_data| show_lend_data_to_string(show_lend_data, config_data))
     .collect();
+  // 見出しの「貸出品」は言語によって長さが変わるので、それより短くならないようにする
+  let header_len = get_char_len(text("show.item"));
   let product_str_len_max = match lend_str_vec
     .iter()
     .max_by_key(|(_, (_, product_str_len), _)| product_str_len)
   {
-    None => 6,
-    Some((_, (_, product_str_len_max), _)) => *product_str_len_max,
+    None => header_len,
+    Some((_, (_, product_str_len_max), _)) => std::cmp::max(header_len, *product_str_len_max),
   };
   let destination_str_len_max = match lend_str_vec
     .iter()
@@ -485,14 +1666,333 @@ pub enum DlmArg {
   Exit,
   NotFoundCommandName(String),
   MissingArgument(String),
-  History(usize),
-  Show(Option<(Regex, Regex)>),
-  AllPrint,
-  Check,
-  Lend(Vec<String>, String),
-  Return(Vec<String>, String),
-  Edit(isize, String, String),
-  Remove(isize),
+  // 表示する件数と、エラーになったものだけを表示するかどうか
+  History(usize, bool),
+  // 種類と、(品名, 貸出先)の正規表現
+  Show(Option<String>, Option<(Regex, Regex)>),
+  AllPrint(AllPrintFilter),
+  Login(Option<String>),
+  Migrate,
+  Verify,
+  Backup,
+  BackupList,
+  BackupRestore(String),
+  // 検査する品名の種類
+  Check(Option<String>),
+  // 貸出票を作るかどうかと、団体ごとの貸出の制限を超えて貸し出すかどうかと、備考
+  Lend(
+    Vec<String>,
+    String,
+    Option<DateTime<FixedOffset>>,
+    bool,
+    bool,
+    Option<String>,
+  ),
+  // 貸出票を作るかどうかと、返却されたときの品の状態と、備考
+  Return(
+    Vec<String>,
+    String,
+    Option<DateTime<FixedOffset>>,
+    bool,
+    Option<ReturnCondition>,
+    Option<String>,
+  ),
+  // 修理した品名
+  Repair(Vec<String>),
+  // 紛失した品名と備考
+  Lost(String, Option<String>),
+  // 使用を終える品名
+  Retire(String),
+  // 棚卸し
+  Stocktake,
+  Backfill(Option<DateTime<FixedOffset>>),
+  // 編集・削除する操作の通し番号と、備考
+  Edit(isize, EditData, Option<String>),
+  Remove(isize, Option<String>),
+  Undo(usize),
+  Redo(usize),
+  // 返却モードかどうか
+  Scan(bool),
+  Labels(label::LabelOption),
+  // 貸出票を作り直す操作番号と形式
+  Receipt(Vec<isize>, Option<receipt::ReceiptFormat>),
+  // 報告書を書き出すファイル名（無い場合は画面にMarkdownで表示する）
+  ReportFinal(Option<String>),
+  // 集計する期間と品名の種類、書き出すファイル名（無い場合は画面に表示する）
+  Stats(stats::StatsWindow, Option<String>, Option<String>),
+  // 一覧にする品名の種類
+  Catalog(Option<String>),
+}
+
+// CSVファイルを書き換えるコマンドかどうか
+// 読み取り専用モードで受け付けないコマンドを判定するのに使う
+pub fn is_write_command(arg: &DlmArg) -> bool {
+  matches!(
+    arg,
+    DlmArg::Migrate
+      | DlmArg::BackupRestore(_)
+      | DlmArg::Lend(_, _, _, _, _, _)
+      | DlmArg::Return(_, _, _, _, _, _)
+      | DlmArg::Repair(_)
+      | DlmArg::Lost(_, _)
+      | DlmArg::Retire(_)
+      | DlmArg::Edit(_, _, _)
+      | DlmArg::Remove(_, _)
+      | DlmArg::Undo(_)
+      | DlmArg::Redo(_)
+      | DlmArg::Scan(_)
+  )
+}
+
+// 'scan'モードで読み取った番号の種類
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanCode {
+  // 'end'または空行
+  End,
+  // 設定ファイルの"sizai"にある品名
+  Item(String),
+  // 設定ファイルの"sandan"にある貸出先
+  Group(String),
+  // どちらにも無い番号
+  Unknown(String),
+}
+
+// スキャナーで読み取った番号が品名か貸出先かを、設定ファイルを使って見分ける
+pub fn classify_scan_code(code: &str, config_data: &ConfigData) -> ScanCode {
+  let code = code.trim();
+  if code.is_empty() || code.eq_ignore_ascii_case("end") {
+    ScanCode::End
+  } else if config_data.sizai.get(code).is_some() {
+    ScanCode::Item(code.to_string())
+  } else if config_data.sandan.get(code).is_some() {
+    ScanCode::Group(code.to_string())
+  } else {
+    ScanCode::Unknown(code.to_string())
+  }
+}
+
+#[test]
+fn check_classify_scan_code() {
+  let config_data = make_config_data(
+    serde_json::json!({"0001": "内リール1"}),
+    serde_json::json!({"12": "物理部"}),
+    serde_json::json!(null),
+  );
+  assert_eq!(
+    classify_scan_code("0001\n", &config_data),
+    ScanCode::Item("0001".to_string())
+  );
+  assert_eq!(
+    classify_scan_code("12", &config_data),
+    ScanCode::Group("12".to_string())
+  );
+  assert_eq!(
+    classify_scan_code("0002", &config_data),
+    ScanCode::Unknown("0002".to_string())
+  );
+  assert_eq!(classify_scan_code("END", &config_data), ScanCode::End);
+  assert_eq!(classify_scan_code("", &config_data), ScanCode::End);
+}
+
+// 入力の履歴の一つ
+// 'history --failed'で表示できるように、エラーになったかどうかも記録しておく
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HistoryEntry {
+  pub command: String,
+  pub failed: bool,
+}
+
+// '!!'（直前の入力）や'!<n>'（'history'で表示されるn番目の入力）を履歴の中の入力に置き換える
+// 置き換える必要が無い入力の場合はNoneを、履歴に見つからない場合はErrを返す
+pub fn expand_history(s: &str, history_lst: &[HistoryEntry]) -> Option<Result<String, String>> {
+  let rest = s.strip_prefix('!')?;
+  let entry_opt = if rest == "!" {
+    history_lst.last()
+  } else {
+    match rest.parse::<usize>() {
+      Ok(n) if n >= 1 => history_lst.get(n - 1),
+      _ => return Some(Err(fill("history.not_found", &[&s]))),
+    }
+  };
+  Some(
+    entry_opt
+      .map(|entry| entry.command.clone())
+      .ok_or_else(|| fill("history.not_found", &[&s])),
+  )
+}
+
+#[test]
+fn check_expand_history() {
+  let history_lst = vec![
+    HistoryEntry {
+      command: "lend 0001 12".to_string(),
+      failed: false,
+    },
+    HistoryEntry {
+      command: "show".to_string(),
+      failed: true,
+    },
+  ];
+  assert_eq!(expand_history("show", &history_lst), None);
+  assert_eq!(
+    expand_history("!!", &history_lst),
+    Some(Ok("show".to_string()))
+  );
+  assert_eq!(
+    expand_history("!1", &history_lst),
+    Some(Ok("lend 0001 12".to_string()))
+  );
+  assert!(matches!(expand_history("!3", &history_lst), Some(Err(_))));
+  assert!(matches!(expand_history("!0", &history_lst), Some(Err(_))));
+  assert!(matches!(expand_history("!!", &[]), Some(Err(_))));
+}
+
+// 入力された文字列を空白で区切って引数のリストにする
+// '"'で囲まれた部分は空白を含んでいても一つの引数として扱う
+pub fn split_arg(s: &str) -> Vec<String> {
+  let mut v = Vec::new();
+  let mut buf = String::new();
+  let mut in_quote = false;
+  let mut has_arg = false;
+  for c in s.chars() {
+    match c {
+      '"' => {
+        in_quote = !in_quote;
+        has_arg = true;
+      }
+      c if c.is_whitespace() && !in_quote => {
+        if has_arg {
+          v.push(buf.clone());
+          buf.clear();
+          has_arg = false;
+        }
+      }
+      c => {
+        buf.push(c);
+        has_arg = true;
+      }
+    }
+  }
+  if has_arg {
+    v.push(buf)
+  }
+  v
+}
+
+#[test]
+fn check_split_arg() {
+  assert_eq!(
+    split_arg("edit 12  --time \"2020/11/23 17:40\""),
+    vec!["edit", "12", "--time", "2020/11/23 17:40"]
+  );
+  assert_eq!(split_arg("lend \"\" 1"), vec!["lend", "", "1"]);
+  assert_eq!(split_arg("   "), Vec::<String>::new());
+}
+
+// 引数のリストから'--at <時刻>'のような時刻を与えるオプションを取り除き、その時刻を読み取る
+// 時刻は'"'で囲まなくても、日付と時刻の二つに分かれた引数として読み取れるようにする
+fn take_time_option<'a>(
+  arg: Vec<&'a str>,
+  option_name: &str,
+) -> Result<(Vec<&'a str>, Option<DateTime<FixedOffset>>), String> {
+  match arg.iter().position(|s| *s == option_name) {
+    None => Ok((arg, None)),
+    Some(i) => {
+      let value = match arg.get(i + 1) {
+        None => return Err(fill("arg.option_value_missing", &[&option_name])),
+        Some(value) => value,
+      };
+      let (time, len) = match parse_time_str(value) {
+        Some(time) => (time, 2),
+        None => match arg
+          .get(i + 2)
+          .and_then(|s| parse_time_str(&format!("{} {}", value, s)))
+        {
+          Some(time) => (time, 3),
+          None => return Err(text("arg.time_format").to_string()),
+        },
+      };
+      let mut arg = arg;
+      arg.drain(i..(i + len));
+      Ok((arg, Some(time)))
+    }
+  }
+}
+
+// 値を取らないオプションを取り除き、与えられていたかどうかを返す
+fn take_flag_option<'a>(arg: Vec<&'a str>, option_name: &str) -> (Vec<&'a str>, bool) {
+  let found = arg.contains(&option_name);
+  let arg = arg.into_iter().filter(|s| *s != option_name).collect();
+  (arg, found)
+}
+
+// 値を一つ取るオプションを取り除き、その値を返す
+fn take_value_option<'a>(
+  arg: Vec<&'a str>,
+  option_name: &str,
+) -> Result<(Vec<&'a str>, Option<String>), String> {
+  match arg.iter().position(|s| *s == option_name) {
+    None => Ok((arg, None)),
+    Some(i) => match arg.get(i + 1) {
+      None => Err(fill("arg.option_value_missing", &[&option_name])),
+      Some(value) => {
+        let value = value.to_string();
+        let mut arg = arg;
+        arg.drain(i..(i + 2));
+        Ok((arg, Some(value)))
+      }
+    },
+  }
+}
+
+// 'lend 0001 12 -- "付属品あり"'のように、'--'より後を操作の備考として取り出す
+fn take_note_option(arg: Vec<&str>) -> Result<(Vec<&str>, Option<String>), String> {
+  match arg.iter().position(|s| *s == "--") {
+    None => Ok((arg, None)),
+    Some(i) => {
+      let note = arg[(i + 1)..].join(" ");
+      if note.trim().is_empty() {
+        Err(text("arg.note_missing").to_string())
+      } else {
+        let mut arg = arg;
+        arg.truncate(i);
+        Ok((arg, Some(note)))
+      }
+    }
+  }
+}
+
+// 'edit'の'--product'・'--destination'・'--time'を読み取る
+// 時刻は'"'で囲まなくても、日付と時刻の二つに分かれた引数として読み取れるようにする
+fn parse_edit_option(num: isize, arg: &[&str], note: Option<String>) -> DlmArg {
+  let mut edit_data = EditData::default();
+  let mut i = 0;
+  while i < arg.len() {
+    let value = match arg.get(i + 1) {
+      None => return DlmArg::MissingArgument(fill("arg.option_value_missing", &[&arg[i]])),
+      Some(value) => value.to_string(),
+    };
+    match arg[i] {
+      "--product" | "-p" => edit_data.product_num = Some(value),
+      "--destination" | "-d" => edit_data.destination_num = Some(value),
+      "--time" | "-t" => match parse_time_str(&value) {
+        Some(time) => edit_data.time = Some(time),
+        None => match arg
+          .get(i + 2)
+          .and_then(|s| parse_time_str(&format!("{} {}", value, s)))
+        {
+          Some(time) => {
+            edit_data.time = Some(time);
+            i += 1
+          }
+          None => return DlmArg::MissingArgument(text("arg.time_format").to_string()),
+        },
+      },
+      name => return DlmArg::MissingArgument(fill("arg.unknown_option", &[&name])),
+    }
+    i += 2
+  }
+  DlmArg::Edit(num, edit_data, note)
 }
 
 // 大文字小文字を考慮するのが面倒なので、アルファベットに関しては小文字化して評価する
@@ -504,109 +2004,319 @@ pub fn parse_arg(arg: Vec<&str>) -> DlmArg {
     match arg_command_name {
       "exit" => {
         if arg.len() >= 2 {
-          DlmArg::MissingArgument("引数は不要です".to_string())
+          DlmArg::MissingArgument(text("arg.no_argument").to_string())
         } else {
           DlmArg::Exit
         }
       }
       "help" => {
         if arg.len() >= 2 {
-          DlmArg::MissingArgument("引数は不要です".to_string())
+          DlmArg::MissingArgument(text("arg.no_argument").to_string())
         } else {
           DlmArg::Help
         }
       }
       "history" => {
+        // [--failed] [<n>]
+        let failed = arg.contains(&"--failed");
+        let arg: Vec<&str> = arg.into_iter().filter(|s| *s != "--failed").collect();
         if arg.len() >= 3 {
-          DlmArg::MissingArgument("引数は1つまでです".to_string())
+          DlmArg::MissingArgument(text("arg.at_most_one").to_string())
         } else {
           match arg.get(1) {
-            None => DlmArg::History(10),
+            None => DlmArg::History(10, failed),
             Some(s) => match s.parse() {
-              Err(_) => DlmArg::MissingArgument("数字を入力してください".to_string()),
-              Ok(i) => DlmArg::History(i),
+              Err(_) => DlmArg::MissingArgument(text("arg.number").to_string()),
+              Ok(i) => DlmArg::History(i, failed),
             },
           }
         }
       }
       "show" => {
+        // [--category <種類>] [<品名の正規表現> <貸出先の正規表現>]
+        let (arg, category_opt) = match take_value_option(arg, "--category") {
+          Err(msg) => return DlmArg::MissingArgument(msg),
+          Ok(v) => v,
+        };
         if arg.len() >= 4 {
-          DlmArg::MissingArgument("引数は2つまでです".to_string())
+          DlmArg::MissingArgument(text("arg.at_most_two").to_string())
         } else {
           match (arg.get(1), arg.get(2)) {
-            (None, None) => DlmArg::Show(None),
+            (None, None) => DlmArg::Show(category_opt, None),
             (Some(s1), Some(s2)) => match (Regex::new(s1), Regex::new(s2)) {
-              (Ok(re1), Ok(re2)) => DlmArg::Show(Some((re1, re2))),
-              _ => DlmArg::MissingArgument("正規表現として不正な引数です".to_string()),
+              (Ok(re1), Ok(re2)) => DlmArg::Show(category_opt, Some((re1, re2))),
+              _ => DlmArg::MissingArgument(text("arg.invalid_regex").to_string()),
             },
-            _ => DlmArg::MissingArgument("フィルターを書ける場合は引数は2つ必要です".to_string()),
+            _ => DlmArg::MissingArgument(text("arg.filter_needs_two").to_string()),
           }
         }
       }
       "all" => {
+        // [--operator <担当者>] [--item <品名>] [--overridden] [--note <文字列>]
+        let (arg, overridden) = take_flag_option(arg, "--overridden");
+        let mut filter = AllPrintFilter {
+          overridden,
+          ..AllPrintFilter::default()
+        };
+        let mut i = 1;
+        while i < arg.len() {
+          match (arg[i], arg.get(i + 1)) {
+            ("--operator", Some(operator)) => filter.operator = Some(operator.to_string()),
+            ("--item", Some(product_num)) => filter.product_num = Some(product_num.to_string()),
+            ("--note", Some(note)) => filter.note = Some(note.to_string()),
+            ("--operator", None) | ("--item", None) | ("--note", None) => {
+              return DlmArg::MissingArgument(fill("arg.option_value_missing", &[&arg[i]]))
+            }
+            (name, _) => return DlmArg::MissingArgument(fill("arg.unknown_option", &[&name])),
+          }
+          i += 2
+        }
+        DlmArg::AllPrint(filter)
+      }
+      "login" => {
+        if arg.len() >= 3 {
+          DlmArg::MissingArgument(text("arg.at_most_one").to_string())
+        } else {
+          match arg.get(1) {
+            None => DlmArg::MissingArgument(text("arg.operator_name").to_string()),
+            Some(name) => DlmArg::Login(Some(name.to_string())),
+          }
+        }
+      }
+      "logout" => {
+        if arg.len() >= 2 {
+          DlmArg::MissingArgument(text("arg.no_argument").to_string())
+        } else {
+          DlmArg::Login(None)
+        }
+      }
+      "verify" => {
         if arg.len() >= 2 {
-          DlmArg::MissingArgument("引数は不要です".to_string())
+          DlmArg::MissingArgument(text("arg.no_argument").to_string())
         } else {
-          DlmArg::AllPrint
+          DlmArg::Verify
+        }
+      }
+      "backup" => {
+        // 引数なし、'list'、'restore <バックアップの識別子>'のいずれか
+        match (arg.get(1), arg.get(2), arg.len()) {
+          (None, _, _) => DlmArg::Backup,
+          (Some(&"list"), None, _) => DlmArg::BackupList,
+          (Some(&"restore"), Some(id), 3) => DlmArg::BackupRestore(id.to_string()),
+          (Some(&"restore"), None, _) => DlmArg::MissingArgument(text("arg.backup_id").to_string()),
+          _ => DlmArg::MissingArgument(text("arg.backup_usage").to_string()),
+        }
+      }
+      "migrate" => {
+        if arg.len() >= 2 {
+          DlmArg::MissingArgument(text("arg.no_argument").to_string())
+        } else {
+          DlmArg::Migrate
         }
       }
       "check" => {
+        // [--category <種類>]
+        let (arg, category_opt) = match take_value_option(arg, "--category") {
+          Err(msg) => return DlmArg::MissingArgument(msg),
+          Ok(v) => v,
+        };
+        if arg.len() >= 2 {
+          DlmArg::MissingArgument(text("arg.no_argument").to_string())
+        } else {
+          DlmArg::Check(category_opt)
+        }
+      }
+      "catalog" => {
+        // [--category <種類>]
+        let (arg, category_opt) = match take_value_option(arg, "--category") {
+          Err(msg) => return DlmArg::MissingArgument(msg),
+          Ok(v) => v,
+        };
+        if arg.len() >= 2 {
+          DlmArg::MissingArgument(text("arg.no_argument").to_string())
+        } else {
+          DlmArg::Catalog(category_opt)
+        }
+      }
+      "stocktake" => {
         if arg.len() >= 2 {
-          DlmArg::MissingArgument("引数は不要です".to_string())
+          DlmArg::MissingArgument(text("arg.no_argument").to_string())
         } else {
-          DlmArg::Check
+          DlmArg::Stocktake
         }
       }
       "lend" | "l" => {
-        // <貸出品の番号1> <貸出品の番号2> .. <貸出品の番号n> <貸出先の番号>
-        match arg.get(1) {
-          None => DlmArg::MissingArgument("貸出品を与えてください".to_string()),
-          Some(_) => {
-            if arg.len() < 3 {
-              DlmArg::MissingArgument("貸出先を与えてください".to_string())
-            } else {
-              let mut v = Vec::new();
-              let len = arg.len();
-              for item in arg.iter().take(len - 1).skip(1) {
-                v.push(item.to_string())
+        // <貸出品の番号1> <貸出品の番号2> .. <貸出品の番号n> <貸出先の番号> [--at <時刻>] [--receipt] [--override]
+        // [-- <備考>]
+        let (arg, note) = match take_note_option(arg) {
+          Err(msg) => return DlmArg::MissingArgument(msg),
+          Ok(v) => v,
+        };
+        let (arg, with_receipt) = take_flag_option(arg, "--receipt");
+        let (arg, with_override) = take_flag_option(arg, "--override");
+        match take_time_option(arg, "--at") {
+          Err(msg) => DlmArg::MissingArgument(msg),
+          Ok((arg, at)) => match arg.get(1) {
+            None => DlmArg::MissingArgument(text("arg.lend_item").to_string()),
+            Some(_) => {
+              if arg.len() < 3 {
+                DlmArg::MissingArgument(text("arg.lend_destination").to_string())
+              } else {
+                let mut v = Vec::new();
+                let len = arg.len();
+                for item in arg.iter().take(len - 1).skip(1) {
+                  v.push(item.to_string())
+                }
+                DlmArg::Lend(
+                  v,
+                  arg[len - 1].to_string(),
+                  at,
+                  with_receipt,
+                  with_override,
+                  note,
+                )
               }
-              DlmArg::Lend(v, arg[len - 1].to_string())
             }
-          }
+          },
         }
       }
       "return" | "r" => {
-        // <返却品の番号1> <返却品の番号2> .. <返却品の番号n> <返却元の番号>
+        // <返却品の番号1> <返却品の番号2> .. <返却品の番号n> <返却元の番号> [--at <時刻>] [--receipt]
+        // [--condition <ok|damaged|missing-parts>] [--condition-note <状態の備考>] [-- <操作の備考>]
+        let (arg, note) = match take_note_option(arg) {
+          Err(msg) => return DlmArg::MissingArgument(msg),
+          Ok(v) => v,
+        };
+        let (arg, with_receipt) = take_flag_option(arg, "--receipt");
+        let (arg, condition_opt) = match take_value_option(arg, "--condition") {
+          Err(msg) => return DlmArg::MissingArgument(msg),
+          Ok(v) => v,
+        };
+        let (arg, note_opt) = match take_value_option(arg, "--condition-note") {
+          Err(msg) => return DlmArg::MissingArgument(msg),
+          Ok(v) => v,
+        };
+        // 状態の備考だけが与えられた場合は、状態に問題が無いものとして記録する
+        let condition = match (condition_opt, note_opt) {
+          (None, None) => None,
+          (None, note) => Some(ReturnCondition {
+            kind: ConditionKind::Ok,
+            note,
+          }),
+          (Some(name), note) => match ConditionKind::from_name(&name) {
+            None => return DlmArg::MissingArgument(fill("arg.return_condition", &[&name])),
+            Some(kind) => Some(ReturnCondition { kind, note }),
+          },
+        };
+        match take_time_option(arg, "--at") {
+          Err(msg) => DlmArg::MissingArgument(msg),
+          Ok((arg, at)) => match arg.get(1) {
+            None => DlmArg::MissingArgument(text("arg.return_item").to_string()),
+            Some(_) => {
+              if arg.len() < 3 {
+                DlmArg::MissingArgument(text("arg.return_destination").to_string())
+              } else {
+                let mut v = Vec::new();
+                let len = arg.len();
+                for item in arg.iter().take(len - 1).skip(1) {
+                  v.push(item.to_string())
+                }
+                DlmArg::Return(
+                  v,
+                  arg[len - 1].to_string(),
+                  at,
+                  with_receipt,
+                  condition,
+                  note,
+                )
+              }
+            }
+          },
+        }
+      }
+      "repair" => {
+        // <修理した品の番号1> <修理した品の番号2> .. <修理した品の番号n>
+        if arg.len() < 2 {
+          DlmArg::MissingArgument(text("arg.repair_item").to_string())
+        } else {
+          DlmArg::Repair(arg.iter().skip(1).map(|s| s.to_string()).collect())
+        }
+      }
+      "lost" => {
+        // <紛失した品の番号> [備考]
+        // 他のコマンドと同じく、備考の前に'--'を付けてもよい
         match arg.get(1) {
-          None => DlmArg::MissingArgument("返却品を与えてください".to_string()),
-          Some(_) => {
-            if arg.len() < 3 {
-              DlmArg::MissingArgument("返却先を与えてください".to_string())
+          None => DlmArg::MissingArgument(text("arg.lost_item").to_string()),
+          Some(product_num) => {
+            let note_arg = match arg.get(2) {
+              Some(&"--") => &arg[3..],
+              _ => &arg[2..],
+            };
+            let note = note_arg.join(" ");
+            if !note.trim().is_empty() {
+              DlmArg::Lost(product_num.to_string(), Some(note))
+            } else if arg.len() >= 3 {
+              DlmArg::MissingArgument(text("arg.note_missing").to_string())
             } else {
-              let mut v = Vec::new();
-              let len = arg.len();
-              for item in arg.iter().take(len - 1).skip(1) {
-                v.push(item.to_string())
-              }
-              DlmArg::Return(v, arg[len - 1].to_string())
+              DlmArg::Lost(product_num.to_string(), None)
             }
           }
         }
       }
+      "retire" => {
+        // <使用を終える品の番号>
+        match (arg.get(1), arg.len()) {
+          (None, _) => DlmArg::MissingArgument(text("arg.retire_item").to_string()),
+          (Some(product_num), 2) => DlmArg::Retire(product_num.to_string()),
+          _ => DlmArg::MissingArgument(text("arg.at_most_one").to_string()),
+        }
+      }
+      "backfill" => {
+        // <時刻> もしくは end
+        match (arg.get(1), arg.len()) {
+          (None, _) => DlmArg::MissingArgument(text("arg.backfill_time").to_string()),
+          (Some(s), 2) if s.eq_ignore_ascii_case("end") => DlmArg::Backfill(None),
+          (Some(_), 2) | (Some(_), 3) => match parse_time_str(&arg[1..].join(" ")) {
+            None => DlmArg::MissingArgument(text("arg.time_format").to_string()),
+            Some(time) => DlmArg::Backfill(Some(time)),
+          },
+          _ => DlmArg::MissingArgument(text("arg.at_most_one").to_string()),
+        }
+      }
       "edit" => {
         // <編集対象に付けられた通し番号> <編集後の品名の番号> <編集後の貸出先の番号>
-        if arg.len() >= 5 {
-          DlmArg::MissingArgument("引数は3までです".to_string())
+        // もしくは
+        // <編集対象に付けられた通し番号> [--product <品名>] [--destination <貸出先>] [--time <時刻>]
+        // のどちらの形でも、最後に[-- <備考>]を付けられる
+        let (arg, note) = match take_note_option(arg) {
+          Err(msg) => return DlmArg::MissingArgument(msg),
+          Ok(v) => v,
+        };
+        if arg.iter().skip(2).any(|s| s.starts_with('-')) {
+          match arg[1].parse() {
+            Err(_) => DlmArg::MissingArgument(text("arg.number").to_string()),
+            Ok(i) => parse_edit_option(i, &arg[2..], note),
+          }
+        } else if arg.len() >= 5 {
+          DlmArg::MissingArgument(text("arg.at_most_three").to_string())
         } else {
           match arg.get(1) {
-            None => DlmArg::MissingArgument("編集対象の通し番号を与えてください".to_string()),
+            None => DlmArg::MissingArgument(text("arg.edit_target").to_string()),
             Some(s1) => match s1.parse() {
-              Err(_) => DlmArg::MissingArgument("数字を与えてください".to_string()),
+              Err(_) => DlmArg::MissingArgument(text("arg.number").to_string()),
               Ok(i) => match arg.get(2) {
-                None => DlmArg::MissingArgument("編集後の品名を与えてください".to_string()),
+                None => DlmArg::MissingArgument(text("arg.edit_product").to_string()),
                 Some(s2) => match arg.get(3) {
-                  None => DlmArg::MissingArgument("編集後の貸出先を与えてください".to_string()),
-                  Some(s3) => DlmArg::Edit(i, s2.to_string(), s3.to_string()),
+                  None => DlmArg::MissingArgument(text("arg.edit_destination").to_string()),
+                  Some(s3) => DlmArg::Edit(
+                    i,
+                    EditData {
+                      product_num: Some(s2.to_string()),
+                      destination_num: Some(s3.to_string()),
+                      time: None,
+                    },
+                    note,
+                  ),
                 },
               },
             },
@@ -614,15 +2324,161 @@ pub fn parse_arg(arg: Vec<&str>) -> DlmArg {
         }
       }
       "remove" => {
+        // <削除対象に付けられた通し番号> [-- <備考>]
+        let (arg, note) = match take_note_option(arg) {
+          Err(msg) => return DlmArg::MissingArgument(msg),
+          Ok(v) => v,
+        };
         if arg.len() >= 3 {
-          DlmArg::MissingArgument("引数は1つまでです".to_string())
+          DlmArg::MissingArgument(text("arg.at_most_one").to_string())
         } else {
           match arg.get(1) {
-            None => DlmArg::MissingArgument("削除対象の操作の番号を与えてください".to_string()),
+            None => DlmArg::MissingArgument(text("arg.remove_target").to_string()),
             Some(s) => match s.parse() {
-              Err(_) => DlmArg::MissingArgument("数字を与えてください".to_string()),
-              Ok(i) => DlmArg::Remove(i),
+              Err(_) => DlmArg::MissingArgument(text("arg.number").to_string()),
+              Ok(i) => DlmArg::Remove(i, note),
+            },
+          }
+        }
+      }
+      "stats" => {
+        // [--from <時刻>] [--to <時刻>] [--category <種類>] [--out <ファイル名>]
+        let (arg, category_opt) = match take_value_option(arg, "--category") {
+          Err(msg) => return DlmArg::MissingArgument(msg),
+          Ok(v) => v,
+        };
+        let (arg, from) = match take_time_option(arg, "--from") {
+          Err(msg) => return DlmArg::MissingArgument(msg),
+          Ok(v) => v,
+        };
+        let (arg, to) = match take_time_option(arg, "--to") {
+          Err(msg) => return DlmArg::MissingArgument(msg),
+          Ok(v) => v,
+        };
+        let window = stats::StatsWindow { from, to };
+        match (arg.get(1), arg.get(2), arg.len()) {
+          (None, _, _) => DlmArg::Stats(window, category_opt, None),
+          (Some(&"--out"), Some(file_name), 3) => {
+            match stats::StatsFormat::from_file_name(file_name) {
+              None => DlmArg::MissingArgument(text("arg.stats_out").to_string()),
+              Some(_) => DlmArg::Stats(window, category_opt, Some(file_name.to_string())),
+            }
+          }
+          (Some(&"--out"), None, _) => {
+            DlmArg::MissingArgument(fill("arg.option_value_missing", &[&"--out"]))
+          }
+          (Some(name), _, _) => DlmArg::MissingArgument(fill("arg.unknown_option", &[name])),
+        }
+      }
+      "report" => {
+        // final [--out <ファイル名>]
+        match (arg.get(1), arg.get(2), arg.get(3), arg.len()) {
+          (Some(&"final"), None, _, _) => DlmArg::ReportFinal(None),
+          (Some(&"final"), Some(&"--out"), Some(file_name), 4) => {
+            match report::ReportFormat::from_file_name(file_name) {
+              None => DlmArg::MissingArgument(text("arg.report_out").to_string()),
+              Some(_) => DlmArg::ReportFinal(Some(file_name.to_string())),
+            }
+          }
+          (Some(&"final"), Some(&"--out"), None, _) => {
+            DlmArg::MissingArgument(fill("arg.option_value_missing", &[&"--out"]))
+          }
+          _ => DlmArg::MissingArgument(text("arg.report_usage").to_string()),
+        }
+      }
+      "receipt" => {
+        // <操作番号1> .. <操作番号n> [--format <text|html|pdf>]
+        let mut num_lst = Vec::new();
+        let mut format_opt = None;
+        let mut i = 1;
+        while i < arg.len() {
+          match (arg[i], arg.get(i + 1)) {
+            ("--format", Some(name)) => match receipt::ReceiptFormat::from_name(name) {
+              None => return DlmArg::MissingArgument(text("arg.receipt_format").to_string()),
+              Some(format) => {
+                format_opt = Some(format);
+                i += 1
+              }
+            },
+            ("--format", None) => {
+              return DlmArg::MissingArgument(fill("arg.option_value_missing", &[&arg[i]]))
+            }
+            (s, _) => match s.parse() {
+              Err(_) => return DlmArg::MissingArgument(text("arg.number").to_string()),
+              Ok(num) => num_lst.push(num),
+            },
+          }
+          i += 1
+        }
+        if num_lst.is_empty() {
+          DlmArg::MissingArgument(text("arg.receipt_num").to_string())
+        } else {
+          DlmArg::Receipt(num_lst, format_opt)
+        }
+      }
+      "labels" => {
+        // [items|groups] [--code <code128|qr>] [--grid <用紙>-<列>x<行>] [--out <ファイル名>]
+        let mut option = label::LabelOption::default();
+        let mut i = 1;
+        while i < arg.len() {
+          match (arg[i], arg.get(i + 1)) {
+            ("items", _) if i == 1 => {
+              option.groups = false;
+              i += 1;
+              continue;
+            }
+            ("groups", _) if i == 1 => {
+              option.items = false;
+              i += 1;
+              continue;
+            }
+            ("--code", Some(name)) => match label::CodeType::from_name(name) {
+              None => return DlmArg::MissingArgument(text("arg.label_code").to_string()),
+              Some(code_type) => option.code_type = code_type,
             },
+            ("--grid", Some(s)) => match label::parse_label_grid(s) {
+              None => return DlmArg::MissingArgument(text("arg.label_grid").to_string()),
+              Some(grid) => option.grid = grid,
+            },
+            ("--out", Some(file_name)) => match label::LabelFormat::from_file_name(file_name) {
+              None => return DlmArg::MissingArgument(text("arg.label_out").to_string()),
+              Some(format) => {
+                option.format = format;
+                option.out = file_name.to_string()
+              }
+            },
+            ("--code", None) | ("--grid", None) | ("--out", None) => {
+              return DlmArg::MissingArgument(fill("arg.option_value_missing", &[&arg[i]]))
+            }
+            (name, _) => return DlmArg::MissingArgument(fill("arg.unknown_option", &[&name])),
+          }
+          i += 2
+        }
+        DlmArg::Labels(option)
+      }
+      "scan" => {
+        // 引数なしで貸出、'return'で返却
+        match (arg.get(1), arg.len()) {
+          (None, _) => DlmArg::Scan(false),
+          (Some(s), 2) if s.eq_ignore_ascii_case("return") => DlmArg::Scan(true),
+          (Some(_), 2) => DlmArg::MissingArgument(text("arg.scan_usage").to_string()),
+          _ => DlmArg::MissingArgument(text("arg.at_most_one").to_string()),
+        }
+      }
+      "undo" | "redo" => {
+        if arg.len() >= 3 {
+          DlmArg::MissingArgument(text("arg.at_most_one").to_string())
+        } else {
+          let n_r = match arg.get(1) {
+            None => Ok(1),
+            Some(s) => s.parse(),
+          };
+          match (n_r, arg_command_name) {
+            (Err(_), _) | (Ok(0), _) => {
+              DlmArg::MissingArgument(text("arg.positive_number").to_string())
+            }
+            (Ok(n), "undo") => DlmArg::Undo(n),
+            (Ok(n), _) => DlmArg::Redo(n),
           }
         }
       }
@@ -632,3 +2488,61 @@ pub fn parse_arg(arg: Vec<&str>) -> DlmArg {
     }
   }
 }
+
+#[test]
+fn check_parse_arg_return_note() {
+  match parse_arg(vec![
+    "return",
+    "0001",
+    "12",
+    "--condition",
+    "damaged",
+    "--condition-note",
+    "断線",
+    "--",
+    "代理で返却",
+  ]) {
+    DlmArg::Return(items, dest, _, _, condition, note) => {
+      assert_eq!(items, vec!["0001".to_string()]);
+      assert_eq!(dest, "12");
+      assert_eq!(
+        condition,
+        Some(ReturnCondition {
+          kind: ConditionKind::Damaged,
+          note: Some("断線".to_string()),
+        })
+      );
+      assert_eq!(note, Some("代理で返却".to_string()));
+    }
+    arg => panic!("{:?}", arg),
+  }
+}
+
+#[test]
+fn check_parse_arg_lost() {
+  match parse_arg(vec!["lost", "0001", "--", "会場で", "紛失"]) {
+    DlmArg::Lost(product, note) => {
+      assert_eq!(product, "0001");
+      assert_eq!(note, Some("会場で 紛失".to_string()));
+    }
+    arg => panic!("{:?}", arg),
+  }
+  match parse_arg(vec!["lost", "0001"]) {
+    DlmArg::Lost(product, note) => {
+      assert_eq!(product, "0001");
+      assert_eq!(note, None);
+    }
+    arg => panic!("{:?}", arg),
+  }
+  match parse_arg(vec!["lost", "0001", "会場で", "紛失"]) {
+    DlmArg::Lost(product, note) => {
+      assert_eq!(product, "0001");
+      assert_eq!(note, Some("会場で 紛失".to_string()));
+    }
+    arg => panic!("{:?}", arg),
+  }
+  assert!(matches!(
+    parse_arg(vec!["lost", "0001", "--"]),
+    DlmArg::MissingArgument(_)
+  ));
+}
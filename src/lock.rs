@@ -0,0 +1,99 @@
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// 書き込み中に他のdlmが書き込まないようにするためのロック
+// CSVファイルそのものではなく、'<CSVファイル名>.lock'というファイルをロックする
+// CSVファイルは書き込みのたびに置き換えられるので、同じファイルを使い続けられるようにするため
+// 値が破棄されるときにロックは解除される
+#[derive(Debug)]
+pub struct DataFileLock {
+  _file: fs::File,
+}
+
+// ロックが取れるまで待つ時間
+const LOCK_WAIT_TIME: Duration = Duration::from_secs(3);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+fn lock_file_name(data_file_name: &str) -> String {
+  format!("{}.lock", data_file_name)
+}
+
+// ロックを取る
+// 他のdlmが書き込み中の場合は少しだけ待ち、それでも取れなければNoneを返す
+pub fn lock_data_file(data_file_name: &str) -> Option<DataFileLock> {
+  lock_data_file_with_wait(data_file_name, LOCK_WAIT_TIME)
+}
+
+fn lock_data_file_with_wait(data_file_name: &str, wait_time: Duration) -> Option<DataFileLock> {
+  let file = fs::OpenOptions::new()
+    .create(true)
+    .truncate(false)
+    .write(true)
+    .open(lock_file_name(data_file_name))
+    .ok()?;
+  let start = SystemTime::now();
+  loop {
+    match file.try_lock() {
+      Ok(()) => return Some(DataFileLock { _file: file }),
+      Err(fs::TryLockError::WouldBlock) => {
+        let waited = start.elapsed().unwrap_or(wait_time);
+        if waited >= wait_time {
+          return None;
+        }
+        thread::sleep(LOCK_RETRY_INTERVAL)
+      }
+      Err(fs::TryLockError::Error(_)) => return None,
+    }
+  }
+}
+
+// CSVファイルが最後に読んだときから変わったかを調べるための目印
+// ファイルの大きさと更新時刻を使う
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataFileStamp {
+  len: u64,
+  modified: Option<SystemTime>,
+}
+
+// CSVファイルがまだ無い場合はNoneを返す
+pub fn data_file_stamp(data_file_name: &str) -> Option<DataFileStamp> {
+  let metadata = fs::metadata(Path::new(data_file_name)).ok()?;
+  Some(DataFileStamp {
+    len: metadata.len(),
+    modified: metadata.modified().ok(),
+  })
+}
+
+#[cfg(test)]
+fn test_data_file_name(name: &str) -> String {
+  let dir = std::env::temp_dir().join(format!("dlm-lock-test-{}", std::process::id()));
+  fs::create_dir_all(&dir).unwrap();
+  dir.join(name).to_string_lossy().to_string()
+}
+
+#[test]
+fn check_lock_data_file() {
+  let data_file_name = test_data_file_name("lock.csv");
+  let lock = lock_data_file(&data_file_name);
+  assert!(lock.is_some());
+  // ロックを持っている間は、他からはロックを取れない
+  assert!(lock_data_file_with_wait(&data_file_name, Duration::from_millis(200)).is_none());
+  drop(lock);
+  assert!(lock_data_file_with_wait(&data_file_name, Duration::from_millis(200)).is_some());
+}
+
+#[test]
+fn check_data_file_stamp() {
+  let data_file_name = test_data_file_name("stamp.csv");
+  let _ = fs::remove_file(&data_file_name);
+  assert_eq!(data_file_stamp(&data_file_name), None);
+  fs::write(&data_file_name, "a\n").unwrap();
+  let stamp = data_file_stamp(&data_file_name);
+  assert!(stamp.is_some());
+  assert_eq!(data_file_stamp(&data_file_name), stamp);
+  // 更新時刻が同じになっても、大きさが変われば別のものとみなす
+  fs::write(&data_file_name, "a\nb\n").unwrap();
+  assert_ne!(data_file_stamp(&data_file_name), stamp);
+}
@@ -1,4 +1,8 @@
-mod lib;
+use dlm as lib;
+use lib::message::{self, fill, text};
+mod backup;
+mod editor;
+mod lock;
 mod print_message;
 
 use chrono::{DateTime, FixedOffset, Utc};
@@ -6,8 +10,23 @@ use clap::*;
 use csv::Writer;
 use serde_json::*;
 use std::fs;
+use std::io::{BufRead, Write};
 use std::process;
 
+// CSVファイルの形式のバージョン
+// 1: v0.1.0からv0.2.0までのdlmで書き出された、バージョンの記録が無いもの
+// 2: 1行目にバージョンを記録し、列の位置ではなくヘッダーの名前で値を読み取るようにしたもの
+// 3: 各行に直前の行のハッシュとその行のハッシュを記録し、改ざんを検出できるようにしたもの
+// 4: 団体ごとの貸出の制限を超えて貸し出したことを記録する欄を加えたもの
+// 5: 返却時の品の状態とその備考を記録する欄と、修理の操作を加えたもの
+// 6: 操作の備考を記録する欄と、紛失と使用終了の操作を加えたもの
+const CSV_FORMAT_VERSION: usize = 6;
+// ハッシュチェーンを記録するようになったバージョン
+// これ以降のファイルから変換するときは、記録されているハッシュをそのまま使う
+const CSV_FORMAT_VERSION_HASH_CHAIN: usize = 3;
+// 1行目に書き込むバージョンの記録の先頭部分
+const CSV_FORMAT_VERSION_PREFIX: &str = "#dlm-format-version:";
+
 // パスからファイルの中身を読み取ってserde_jsonで提供される関数でデータ化する
 fn path_to_json_data(path: &str) -> Option<Value> {
   let data = fs::read_to_string(path).ok()?;
@@ -15,191 +34,815 @@ fn path_to_json_data(path: &str) -> Option<Value> {
 }
 
 // 一行のCSVデータから一つの貸出返却関係のデータを作る
-fn csv_to_lend_data(csv_record: &csv::StringRecord) -> lib::LendData {
-  // "操作時刻", "どの種類の操作か", "品名", "貸出先", "削除・編集する先の操作番号", "編集後の品名", "編集後の貸出先", "操作番号",
+// 値は列の位置ではなくヘッダーの名前で取り出すので、列の並びが変わったり列が増えたりしても読める
+// 手で書き換えられた行などで必要な値が読み取れない場合は、その理由を返す
+fn csv_to_lend_data(
+  csv_record: &csv::StringRecord,
+  header: &csv::StringRecord,
+) -> std::result::Result<lib::LendData, String> {
+  // "操作時刻", "どの種類の操作か", "品名", "貸出先", "削除・編集する先の操作番号", "編集後の品名", "編集後の貸出先", "操作番号", "編集後の時刻", "後日入力", "担当者", "制限超過", "返却時の状態", "状態の備考", "備考"
+  // "編集後の時刻"・"後日入力"・"担当者"はv0.2.0以前のファイルには無いので、無くても読めるようにする
+  // "制限超過"はバージョン3以前のファイルには無い
+  // "返却時の状態"・"状態の備考"はバージョン4以前のファイルには無い
+  // "備考"はバージョン5以前のファイルには無い
+  let get = |name: &str| {
+    header
+      .iter()
+      .position(|header_name| header_name == name)
+      .and_then(|i| csv_record.get(i))
+  };
+  let get_required = |name: &str| get(name).ok_or_else(|| fill("csv.missing_field", &[&name]));
+  let parse_time = |name: &str, s: &str| {
+    DateTime::parse_from_rfc3339(s).map_err(|_| fill("csv.invalid_field", &[&name, &s]))
+  };
+  let parse_num = |name: &str| -> std::result::Result<isize, String> {
+    let s = get_required(name)?;
+    s.parse()
+      .map_err(|_| fill("csv.invalid_field", &[&name, &s]))
+  };
   // 時刻と操作番号は全ての操作に置いて必要なので先に取得してそれぞれのデータに直す
-  let time: DateTime<FixedOffset> =
-    DateTime::parse_from_rfc3339(csv_record.get(0).unwrap()).unwrap();
-  let num: isize = csv_record.get(7).unwrap().parse().unwrap();
-  let lend_type_str: &str = &csv_record.get(1).unwrap().to_owned().to_ascii_lowercase();
+  let time: DateTime<FixedOffset> = parse_time("操作時刻", get_required("操作時刻")?)?;
+  let num: isize = parse_num("操作番号")?;
+  let lend_type_raw = get_required("どの種類の操作か")?;
+  let lend_type_str: &str = &lend_type_raw.to_ascii_lowercase();
   // 操作の中身によって取り出す値を変える
   let lend_type = match lend_type_str {
     "lend" => {
       // 貸出：「貸し出した品名」と「貸出先」
-      let product = csv_record.get(2).unwrap().to_owned();
-      let destination = csv_record.get(3).unwrap().to_owned();
+      let product = get_required("品名")?.to_owned();
+      let destination = get_required("貸出先")?.to_owned();
       lib::LendType::Lend(product, destination)
     }
     "return" => {
       // 返却：「返された品名」と「返却先」
-      let product = csv_record.get(2).unwrap().to_owned();
-      let destination = csv_record.get(3).unwrap().to_owned();
+      let product = get_required("品名")?.to_owned();
+      let destination = get_required("貸出先")?.to_owned();
       lib::LendType::Return(product, destination)
     }
     "edit" => {
-      // 編集：「編集する操作対象に付けられた通し番号」と「編集後の品名」と「編集後の貸出先」
-      let num = csv_record.get(4).unwrap().parse().unwrap();
-      let new_product = csv_record.get(5).unwrap().to_owned();
-      let new_destination = csv_record.get(6).unwrap().to_owned();
-      lib::LendType::Edit(num, new_product, new_destination)
+      // 編集：「編集する操作対象に付けられた通し番号」と「編集内容」
+      // 空欄の項目は編集しなかったものとして扱う
+      let num = parse_num("削除・編集する先の操作番号")?;
+      let non_empty = |s: &str| {
+        if s.is_empty() {
+          None
+        } else {
+          Some(s.to_owned())
+        }
+      };
+      let new_product = get("編集後の品名").and_then(non_empty);
+      let new_destination = get("編集後の貸出先").and_then(non_empty);
+      let new_time = match get("編集後の時刻").and_then(non_empty) {
+        None => None,
+        Some(s) => Some(parse_time("編集後の時刻", &s)?),
+      };
+      lib::LendType::Edit(
+        num,
+        lib::EditData {
+          product_num: new_product,
+          destination_num: new_destination,
+          time: new_time,
+        },
+      )
     }
     "remove" => {
       // 編集：「削除する操作対象に付けられた通し番号」
-      let num = csv_record.get(4).unwrap().parse().unwrap();
+      let num = parse_num("削除・編集する先の操作番号")?;
       lib::LendType::Remove(num)
     }
-    _ => panic!(),
+    "repair" => {
+      // 修理：「修理した品名」
+      let product = get_required("品名")?.to_owned();
+      lib::LendType::Repair(product)
+    }
+    "lost" | "retire" => {
+      // 紛失・使用終了：「品名」と「そのときの貸出先（貸し出されていなかった場合は空欄）」
+      let product = get_required("品名")?.to_owned();
+      let holder = get("貸出先")
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_owned());
+      if lend_type_str == "lost" {
+        lib::LendType::Lost(product, holder)
+      } else {
+        lib::LendType::Retire(product, holder)
+      }
+    }
+    _ => return Err(fill("csv.unknown_type", &[&lend_type_raw])),
   };
-  lib::LendData {
+  let backfilled = get("後日入力") == Some("true");
+  let overridden = get("制限超過") == Some("true");
+  let condition = get("返却時の状態")
+    .and_then(lib::ConditionKind::from_name)
+    .map(|kind| lib::ReturnCondition {
+      kind,
+      note: get("状態の備考")
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_owned()),
+    });
+  let note = get("備考").filter(|s| !s.is_empty()).map(|s| s.to_owned());
+  let operator = get("担当者")
+    .filter(|s| !s.is_empty())
+    .map(|s| s.to_owned());
+  Ok(lib::LendData {
     time,
     lend_type,
     num,
-  }
+    backfilled,
+    overridden,
+    condition,
+    note,
+    operator,
+  })
 }
 
-fn csv_data_to_lend_data(csv_data: Vec<csv::StringRecord>) -> Vec<lib::LendData> {
-  csv_data.iter().map(|r| csv_to_lend_data(r)).collect()
+#[test]
+fn check_csv_to_lend_data() {
+  let header = csv::StringRecord::from(vec![
+    "操作時刻",
+    "どの種類の操作か",
+    "品名",
+    "貸出先",
+    "操作番号",
+  ]);
+  let record = |v: Vec<&str>| csv::StringRecord::from(v);
+  assert!(csv_to_lend_data(
+    &record(vec!["2020-11-23T17:40:00+09:00", "Lend", "0001", "12", "1"]),
+    &header
+  )
+  .is_ok());
+  // 書き換えられた行は、読み取れない理由を返す
+  assert_eq!(
+    csv_to_lend_data(
+      &record(vec!["2020-11-23T17:40:00+09:00", "Lend", "0001", "12", "x"]),
+      &header
+    )
+    .map(|_| ()),
+    Err(fill("csv.invalid_field", &[&"操作番号", &"x"]))
+  );
+  assert!(csv_to_lend_data(
+    &record(vec![
+      "2020-11-23T17:40:00+09:00",
+      "Borrow",
+      "0001",
+      "12",
+      "1"
+    ]),
+    &header
+  )
+  .is_err());
+  assert!(csv_to_lend_data(&record(vec!["2020-11-23T17:40:00+09:00"]), &header).is_err());
+}
+
+// CSVファイルの読み取れなかった行
+#[derive(Debug, Clone)]
+struct CsvRowError {
+  // ファイルの何行目か
+  line: u64,
+  // 読み取れなかった理由
+  reason: String,
 }
 
 // 受け取ったCSVファイルのパスからCSVデータを取り出し、
 // StringRecordのリストに直した後に貸出返却のデータ群に変換をかける
-fn csv_file_name_to_lend_data(file_name: String) -> Vec<lib::LendData> {
-  let csv_reader_r = csv::Reader::from_path(file_name);
+// 1行目のバージョンの記録は'#'から始まるので、コメントとして読み飛ばす
+// 読み取れない行があった場合は、最初の一つの行番号と理由を返す
+fn csv_file_name_to_lend_data(
+  file_name: &str,
+) -> std::result::Result<Vec<lib::LendData>, CsvRowError> {
+  let csv_reader_r = csv::ReaderBuilder::new()
+    .comment(Some(b'#'))
+    .flexible(true)
+    .from_path(file_name);
   match csv_reader_r {
-    Err(_) => Vec::new(),
-    Ok(csv_reader) => {
-      let mut csv_reader = csv_reader;
-      // for文を使ってリスト化
-      let mut csv_data_vec = vec![];
-      for csv_data in csv_reader.records() {
-        csv_data_vec.push(csv_data.unwrap())
+    Err(_) => Ok(Vec::new()),
+    Ok(mut csv_reader) => {
+      let header = csv_reader.headers().cloned().map_err(|err| CsvRowError {
+        line: err.position().map(|pos| pos.line()).unwrap_or(1),
+        reason: err.to_string(),
+      })?;
+      let mut lend_data_lst = Vec::new();
+      for csv_record in csv_reader.records() {
+        let csv_record = csv_record.map_err(|err| CsvRowError {
+          line: err.position().map(|pos| pos.line()).unwrap_or(0),
+          reason: err.to_string(),
+        })?;
+        let lend_data = csv_to_lend_data(&csv_record, &header).map_err(|reason| CsvRowError {
+          line: csv_record.position().map(|pos| pos.line()).unwrap_or(0),
+          reason,
+        })?;
+        lend_data_lst.push(lend_data)
       }
-      csv_data_to_lend_data(csv_data_vec)
+      Ok(lend_data_lst)
+    }
+  }
+}
+
+// CSVファイルの形式のバージョンを1行目から読み取る
+// ファイルが存在しない場合や空の場合はNoneを返す
+// 1行目にバージョンの記録が無い場合はバージョン1とし、
+// 記録が読めない場合は未知の新しい形式とみなす
+fn read_csv_format_version(path: &str) -> Option<usize> {
+  let file = fs::File::open(path).ok()?;
+  let mut first_line = String::new();
+  std::io::BufReader::new(file)
+    .read_line(&mut first_line)
+    .ok()?;
+  let first_line = first_line.trim();
+  if first_line.is_empty() {
+    None
+  } else {
+    match first_line.strip_prefix(CSV_FORMAT_VERSION_PREFIX) {
+      None => Some(1),
+      Some(v) => Some(v.trim().parse().unwrap_or(usize::MAX)),
     }
   }
 }
 
+// 古い形式のCSVファイルを'<ファイル名>.v<バージョン>.bak'という名前で保存する
+// 既に同じ名前のファイルがある場合は時刻を付け加えた名前にする
+// 保存できなかった場合は、元のファイルを書き換えないようにエラーを返す
+fn backup_old_format_file(path: &str, version: usize) -> std::io::Result<String> {
+  let backup_path = {
+    let backup_path = format!("{}.v{}.bak", path, version);
+    if std::path::Path::new(&backup_path).exists() {
+      format!(
+        "{}.v{}.{}.bak",
+        path,
+        version,
+        Utc::now().format("%Y%m%d%H%M%S")
+      )
+    } else {
+      backup_path
+    }
+  };
+  fs::copy(path, &backup_path)?;
+  Ok(backup_path)
+}
+
+// CSVファイルのヘッダーのうち、ハッシュを計算する対象になる欄の名前
+// 並びを変えたり欄を減らしたりすると、記録済みの行のハッシュが合わなくなるので、欄は末尾に加える
+const CSV_HEADER: [&str; 15] = [
+  "操作時刻",
+  "どの種類の操作か",
+  "品名",
+  "貸出先",
+  "削除・編集する先の操作番号",
+  "編集後の品名",
+  "編集後の貸出先",
+  "操作番号",
+  "編集後の時刻",
+  "後日入力",
+  "担当者",
+  "制限超過",
+  "返却時の状態",
+  "状態の備考",
+  "備考",
+];
+// ハッシュチェーンを記録する欄の名前
+const CSV_HEADER_PREV_HASH: &str = "前の操作のハッシュ";
+const CSV_HEADER_HASH: &str = "ハッシュ";
+
+// 貸出返却のデータ一つをCSVファイルの一行分の値に直す
+// 並びはCSV_HEADERと同じ
+fn lend_data_to_record(lend_data: &lib::LendData) -> Vec<String> {
+  let empty_str = String::new();
+  let time_str = lend_data.time.to_rfc3339();
+  let num_str = lend_data.num.to_string();
+  let backfilled_str = if lend_data.backfilled {
+    "true".to_string()
+  } else {
+    empty_str.clone()
+  };
+  let operator_str = lend_data.operator.clone().unwrap_or_default();
+  let overridden_str = if lend_data.overridden {
+    "true".to_string()
+  } else {
+    empty_str.clone()
+  };
+  let mut record = match &lend_data.lend_type {
+    // 貸出：「貸し出した品名」と「貸出先」
+    lib::LendType::Lend(product_name, destination) => vec![
+      time_str,
+      "Lend".to_string(),
+      product_name.clone(),
+      destination.clone(),
+      empty_str.clone(),
+      empty_str.clone(),
+      empty_str.clone(),
+      num_str,
+      empty_str,
+      backfilled_str,
+      operator_str,
+    ],
+    // 返却：「返された品名」と「返却先」
+    lib::LendType::Return(product_name, destination_string) => vec![
+      time_str,
+      "Return".to_string(),
+      product_name.clone(),
+      destination_string.clone(),
+      empty_str.clone(),
+      empty_str.clone(),
+      empty_str.clone(),
+      num_str,
+      empty_str,
+      backfilled_str,
+      operator_str,
+    ],
+    // 編集：「編集する操作対象に付けられた通し番号」と「編集内容」
+    // 編集しなかった項目は空欄にする
+    lib::LendType::Edit(num, edit_data) => vec![
+      time_str,
+      "Edit".to_string(),
+      empty_str.clone(),
+      empty_str.clone(),
+      num.to_string(),
+      edit_data.product_num.clone().unwrap_or_default(),
+      edit_data.destination_num.clone().unwrap_or_default(),
+      num_str,
+      edit_data
+        .time
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default(),
+      backfilled_str,
+      operator_str,
+    ],
+    // 削除：「削除する操作対象に付けられた通し番号」
+    lib::LendType::Remove(num) => vec![
+      time_str,
+      "Remove".to_string(),
+      empty_str.clone(),
+      empty_str.clone(),
+      num.to_string(),
+      empty_str.clone(),
+      empty_str.clone(),
+      num_str,
+      empty_str,
+      backfilled_str,
+      operator_str,
+    ],
+    // 修理：「修理した品名」
+    lib::LendType::Repair(product_name) => vec![
+      time_str,
+      "Repair".to_string(),
+      product_name.clone(),
+      empty_str.clone(),
+      empty_str.clone(),
+      empty_str.clone(),
+      empty_str.clone(),
+      num_str,
+      empty_str,
+      backfilled_str,
+      operator_str,
+    ],
+    // 紛失・使用終了：「品名」と「そのときの貸出先（貸し出されていなかった場合は空欄）」
+    lib::LendType::Lost(product_name, holder) | lib::LendType::Retire(product_name, holder) => {
+      vec![
+        time_str,
+        if matches!(lend_data.lend_type, lib::LendType::Lost(_, _)) {
+          "Lost".to_string()
+        } else {
+          "Retire".to_string()
+        },
+        product_name.clone(),
+        holder.clone().unwrap_or_default(),
+        empty_str.clone(),
+        empty_str.clone(),
+        empty_str.clone(),
+        num_str,
+        empty_str,
+        backfilled_str,
+        operator_str,
+      ]
+    }
+  };
+  // バージョン4で加えた欄
+  record.push(overridden_str);
+  // バージョン5で加えた欄
+  match &lend_data.condition {
+    None => {
+      record.push(String::new());
+      record.push(String::new());
+    }
+    Some(condition) => {
+      record.push(condition.kind.name().to_string());
+      record.push(condition.note.clone().unwrap_or_default());
+    }
+  }
+  // バージョン6で加えた欄
+  record.push(lend_data.note.clone().unwrap_or_default());
+  record
+}
+
+// CSVファイルからハッシュチェーンの検査に使う生のデータを読み取る
+// 値はヘッダーの名前で取り出し、無い欄は空欄として扱う
+// 読み取れない行があった場合は、最初の一つの行番号と理由を返す
+fn csv_file_name_to_chain_record(
+  file_name: &str,
+) -> std::result::Result<Vec<lib::ChainRecord>, CsvRowError> {
+  let csv_reader_r = csv::ReaderBuilder::new()
+    .comment(Some(b'#'))
+    .flexible(true)
+    .from_path(file_name);
+  match csv_reader_r {
+    Err(_) => Ok(Vec::new()),
+    Ok(mut csv_reader) => {
+      let header = csv_reader.headers().cloned().map_err(|err| CsvRowError {
+        line: err.position().map(|pos| pos.line()).unwrap_or(1),
+        reason: err.to_string(),
+      })?;
+      let mut v = Vec::new();
+      for csv_record in csv_reader.records() {
+        let csv_record = csv_record.map_err(|err| CsvRowError {
+          line: err.position().map(|pos| pos.line()).unwrap_or(0),
+          reason: err.to_string(),
+        })?;
+        let get = |name: &str| {
+          header
+            .iter()
+            .position(|header_name| header_name == name)
+            .and_then(|i| csv_record.get(i))
+            .unwrap_or("")
+            .to_string()
+        };
+        v.push(lib::ChainRecord {
+          line: csv_record.position().map(|pos| pos.line()).unwrap_or(0),
+          num: get("操作番号"),
+          fields: CSV_HEADER.iter().map(|name| get(name)).collect(),
+          prev_hash: get(CSV_HEADER_PREV_HASH),
+          hash: get(CSV_HEADER_HASH),
+        })
+      }
+      Ok(v)
+    }
+  }
+}
+
+// 書き出すときに記録済みのハッシュを読み取る
+// 読み取れない行があった場合は、書き出しに失敗したものとして扱う
+fn read_chain_record_for_output(path: &str) -> std::io::Result<Vec<lib::ChainRecord>> {
+  csv_file_name_to_chain_record(path).map_err(|err| {
+    std::io::Error::new(
+      std::io::ErrorKind::InvalidData,
+      fill("verify.invalid_row", &[&err.line, &err.reason]),
+    )
+  })
+}
+
 // 貸出返却のデータを受け取ってCSVファイルの中身を作成し、実際に出力するところまで行う
-fn lend_data_lst_to_output(path: &str, csv_data_lst: Vec<lib::LendData>) {
-  let empty_str = "";
-  let mut wtr = Writer::from_path(path).unwrap();
+// 常に最新の形式で書き出すので、古い形式のファイルだった場合は書き出す前に元のファイルを保存しておく
+// 既にハッシュが記録されている行はそのハッシュをそのまま書き出し、新しく追加された行にだけハッシュを計算する
+// 書き換えられた行のハッシュを計算し直してしまうと、書き換えを検出できなくなるため
+// 書き込めなかった場合は元のファイルをそのまま残してエラーを返す
+fn lend_data_lst_to_output(path: &str, csv_data_lst: &[lib::LendData]) -> std::io::Result<()> {
+  let (chain, migrated_opt) = match read_csv_format_version(path) {
+    Some(version) if version < CSV_FORMAT_VERSION => {
+      let backup_path = backup_old_format_file(path, version)?;
+      let chain = if version < CSV_FORMAT_VERSION_HASH_CHAIN {
+        Vec::new()
+      } else {
+        read_chain_record_for_output(path)?
+      };
+      (chain, Some((version, backup_path)))
+    }
+    _ => (read_chain_record_for_output(path)?, None),
+  };
+  // 読み込み中の他のdlmが書きかけのファイルを読まないように、
+  // 一時ファイルに書き出してから置き換える
+  let tmp_path = format!("{}.tmp", path);
+  if let Err(err) =
+    write_csv_file(&tmp_path, csv_data_lst, &chain).and_then(|_| fs::rename(&tmp_path, path))
+  {
+    let _ = fs::remove_file(&tmp_path);
+    return Err(err);
+  }
+  if let Some((version, backup_path)) = migrated_opt {
+    print_message::print_migrate_success(version, CSV_FORMAT_VERSION, &backup_path);
+  }
+  Ok(())
+}
+
+// 最新の形式のCSVファイルを書き出す
+// chainにある行はそのハッシュを使い、それ以降の行はハッシュを計算する
+fn write_csv_file(
+  path: &str,
+  csv_data_lst: &[lib::LendData],
+  chain: &[lib::ChainRecord],
+) -> std::io::Result<()> {
+  let mut file = fs::File::create(path)?;
+  // 1行目に形式のバージョンを記録する
+  writeln!(file, "{}{}", CSV_FORMAT_VERSION_PREFIX, CSV_FORMAT_VERSION)?;
+  let mut wtr = Writer::from_writer(file);
   // ヘッダー部分
-  wtr
-    .write_record(&[
-      "操作時刻",
-      "どの種類の操作か",
-      "品名",
-      "貸出先",
-      "削除・編集する先の操作番号",
-      "編集後の品名",
-      "編集後の貸出先",
-      "操作番号",
-    ])
-    .unwrap();
+  let mut header = CSV_HEADER.to_vec();
+  header.push(CSV_HEADER_PREV_HASH);
+  header.push(CSV_HEADER_HASH);
+  wtr.write_record(header)?;
   // 貸出返却のデータは一つずつ書き込んでいく
-  for lend_data in csv_data_lst.iter() {
-    let time = lend_data.clone().time;
-    let time_str = &time.to_rfc3339();
-    let num_str = &lend_data.num.to_string();
-    let lend_type = lend_data.clone().lend_type;
-    match lend_type {
-      // 貸出：「貸し出した品名」と「貸出先」
-      lib::LendType::Lend(product_name, destination) => {
-        wtr
-          .write_record(&[
-            time_str,
-            "Lend",
-            &product_name,
-            &destination,
-            empty_str,
-            empty_str,
-            empty_str,
-            num_str,
-          ])
-          .unwrap();
+  let mut prev_hash = String::new();
+  for (i, lend_data) in csv_data_lst.iter().enumerate() {
+    let mut record = lend_data_to_record(lend_data);
+    let (record_prev_hash, hash) = match chain.get(i) {
+      Some(chain_record) => (chain_record.prev_hash.clone(), chain_record.hash.clone()),
+      None => (prev_hash.clone(), lib::hash_record(&prev_hash, &record)),
+    };
+    record.push(record_prev_hash);
+    record.push(hash.clone());
+    wtr.write_record(&record)?;
+    prev_hash = hash;
+  }
+  // 書き終えたらこれで終了させる
+  wtr.flush()
+}
+
+// 読み込み済みの貸出状況と、読み込んだときのCSVファイルの目印
+// CSVファイルが変わっていなければ、コマンドごとに読み込み直さずに使い回す
+struct LedgerCache {
+  stamp: Option<lock::DataFileStamp>,
+  ledger: lib::Ledger,
+  // 読み取れない行があった場合はその行
+  // そのまま書き出すとその行が失われるので、直されるまでは書き込まない
+  invalid_row: Option<CsvRowError>,
+}
+
+// CSVファイルが最後に読み込んだときから変わっていれば読み込み直して、貸出状況を返す
+// 読み取れない行があった場合はそのことを表示し、空の貸出状況を返す
+fn load_ledger<'a>(
+  data_file_name: &str,
+  cache: &'a mut Option<LedgerCache>,
+) -> &'a mut lib::Ledger {
+  let stamp = lock::data_file_stamp(data_file_name);
+  if cache.as_ref().is_none_or(|cache| cache.stamp != stamp) {
+    let (ledger, invalid_row) = match csv_file_name_to_lend_data(data_file_name) {
+      Ok(lend_data_lst) => (lib::Ledger::new(lend_data_lst), None),
+      Err(err) => {
+        print_message::print_invalid_row(err.line, &err.reason);
+        (lib::Ledger::new(Vec::new()), Some(err))
       }
-      // 返却：「返された品名」と「返却先」
-      lib::LendType::Return(product_name, destination_string) => {
-        wtr
-          .write_record(&[
-            time_str,
-            "Return",
-            &product_name,
-            &destination_string,
-            empty_str,
-            empty_str,
-            empty_str,
-            num_str,
-          ])
-          .unwrap();
-      }
-      // 編集：「編集する操作対象に付けられた通し番号」と「編集後の品名」と「編集後の貸出先」
-      lib::LendType::Edit(num, new_product_name, new_destination_string) => {
-        wtr
-          .write_record(&[
-            time_str,
-            "Edit",
-            empty_str,
-            empty_str,
-            &num.to_string(),
-            &new_product_name,
-            &new_destination_string,
-            num_str,
-          ])
-          .unwrap();
-      }
-      // 削除：「削除する操作対象に付けられた通し番号」
-      lib::LendType::Remove(num) => {
-        wtr
-          .write_record(&[
-            time_str,
-            "Remove",
-            empty_str,
-            empty_str,
-            &num.to_string(),
-            empty_str,
-            empty_str,
-            num_str,
-          ])
-          .unwrap();
+    };
+    *cache = Some(LedgerCache {
+      stamp,
+      ledger,
+      invalid_row,
+    })
+  }
+  &mut cache.as_mut().unwrap().ledger
+}
+
+// CSVファイルに読み取れない行があるかを、読み込み直してから調べる
+fn has_invalid_row(data_file_name: &str, cache: &mut Option<LedgerCache>) -> bool {
+  load_ledger(data_file_name, cache);
+  cache
+    .as_ref()
+    .is_some_and(|cache| cache.invalid_row.is_some())
+}
+
+// 確認を待っている間に他の端末でCSVファイルが更新されていた場合は、そのことを伝えてから読み込み直す
+fn reload_ledger_if_changed<'a>(
+  data_file_name: &str,
+  cache: &'a mut Option<LedgerCache>,
+) -> &'a mut lib::Ledger {
+  if cache
+    .as_ref()
+    .is_some_and(|cache| cache.stamp != lock::data_file_stamp(data_file_name))
+  {
+    print_message::print_data_file_changed();
+  }
+  load_ledger(data_file_name, cache)
+}
+
+// 貸出状況に追加した操作をCSVファイルに書き出し、書き出した後の目印を記録する
+// 書き出せなかった場合はそのことを表示してfalseを返す
+// 追加した操作は記録されなかったことになるので、次に使うときにCSVファイルから読み込み直す
+fn output_ledger(data_file_name: &str, cache: &mut Option<LedgerCache>) -> bool {
+  let result = match cache {
+    None => return true,
+    Some(cache) => lend_data_lst_to_output(data_file_name, cache.ledger.lend_data_lst()),
+  };
+  match result {
+    Ok(()) => {
+      if let Some(cache) = cache {
+        cache.stamp = lock::data_file_stamp(data_file_name);
       }
+      true
+    }
+    Err(err) => {
+      print_message::print_data_file_write_failed(data_file_name, &err);
+      *cache = None;
+      false
     }
   }
-  // 書き終えたらこれで終了させる
-  wtr.flush().unwrap()
 }
 
-// 貸出の重複や貸し出していないものの返却などを見つけるために、
-// 「貸出す予定もしくは返却される予定の品名」とデータの中身の貸出品名が一致するかを探る関数
-// データの中身がそもそもとして貸出以外の操作であった場合は「一致しない」を返すようにしている
-fn check_lend_product_num(data: &lib::LendData, product_num: &str) -> bool {
-  match data.clone().lend_type {
-    lib::LendType::Lend(data_product_num, _) => data_product_num == *product_num,
-    _ => false,
-  }
+// ハッシュチェーンの検査に使う生のデータを、空欄以外の欄を並べた文字列にする
+fn chain_record_to_str(record: &lib::ChainRecord) -> String {
+  CSV_HEADER
+    .iter()
+    .zip(record.fields.iter())
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(name, value)| fill("verify.field", &[name, value]))
+    .collect::<Vec<String>>()
+    .join(text("list_separator"))
 }
 
-// 貸出先と返却先が一致しているかチェックする
-// 一致していたらtrue
-// 一致していなかったり対象がなかったりしたらfalse
-fn check_return_destination_num(
-  data_lst: &[lib::LendData],
-  product_num: &str,
-  destination_num: &str,
-) -> bool {
-  match data_lst
+// バックアップの中から、指定したハッシュが記録されている行を新しいバックアップから順に探す
+// 読み取れない行があるバックアップは飛ばす
+fn find_chain_record_in_backup(
+  backup_config: &backup::BackupConfig,
+  data_file_name: &str,
+  hash: &str,
+) -> Option<(String, lib::ChainRecord)> {
+  backup::snapshot_lst(backup_config, data_file_name)
     .iter()
-    .find(|data| check_lend_product_num(data, &product_num))
+    .rev()
+    .find_map(|snapshot| {
+      csv_file_name_to_chain_record(&snapshot.path.to_string_lossy())
+        .ok()?
+        .into_iter()
+        .find(|record| record.hash == hash)
+        .map(|record| (snapshot.id.clone(), record))
+    })
+}
+
+// ハッシュチェーンを検査して結果を表示する
+// 途切れている箇所があった場合は最初の一つについて、
+// バックアップから元の内容を探して何が変わったのかを表示する
+fn print_verify_hash_chain(data_file_name: &str, backup_config: &backup::BackupConfig) {
+  // 手で書き換えられた行は、ハッシュを調べる前にそもそも読み取れない場合がある
+  let invalid_row_opt = csv_file_name_to_lend_data(data_file_name).err();
+  if let Some(err) = &invalid_row_opt {
+    eprintln!(
+      "- {}",
+      fill("verify.invalid_row", &[&err.line, &err.reason])
+    );
+  }
+  if read_csv_format_version(data_file_name)
+    .is_some_and(|version| version < CSV_FORMAT_VERSION_HASH_CHAIN)
   {
-    Some(data) => match data.clone().lend_type {
-      lib::LendType::Lend(_, n) => n == destination_num,
-      _ => false,
-    },
-    None => false,
+    println!("{}", text("verify.no_hash"));
+    return;
+  }
+  // CSVファイルとして読み取れない行があると、ハッシュを調べられないのでそこで検査をやめる
+  // 同じ行を上で表示している場合は重ねて表示しない
+  let chain = match csv_file_name_to_chain_record(data_file_name) {
+    Ok(chain) => chain,
+    Err(err) => {
+      if invalid_row_opt.is_none_or(|invalid_row| invalid_row.line != err.line) {
+        eprintln!(
+          "- {}",
+          fill("verify.invalid_row", &[&err.line, &err.reason])
+        );
+      }
+      return;
+    }
+  };
+  match lib::verify_hash_chain(&chain) {
+    None => println!("{}", fill("verify.ok", &[&chain.len()])),
+    Some(lib::ChainBreak::MissingHash(record)) => {
+      eprintln!(
+        "- {}",
+        fill(
+          "verify.missing_hash",
+          &[&record.line, &record.num, &chain_record_to_str(&record)]
+        )
+      );
+    }
+    Some(lib::ChainBreak::LinkBroken(record)) => {
+      eprintln!(
+        "- {}",
+        fill(
+          "verify.link_broken",
+          &[&record.line, &record.num, &chain_record_to_str(&record)]
+        )
+      );
+      match find_chain_record_in_backup(backup_config, data_file_name, &record.prev_hash) {
+        None => eprintln!("  {}", text("verify.prev_not_found")),
+        Some((id, prev_record)) => eprintln!(
+          "  {}",
+          fill(
+            "verify.prev_found",
+            &[&id, &chain_record_to_str(&prev_record)]
+          )
+        ),
+      }
+    }
+    Some(lib::ChainBreak::ContentChanged(record)) => {
+      eprintln!(
+        "- {}",
+        fill(
+          "verify.content_changed",
+          &[&record.line, &record.num, &chain_record_to_str(&record)]
+        )
+      );
+      match find_chain_record_in_backup(backup_config, data_file_name, &record.hash) {
+        None => eprintln!("  {}", text("verify.original_not_found")),
+        Some((id, original_record)) => {
+          eprintln!("  {}", fill("verify.original_found", &[&id]));
+          for ((name, original), now) in CSV_HEADER
+            .iter()
+            .zip(original_record.fields.iter())
+            .zip(record.fields.iter())
+          {
+            if original != now {
+              eprintln!("    {}", fill("verify.field_diff", &[name, original, now]))
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+// 'undo'と'redo'の共通処理
+// 操作番号のまとまりのリストを受け取り、それぞれの操作を無かったことにする'remove'操作を記録する
+// 記録した'remove'操作の操作番号を、受け取ったまとまりごとにまとめたリストを返す
+// 確認で中止した場合はNoneを、取り消せない操作が含まれていた場合などエラーを表示した場合はErrを返す
+fn remove_session_lend_data(
+  data_file_name: &str,
+  ledger_cache: &mut Option<LedgerCache>,
+  config_data: &lib::ConfigData,
+  target_lst: &[Vec<isize>],
+  session_num_lst: &[isize],
+  operator_opt: &Option<String>,
+) -> std::result::Result<Option<Vec<Vec<isize>>>, ()> {
+  let ledger = load_ledger(data_file_name, ledger_cache);
+  let target_num_lst: Vec<isize> = target_lst.iter().flatten().cloned().collect();
+  // 取り消す対象の操作を表示する
+  // 他の端末で行われた操作が積み重なっていたら取り消さない
+  match ledger.check_undo(&target_num_lst, session_num_lst) {
+    Err(err @ lib::LedgerError::HasDependent { .. }) => {
+      for data in target_num_lst
+        .iter()
+        .filter_map(|num| ledger.state().get(*num))
+      {
+        println!(
+          "{}",
+          lib::lend_data_to_message_with_config_data(data, config_data)
+        )
+      }
+      print_message::print_ledger_error(&err, config_data);
+      return Err(());
+    }
+    Err(err) => {
+      print_message::print_ledger_error(&err, config_data);
+      return Err(());
+    }
+    Ok(data_lst) => {
+      for data in data_lst.iter() {
+        println!(
+          "{}",
+          lib::lend_data_to_message_with_config_data(data, config_data)
+        )
+      }
+    }
+  }
+  println!("{}", text("undo.confirm"));
+  let mut s = String::new();
+  std::io::stdin().read_line(&mut s).ok();
+  let s: &str = &s.trim().to_owned().to_ascii_lowercase();
+  match s {
+    "n" => {
+      println!("{}", text("cancelled"));
+      Ok(None)
+    }
+    _ => {
+      // 確認している間に他の端末で操作が行われていたら、読み込み直して検査し直す
+      let _lock = match lock::lock_data_file(data_file_name) {
+        None => {
+          print_message::print_data_file_locked();
+          return Err(());
+        }
+        Some(lock) => lock,
+      };
+      let ledger = reload_ledger_if_changed(data_file_name, ledger_cache);
+      let removed_num_lst =
+        match ledger.undo(&target_num_lst, session_num_lst, operator_opt.as_deref()) {
+          Err(err) => {
+            print_message::print_ledger_error(&err, config_data);
+            return Err(());
+          }
+          Ok(removed_num_lst) => removed_num_lst,
+        };
+      // コマンドごとのまとまりに分け直す
+      let mut removed_iter = removed_num_lst.into_iter();
+      let removed_lst: Vec<Vec<isize>> = target_lst
+        .iter()
+        .map(|target| removed_iter.by_ref().take(target.len()).collect())
+        .collect();
+      if !output_ledger(data_file_name, ledger_cache) {
+        return Err(());
+      }
+      for (target, removed) in target_lst.iter().zip(removed_lst.iter()) {
+        for (num, lend_num) in target.iter().zip(removed.iter()) {
+          print_message::print_remove_success(num, lend_num)
+        }
+      }
+      Ok(Some(removed_lst))
+    }
   }
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn check_regex() {
   use regex::Regex;
   let re1 = Regex::new("\\d{4}").unwrap();
@@ -222,28 +865,197 @@ fn check_regex() {
   assert_eq!(false, re.is_match("1"));
 }
 
+// コマンドを入力するときのプロンプト
+const PROMPT: &str = "> ";
+// 'scan'モードで番号を読み取るときのプロンプト
+const SCAN_PROMPT: &str = "scan> ";
+
+// 操作番号のリストから貸出票を作り、貸出票のディレクトリに保存する
+// 形式が与えられていない場合は設定ファイルの形式にする
+// 作れなかった場合はエラーを表示してfalseを返す
+fn issue_receipt(
+  ledger: &lib::Ledger,
+  num_lst: &[isize],
+  format_opt: Option<lib::receipt::ReceiptFormat>,
+  receipt_config: &lib::receipt::ReceiptConfig,
+  config_data: &lib::ConfigData,
+) -> bool {
+  let receipt = match lib::receipt::make_receipt(ledger.lend_data_lst(), num_lst) {
+    Err(err) => {
+      print_message::print_receipt_error(&err);
+      return false;
+    }
+    Ok(receipt) => receipt,
+  };
+  let format = format_opt.unwrap_or(receipt_config.format);
+  let issued = Utc::now().with_timezone(&FixedOffset::east(9 * 3600));
+  let bytes = match format {
+    lib::receipt::ReceiptFormat::Text => {
+      lib::receipt::render_text(&receipt, config_data, issued).into_bytes()
+    }
+    lib::receipt::ReceiptFormat::Html => {
+      lib::receipt::render_html(&receipt, config_data, issued).into_bytes()
+    }
+    lib::receipt::ReceiptFormat::Pdf => lib::receipt::render_pdf(&receipt, config_data, issued),
+  };
+  let path = receipt_config
+    .dir
+    .join(lib::receipt::receipt_file_name(&receipt, format));
+  match fs::create_dir_all(&receipt_config.dir).and_then(|_| fs::write(&path, bytes)) {
+    Err(_) => {
+      print_message::print_receipt_write_failed(&path.to_string_lossy());
+      false
+    }
+    Ok(_) => {
+      print_message::print_receipt_success(&path.to_string_lossy());
+      true
+    }
+  }
+}
+
+// SVGはページごとにファイルを分ける
+// 2ページ以上になる場合は'labels-1.svg'・'labels-2.svg'のように番号を付ける
+fn svg_file_name_lst(out: &str, page_count: usize) -> Vec<String> {
+  if page_count == 1 {
+    vec![out.to_string()]
+  } else {
+    let path = std::path::Path::new(out);
+    let stem = path
+      .file_stem()
+      .map(|s| s.to_string_lossy().to_string())
+      .unwrap_or_default();
+    (1..=page_count)
+      .map(|i| {
+        path
+          .with_file_name(format!("{}-{}.svg", stem, i))
+          .to_string_lossy()
+          .to_string()
+      })
+      .collect()
+  }
+}
+
+// 'scan'モードでスキャナーから一行を受け取る
+// 入力が終わった場合はNoneを返し、Ctrl-Cの場合は空行と同じにする
+fn read_scan_line(editor_opt: &mut Option<editor::DlmEditor>) -> Option<String> {
+  match editor_opt {
+    None => {
+      let mut s = String::new();
+      match std::io::stdin().read_line(&mut s) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(s),
+      }
+    }
+    Some(editor) => match editor.readline(SCAN_PROMPT) {
+      Ok(s) => Some(s),
+      Err(rustyline::error::ReadlineError::Interrupted) => Some(String::new()),
+      Err(_) => None,
+    },
+  }
+}
+
+// 直前の入力がエラーになったことを、'history --failed'のために履歴に記録する
+fn mark_last_failed(history_lst: &mut [lib::HistoryEntry]) {
+  if let Some(entry) = history_lst.last_mut() {
+    entry.failed = true
+  }
+}
+
+// 設定ファイルのタグに一つでも登録があるか
+fn has_config_entry(json: &serde_json::Value) -> bool {
+  json.as_object().is_some_and(|map| !map.is_empty())
+}
+
+// 引数の中から'--lang <言語>'か'--lang=<言語>'の値を探す
+// clapで引数を処理する前に、その説明を表示する言語を決めるために使う
+fn find_lang_arg(args: &[String]) -> Option<String> {
+  let mut iter = args.iter().skip(1);
+  while let Some(arg) = iter.next() {
+    if arg == "--" {
+      break;
+    } else if arg == "--lang" {
+      return iter.next().cloned();
+    } else if let Some(value) = arg.strip_prefix("--lang=") {
+      return Some(value.to_string());
+    }
+  }
+  None
+}
+
+#[test]
+fn check_find_lang_arg() {
+  let args = |lst: &[&str]| lst.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+  assert_eq!(
+    find_lang_arg(&args(&["dlm", "--lang", "en", "--help"])),
+    Some("en".to_string())
+  );
+  assert_eq!(
+    find_lang_arg(&args(&["dlm", "data.csv", "--lang=ja"])),
+    Some("ja".to_string())
+  );
+  assert_eq!(find_lang_arg(&args(&["dlm", "--help"])), None);
+}
+
 // mainの関数
 // ソフトウェアが実行された場合、この関数が実行され、他の関数を次々に呼び出して処理を行っていく
 #[allow(unused_assignments)]
 fn main() {
+  // 表示に使う言語を決める
+  // 引数の説明も訳すため、'--lang'だけは引数の処理より前に読み取る
+  // 設定ファイルの"lang"タグは読み込んだ後に反映させる
+  let lang_arg_opt = find_lang_arg(&std::env::args().collect::<Vec<_>>());
+  let lang_env_opt = std::env::var("LANG").ok();
+  message::set_lang(message::select_lang(
+    lang_arg_opt.as_deref(),
+    None,
+    lang_env_opt.as_deref(),
+  ));
+
   // 引数の処理
   let matches = App::new("dlm")
     .version("0.2.0")
     .author("(C) 2020 149th文化祭準備員会（2021年1月開催）電気係SC")
-    .about("電気係の使用する貸出管理ソフトウェア")
+    .about(text("cli.about"))
     .arg(
       Arg::with_name("data_file_name")
         .value_name("FILE")
-        .help("貸出先や貸出時刻などのデータを記録したファイル（CSV形式）")
+        .help(text("cli.data_file"))
         .takes_value(true)
         .required(true),
     )
+    .arg(
+      Arg::with_name("operator")
+        .short("o")
+        .long("operator")
+        .value_name("NAME")
+        .help(text("cli.operator"))
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("read_only")
+        .long("read-only")
+        .help(text("cli.read_only")),
+    )
+    .arg(
+      Arg::with_name("backup_dir")
+        .long("backup-dir")
+        .value_name("DIR")
+        .help(text("cli.backup_dir"))
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("lang")
+        .long("lang")
+        .value_name("LANG")
+        .help(text("cli.lang"))
+        .takes_value(true),
+    )
     .arg(
       Arg::with_name("config_file_name")
         .short("c")
         .long("config")
         .value_name("FILE")
-        .help("資材や参団の名前の対応ファイル（JSON形式）")
+        .help(text("cli.config"))
         .takes_value(true),
     )
     .get_matches();
@@ -253,42 +1065,101 @@ fn main() {
   let data_file_name = match data_file_name_opt {
     Some(s) => s,
     None => {
-      eprintln!("ファイル名を入力してください！");
+      eprintln!("{}", text("no_data_file_name"));
       process::exit(1)
     }
   };
 
+  // CSVファイルの形式のバージョンを確かめる
+  // このdlmより新しい形式のファイルは読み書きできないので終了する
+  let csv_format_version_opt = read_csv_format_version(data_file_name);
+  if csv_format_version_opt.is_some_and(|version| version > CSV_FORMAT_VERSION) {
+    eprintln!("{}", text("newer_format"));
+    process::exit(1)
+  }
+
   // 対応させる名前が書かれたJSONファイルのパスを受け取る
   // 別に与えられていなくても大丈夫
   // JSONファイルが与えられていないときは空のリストを作成し、
   // JSONファイルが与えられている場合は中身を解析して
   // 品名の対応リストと団体の対応リストをそれぞれ作成してまとめる
   let config_file_name_opt = matches.value_of("config_file_name");
-  let config_data: lib::ConfigData = match config_file_name_opt {
-    None => lib::make_config_data(json!(null), json!(null), json!(null)),
-    Some(config_file_name) => {
-      let json_data = match path_to_json_data(config_file_name) {
-        None => {
-          eprintln!("JSONファイルの読み込み・解析に失敗しました");
-          process::exit(1)
-        }
-        Some(v) => v,
-      };
-      let sizai_json_data = &json_data["sizai"];
-      let sandan_json_data = &json_data["sandan"];
-      let room_json_data = &json_data["room"];
-      lib::make_config_data(
-        sizai_json_data.clone(),
-        sandan_json_data.clone(),
-        room_json_data.clone(),
-      )
-    }
+  let config_json_data = match config_file_name_opt {
+    None => json!(null),
+    Some(config_file_name) => match path_to_json_data(config_file_name) {
+      None => {
+        eprintln!("{}", text("config_error"));
+        process::exit(1)
+      }
+      Some(v) => v,
+    },
   };
+  message::set_lang(message::select_lang(
+    matches.value_of("lang"),
+    config_json_data["lang"].as_str(),
+    lang_env_opt.as_deref(),
+  ));
+  let config_data: lib::ConfigData = lib::make_config_data(
+    config_json_data["sizai"].clone(),
+    config_json_data["sandan"].clone(),
+    config_json_data["room"].clone(),
+  );
+  // 自動バックアップの設定
+  // JSONファイルの"backup"タグと'--backup-dir'オプションから作る
+  let backup_config = backup::make_backup_config(
+    data_file_name,
+    &config_json_data["backup"],
+    matches.value_of("backup_dir"),
+  );
+  // 貸出票の設定
+  let receipt_config =
+    lib::receipt::make_receipt_config(data_file_name, &config_json_data["receipt"]);
+  // 団体ごとの貸出の制限
+  let policy = lib::policy::make_policy(&config_json_data["policy"]);
 
-  // 'history'コマンド用に、入力されたコマンドを記録するための空リストを作成しておく
-  let mut arg_command_history_vec: Vec<String> = Vec::new();
+  // 入力を受け取るための行エディタ
+  // 履歴はCSVファイルの隣に保存され、次に起動したときにも使える
+  // 作れなかった場合は標準入力から一行ずつ読む
+  let mut editor_opt = editor::make_editor(data_file_name, &config_data);
+  let history_file_name = editor::history_file_name(data_file_name);
+  // 'history'コマンド用に、入力されたコマンドを記録するリスト
+  // 以前に起動したときの履歴も含める
+  // 以前の起動時の入力はエラーになったかどうかが分からないので、エラーにならなかったものとして扱う
+  let mut arg_command_history_vec: Vec<lib::HistoryEntry> = match &editor_opt {
+    None => Vec::new(),
+    Some(editor) => editor
+      .history()
+      .iter()
+      .map(|command| lib::HistoryEntry {
+        command: command.clone(),
+        failed: false,
+      })
+      .collect(),
+  };
+  // 'undo'と'redo'用に、このセッションで行った操作の操作番号を記録しておく
+  // 一回のコマンドで行った操作の操作番号を一つのまとまりとして積んでいく
+  let mut undo_stack: Vec<Vec<isize>> = Vec::new();
+  let mut redo_stack: Vec<Vec<isize>> = Vec::new();
+  // 他の端末での操作と区別するために、このセッションで記録した全ての操作番号も記録しておく
+  let mut session_num_lst: Vec<isize> = Vec::new();
+  // 操作を行う担当者の名前
+  // 全ての操作に記録され、'login'コマンドで変更できる
+  let mut operator_opt: Option<String> = matches.value_of("operator").map(|s| s.to_owned());
+  // 読み込み済みの貸出状況
+  let mut ledger_cache: Option<LedgerCache> = None;
+  // 最後にバックアップを取った時点で、このセッションで何回の操作を行っていたか
+  let mut backup_session_num_len = 0;
+  // 読み取り専用モードかどうか
+  // 他のdlmと同時に使う場合でも、書き込むたびにロックを取って読み込み直すので読み取り専用にする必要は無い
+  let read_only = matches.is_present("read_only");
+  // 'backfill'コマンドで後日入力モードに入っているときの時刻
+  let mut backfill_time_opt: Option<DateTime<FixedOffset>> = None;
   // このソフトウェアの目的や役割、リポジトリのURLなどの基本情報を出力する
   print_message::print_start();
+  // 古い形式のCSVファイルの場合は変換されることを伝える
+  if let Some(version) = csv_format_version_opt.filter(|version| *version < CSV_FORMAT_VERSION) {
+    print_message::print_old_format(version, CSV_FORMAT_VERSION)
+  }
   // 対話環境の開始
   // 操作を促すメッセージを表示し、コマンドが入力されたらそれに対応する処理をして反応を返し、
   // 処理が終わったらまた操作を促すメッセージを表示する、というループによって成り立っている
@@ -296,323 +1167,1189 @@ fn main() {
   // 実行時エラーでは以上終了します
   loop {
     // 操作を促すメッセージの表示
+    // 担当者が登録されている場合や後日入力モードの場合はそのことも表示する
+    if let Some(operator) = &operator_opt {
+      print_message::print_operator(operator)
+    }
+    if let Some(backfill_time) = backfill_time_opt {
+      print_message::print_backfill_mode(&backfill_time.format("%Y/%m/%d %H:%M").to_string())
+    }
     print_message::print_restart();
-    // コマンド文字列を標準入力から受け取ります
-    let mut s = String::new();
-    std::io::stdin().read_line(&mut s).ok();
+    // コマンド文字列を受け取ります
+    // 入力が終わった（Ctrl-Dなど）場合は'exit'と同じように終了し、Ctrl-Cの場合は入力をやり直します
+    let s = match &mut editor_opt {
+      None => {
+        let mut s = String::new();
+        match std::io::stdin().read_line(&mut s) {
+          Ok(0) | Err(_) => break,
+          Ok(_) => s,
+        }
+      }
+      Some(editor) => {
+        // 'return'の補完に使うので、貸出中の品名を教えておく
+        if let Some(helper) = editor.helper_mut() {
+          helper.set_lent_lst(&load_ledger(data_file_name, &mut ledger_cache).outstanding())
+        }
+        match editor.readline(PROMPT) {
+          Ok(s) => s,
+          Err(rustyline::error::ReadlineError::Interrupted) => continue,
+          Err(_) => break,
+        }
+      }
+    };
     // 受け取った文字列の前後から改行文字等を削除します
-    s = s.trim().to_owned();
+    let s = s.trim().to_owned();
+    if s.is_empty() {
+      continue;
+    }
+    // '!!'や'!<n>'は履歴の中の入力に置き換え、置き換えた入力を表示してから実行します
+    let s = match lib::expand_history(&s, &arg_command_history_vec) {
+      None => s,
+      Some(Ok(command)) => {
+        println!("{}", command);
+        command
+      }
+      Some(Err(msg)) => {
+        print_message::print_history_not_found(&msg);
+        continue;
+      }
+    };
     // 綺麗にした文字列をコマンドを記録するリストに登録します
-    arg_command_history_vec.push(s.clone());
+    // 履歴のファイルにも書き足しておく
+    arg_command_history_vec.push(lib::HistoryEntry {
+      command: s.clone(),
+      failed: false,
+    });
+    if let Some(editor) = &mut editor_opt {
+      if editor.add_history_entry(s.as_str()).unwrap_or(false) {
+        let _ = editor.append_history(&history_file_name);
+      }
+    }
     // 空白で区切ってリスト化し、コマンドと引数に対応するデータ構造を受け取ります
-    let arg_str_vec: Vec<&str> = s.split_whitespace().collect();
+    // '"'で囲まれた部分は一つの引数として扱います
+    let arg_string_vec = lib::split_arg(&s);
+    let arg_str_vec: Vec<&str> = arg_string_vec.iter().map(|s| s.as_str()).collect();
     let arg = lib::parse_arg(arg_str_vec);
+    // 読み取り専用モードではCSVファイルを書き換えるコマンドを受け付けない
+    if read_only && lib::is_write_command(&arg) {
+      print_message::print_read_only();
+      mark_last_failed(&mut arg_command_history_vec);
+      continue;
+    }
+    // 読み取れない行がある間は、バックアップの復元以外の書き込みを受け付けない
+    if lib::is_write_command(&arg)
+      && !matches!(arg, lib::DlmArg::BackupRestore(_))
+      && has_invalid_row(data_file_name, &mut ledger_cache)
+    {
+      print_message::print_invalid_row_write();
+      mark_last_failed(&mut arg_command_history_vec);
+      continue;
+    }
     // 引数のデータ構造に対応する処理と反応を行います
-    match arg {
+    // それぞれの処理はエラーにならなかったかどうかを返し、'history --failed'のために履歴に記録する
+    let succeeded = match arg {
       // コメントや何も入力されなかったときは何もしないでループを回す
-      lib::DlmArg::Null => (),
+      lib::DlmArg::Null => true,
       // 'exit'はループから脱出して正常終了
       lib::DlmArg::Exit => break,
       // helpメッセージを表示
-      lib::DlmArg::Help => print_message::print_help(),
+      lib::DlmArg::Help => {
+        print_message::print_help();
+        true
+      }
       // 使えるコマンドではないというメッセージを表示して再度の入力を促す
-      lib::DlmArg::NotFoundCommandName(name) => print_message::print_not_found_command_name(name),
+      lib::DlmArg::NotFoundCommandName(name) => {
+        print_message::print_not_found_command_name(name);
+        false
+      }
       // 「引数の数や型が間違っている」ということを伝えて再度の入力を促す
-      lib::DlmArg::MissingArgument(msg) => print_message::print_missing_argument(msg),
+      lib::DlmArg::MissingArgument(msg) => {
+        print_message::print_missing_argument(msg);
+        false
+      }
       // 記録していたコマンド文字列を表示する
-      lib::DlmArg::History(n) => print_message::print_history(&arg_command_history_vec, n),
+      lib::DlmArg::History(n, failed_only) => {
+        print_message::print_history(&arg_command_history_vec, n, failed_only);
+        true
+      }
       // データを記録していたCSVファイルを読み込んでデータ群を抜き出し、
       // ヘッダーを出力した後に、データから作成した文字列を出力する
-      lib::DlmArg::Show(re_opt) => {
-        let lend_data = csv_file_name_to_lend_data(data_file_name.to_owned());
-        let (lend_data_str, product_str_len_max) =
-          lib::make_lend_data_str(lend_data, config_data.clone(), re_opt);
-        println!(
-          "操作番号   時刻               貸出品{}   貸出先（団体名）（場所）",
-          " ".repeat(product_str_len_max - 6)
+      lib::DlmArg::Show(category_opt, re_opt) => {
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        let now_lend_data_lst = ledger.outstanding();
+        let (lend_data_str, product_str_len_max) = lib::make_lend_data_str(
+          &now_lend_data_lst,
+          &config_data,
+          category_opt.as_deref(),
+          re_opt,
+        );
+        let item_header = text("show.item");
+        let item_header = format!(
+          "{}{}",
+          item_header,
+          " ".repeat(product_str_len_max - lib::get_char_len(item_header))
         );
-        println!("{}", lend_data_str)
+        println!("{}", fill("show.header", &[&item_header]));
+        println!("{}", lend_data_str);
+        // 種類ごとに、全体のうちいくつが貸し出されているかを表示する
+        // 種類を指定しない場合は、貸し出されているものがある種類だけにする
+        let summary_lst = lib::catalog::make_category_summary_lst(
+          &now_lend_data_lst,
+          &ledger.retired(),
+          &config_data,
+          category_opt.as_deref(),
+        );
+        for summary in summary_lst.iter() {
+          if category_opt.is_some() || summary.out > 0 {
+            print_message::print_category_summary(summary)
+          }
+        }
+        true
+      }
+      lib::DlmArg::Catalog(category_opt) => {
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        print!(
+          "{}",
+          lib::catalog::render_text(
+            &ledger.outstanding(),
+            &ledger.retired(),
+            &config_data,
+            category_opt.as_deref()
+          )
+        );
+        true
       }
       // データを記録していたCSVファイルを読み込んでデータ群を抜き出し、
       // 実際に貸出と返却の処理を仮想的に行いながら二重貸出等の間違いを探す
       // 間違いが検出されたらその中身を出力し、全てのデータについて検査し終わったら終了
-      lib::DlmArg::Check => {
-        println!("検査を開始します\n--- --- ---\n");
-        // 操作の削除や編集を反映し終えて貸出と返却のみで構成されたデータ群を作成する
-        let lend_data_lst =
-          lib::organize_lend_data(&csv_file_name_to_lend_data(data_file_name.to_owned()));
-        // 現在貸し出されている品名を記録するためのリスト
-        let mut lend_stack: Vec<&lib::LendData> = Vec::new();
-        // 全ての操作を順番に行う
-        for lend_data in lend_data_lst.iter() {
-          let lend_type = &lend_data.lend_type;
-          match lend_type {
-            lib::LendType::Lend(product_num, _) => {
-              // これから貸し出そうとする品名が既に貸し出したものを記録したリストに無いかをチェックする
-              // 存在したらメッセージを出力し、とりあえず再度登録しなおす
-              match lend_stack
-                .iter()
-                .find(|data| check_lend_product_num(data, product_num))
-              {
-                None => (),
-                Some(_) => eprintln!("- {}が2重に貸し出されています\n", product_num),
+      lib::DlmArg::Check(category_opt) => {
+        println!("{}\n--- --- ---\n", text("check.start"));
+        // 操作の削除や編集を反映し終えた貸出と返却を時刻順に行い、間違いを探す
+        // 種類が与えられた場合は、その種類の品名に関わる間違いだけを表示する
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        if let Err(err_lst) = ledger.check() {
+          for err in err_lst.iter() {
+            let is_target = match (&category_opt, err.item()) {
+              (None, _) => true,
+              (Some(category), Some(item)) => {
+                lib::catalog::matches_category(item, &config_data, category)
               }
-              lend_stack.push(lend_data)
+              (Some(_), None) => false,
+            };
+            if is_target {
+              print_message::print_check_error(err, &config_data)
             }
-            lib::LendType::Return(product_num, _) => {
-              // 返却された品名が貸し出したものを記録したリストにきちんとあるかをチェックする
-              // 無かった場合はメッセージを出力する
-              match lend_stack
-                .iter()
-                .find(|data| check_lend_product_num(data, product_num))
-              {
-                None => eprintln!(
-                  "- {}が貸し出されていないにもかかわらず返却されたことになっています\n",
-                  product_num
-                ),
-                Some(_) => (),
-              }
-              // 「返却された品を含まないリストを作り直す」ことで「返却」という挙動を再現する
-              let new_lend_stack = lend_stack
-                .iter()
-                .filter(|data| !(check_lend_product_num(data, product_num)))
-                .cloned()
-                .collect();
-              lend_stack = new_lend_stack
-            }
-            // これ以外は無いはずなので考慮しない
-            _ => {}
-          }
-        }
-        println!("--- --- ---\n検査を終了しました\n");
-      }
-      lib::DlmArg::Lend(product_num_lst, destination_num) => {
-        // CSVファイルへのパスから生のデータ群を取り出す
-        let mut lend_data = csv_file_name_to_lend_data(data_file_name.to_owned());
-        // データ群の中で最大の操作番号を探し出す。
-        let lend_data_num_max = lend_data
-          .iter()
-          .max_by_key(|x| x.num)
-          .map(|data_opt| data_opt.num)
-          .unwrap_or(0);
-        let mut lend_num = lend_data_num_max;
-        // 貸出品のリストに対して
-        //  - 現在時刻の取得
-        //  - 検査
-        // を行い、全部が検査を通った時に書き込む
-        // 一つでも検査を通らなかったらエラーとして処理し、なにも書き込まない
-        let mut check_is_ok = true;
-        for product_num in product_num_lst.iter() {
-          lend_num += 1;
-          // 現在時刻をタイムゾーン分の9時間分ずらした上で取得
-          let time_fixed_offset = Utc::now().with_timezone(&FixedOffset::east(9 * 3600));
-          // 'check'コマンドへの処理でやったことと同じ検査を行う
-          // 検査を通過したらリストに登録してファイル更新
-          // 検査を通らなかったらメッセージを表示して終了
-          if lib::make_now_lend_data_lst(&lend_data)
-            .iter()
-            .any(|data| check_lend_product_num(data, &product_num))
-          {
-            check_is_ok = false;
-            eprintln!(
-              "!  {}が既に貸し出されているのでこの操作を行うことはできません",
-              product_num
-            );
-            break;
-          } else {
-            // 成功したものを一時リストに登録していく
-            lend_data.push(lib::LendData {
-              time: time_fixed_offset,
-              lend_type: lib::LendType::Lend(product_num.clone(), destination_num.clone()),
-              num: lend_num,
-            });
           }
         }
-        if check_is_ok {
-          // 書き出し
-          lend_data_lst_to_output(data_file_name, lend_data);
-          // 成功メッセージの出力
-          for product_num in product_num_lst.iter() {
-            print_message::print_lend_success(&product_num, &destination_num, &lend_num);
+        // 壊れた状態や部品が欠けた状態で返却され、まだ修理されていない品を表示する
+        let damaged_lst: Vec<lib::LendData> = ledger
+          .damaged()
+          .into_iter()
+          .filter(|lend_data| match (&category_opt, &lend_data.lend_type) {
+            (Some(category), lib::LendType::Return(product_num, _)) => {
+              lib::catalog::matches_category(product_num, &config_data, category)
+            }
+            _ => true,
+          })
+          .collect();
+        if !damaged_lst.is_empty() {
+          println!("--- --- ---\n{}\n", text("check.damaged"));
+          for lend_data in damaged_lst.iter() {
+            print_message::print_damaged_item(lend_data, &config_data)
+          }
+        }
+        // 紛失・使用終了した品を、そのときの貸出先と共に表示する
+        let retired_lst: Vec<lib::LendData> = ledger
+          .retired()
+          .into_iter()
+          .filter(|lend_data| match (&category_opt, &lend_data.lend_type) {
+            (Some(category), lib::LendType::Lost(product_num, _))
+            | (Some(category), lib::LendType::Retire(product_num, _)) => {
+              lib::catalog::matches_category(product_num, &config_data, category)
+            }
+            _ => true,
+          })
+          .collect();
+        if !retired_lst.is_empty() {
+          println!("--- --- ---\n{}\n", text("check.retired"));
+          for lend_data in retired_lst.iter() {
+            print_message::print_retired_item(lend_data, &config_data)
           }
+        }
+        // 記録された後に書き換えられた行が無いかを検査する
+        println!("--- --- ---\n{}\n", text("check.hash_chain"));
+        print_verify_hash_chain(data_file_name, &backup_config);
+        println!("--- --- ---\n{}\n", text("check.end"));
+        true
+      }
+      lib::DlmArg::Lend(
+        product_num_lst,
+        destination_num,
+        at_opt,
+        with_receipt,
+        with_override,
+        note_opt,
+      ) => 'command: {
+        // 時刻が与えられていない場合でも、後日入力モードであればその時刻を使う
+        let at_opt = at_opt.or(backfill_time_opt);
+        // 読み込んでから書き出すまでの間、他のdlmが書き込まないようにロックする
+        let _lock = match lock::lock_data_file(data_file_name) {
+          None => {
+            print_message::print_data_file_locked();
+            break 'command false;
+          }
+          Some(lock) => lock,
+        };
+        // 読み込み済みの貸出状況を取り出す（CSVファイルが変わっていたら読み込み直す）
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        // 団体ごとの貸出の制限を検査する
+        // 後日入力では既に貸し出した後なので検査しない
+        let violation_lst = if at_opt.is_none() {
+          policy.check_lend(
+            &ledger.outstanding(),
+            &config_data,
+            &product_num_lst,
+            &destination_num,
+          )
         } else {
-          // 検査不合格が発声していた場合
-          eprintln!("今回行われた操作は全て中止されました。再度正しい貸出を実行してください。\n")
+          Vec::new()
+        };
+        if !violation_lst.is_empty() && !with_override {
+          for violation in violation_lst.iter() {
+            print_message::print_policy_violation(violation, &config_data)
+          }
+          print_message::print_policy_override_hint();
+          print_message::print_lend_aborted();
+          break 'command false;
+        }
+        let overridden = !violation_lst.is_empty();
+        // 全ての貸出品が検査を通った時だけ記録される
+        // 一つでも検査を通らなかったらエラーとして処理し、なにも記録しない
+        match ledger.lend(
+          &product_num_lst,
+          &destination_num,
+          at_opt,
+          operator_opt.as_deref(),
+          overridden,
+          note_opt.as_deref(),
+        ) {
+          Ok(num_lst) => {
+            // 書き出し
+            if !output_ledger(data_file_name, &mut ledger_cache) {
+              break 'command false;
+            }
+            // 'undo'用に記録
+            session_num_lst.extend(num_lst.iter());
+            undo_stack.push(num_lst.clone());
+            redo_stack.clear();
+            // 成功メッセージの出力
+            for (product_num, lend_num) in product_num_lst.iter().zip(num_lst.iter()) {
+              print_message::print_lend_success(product_num, &destination_num, lend_num);
+            }
+            if let Some(at) = at_opt {
+              print_message::print_backfill_success(&at.format("%Y/%m/%d %H:%M").to_string())
+            }
+            if overridden {
+              print_message::print_policy_overridden()
+            }
+            if with_receipt || receipt_config.auto {
+              let ledger = load_ledger(data_file_name, &mut ledger_cache);
+              issue_receipt(ledger, &num_lst, None, &receipt_config, &config_data)
+            } else {
+              true
+            }
+          }
+          Err(err) => {
+            // 検査不合格が発声していた場合
+            print_message::print_ledger_error(&err, &config_data);
+            print_message::print_lend_aborted();
+            false
+          }
         }
       }
-      lib::DlmArg::Return(product_num_lst, destination_num) => {
+      lib::DlmArg::Return(
+        product_num_lst,
+        destination_num,
+        at_opt,
+        with_receipt,
+        condition_opt,
+        note_opt,
+      ) => 'command: {
         // Lendのときとほとんど同じ
-        let mut lend_data = csv_file_name_to_lend_data(data_file_name.to_owned());
-        let lend_data_num_max = lend_data
-          .iter()
-          .max_by_key(|x| x.num)
-          .map(|data_opt| data_opt.num)
-          .unwrap_or(0);
-        let mut lend_num = lend_data_num_max;
-        // 返却品のリストに対して
-        //  - 現在時刻の取得
-        //  - 検査
-        // を行い、全部が検査を通った時に書き込む
-        // 一つでも検査を通らなかったらエラーとして処理し、なにも書き込まない
-        let mut check_is_ok = true;
-        for product_num in product_num_lst.iter() {
-          lend_num += 1;
-          // 現在時刻をタイムゾーン分の9時間分ずらした上で取得
-          let time_fixed_offset = Utc::now().with_timezone(&FixedOffset::east(9 * 3600));
-          // 'check'コマンドへの処理でやったことと同じ検査を行う
-          // 検査を通過したらリストに登録してファイル更新
-          // 検査を通らなかったらメッセージを表示して終了
-          if lib::make_now_lend_data_lst(&lend_data)
-            .iter()
-            .any(|data| check_lend_product_num(data, &product_num))
-          {
-            // 貸出先と返却先が一致していなかったら警告を出して終了
-            if !check_return_destination_num(
-              &lib::make_now_lend_data_lst(&lend_data),
-              &product_num,
-              &destination_num,
-            ) {
-              // 貸出先と返却先が一致していない
-              check_is_ok = false;
-              eprintln!(
-                "!  {}の貸出先と返却先が一致していないため、この操作を行うことは出来ません",
-                product_num
-              );
-              break;
+        let at_opt = at_opt.or(backfill_time_opt);
+        let _lock = match lock::lock_data_file(data_file_name) {
+          None => {
+            print_message::print_data_file_locked();
+            break 'command false;
+          }
+          Some(lock) => lock,
+        };
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        let needs_repair = condition_opt
+          .as_ref()
+          .is_some_and(|condition| condition.kind.needs_repair());
+        match ledger.return_items(
+          &product_num_lst,
+          &destination_num,
+          at_opt,
+          operator_opt.as_deref(),
+          condition_opt,
+          note_opt.as_deref(),
+        ) {
+          Ok(num_lst) => {
+            if !output_ledger(data_file_name, &mut ledger_cache) {
+              break 'command false;
+            }
+            session_num_lst.extend(num_lst.iter());
+            undo_stack.push(num_lst.clone());
+            redo_stack.clear();
+            for (product_num, lend_num) in product_num_lst.iter().zip(num_lst.iter()) {
+              print_message::print_return_success(product_num, &destination_num, lend_num);
+            }
+            if let Some(at) = at_opt {
+              print_message::print_backfill_success(&at.format("%Y/%m/%d %H:%M").to_string())
+            }
+            if needs_repair {
+              print_message::print_needs_repair_notice()
+            }
+            if with_receipt || receipt_config.auto {
+              let ledger = load_ledger(data_file_name, &mut ledger_cache);
+              issue_receipt(ledger, &num_lst, None, &receipt_config, &config_data)
             } else {
-              // 一致していたので返却操作を登録
-              lend_data.push(lib::LendData {
-                time: time_fixed_offset,
-                lend_type: lib::LendType::Return(product_num.clone(), destination_num.clone()),
-                num: lend_num,
-              });
+              true
             }
-          } else {
-            check_is_ok = false;
-            eprintln!(
-              "!  {}がまだ貸し出されてないのでこの操作を行うことは出来ません",
-              product_num
-            );
-            break;
+          }
+          Err(err) => {
+            print_message::print_ledger_error(&err, &config_data);
+            print_message::print_lend_aborted();
+            false
           }
         }
-        if check_is_ok {
-          // 書き出し
-          lend_data_lst_to_output(data_file_name, lend_data);
-          // 成功メッセージの出力
-
-          for product_num in product_num_lst.iter() {
-            print_message::print_return_success(&product_num, &destination_num, &lend_num);
+      }
+      lib::DlmArg::Repair(product_num_lst) => 'command: {
+        // 壊れた状態で返却された品を、修理して再び貸し出せるようにする
+        let _lock = match lock::lock_data_file(data_file_name) {
+          None => {
+            print_message::print_data_file_locked();
+            break 'command false;
+          }
+          Some(lock) => lock,
+        };
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        match ledger.repair(&product_num_lst, operator_opt.as_deref()) {
+          Ok(num_lst) => {
+            if !output_ledger(data_file_name, &mut ledger_cache) {
+              break 'command false;
+            }
+            session_num_lst.extend(num_lst.iter());
+            undo_stack.push(num_lst.clone());
+            redo_stack.clear();
+            for (product_num, lend_num) in product_num_lst.iter().zip(num_lst.iter()) {
+              print_message::print_repair_success(product_num, lend_num);
+            }
+            true
+          }
+          Err(err) => {
+            print_message::print_ledger_error(&err, &config_data);
+            false
           }
-        } else {
-          // 検査不合格が発声していた場合
-          eprintln!("今回行われた操作は全て中止されました。再度正しい貸出を実行してください。\n")
         }
       }
-      lib::DlmArg::Edit(num, new_product_num, new_destination_num) => {
-        // Lendとほぼ同じだが、編集する対象の操作が未来のものであった場合は不正とみなしてメッセージを表示して終了
+      lib::DlmArg::Lost(product_num, note_opt) => 'command: {
+        // 戻ってくることのない品の貸出を終わらせ、それ以降は貸し出せなくする
+        let _lock = match lock::lock_data_file(data_file_name) {
+          None => {
+            print_message::print_data_file_locked();
+            break 'command false;
+          }
+          Some(lock) => lock,
+        };
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        match ledger.lost(&product_num, note_opt.as_deref(), operator_opt.as_deref()) {
+          Ok(lend_num) => {
+            if !output_ledger(data_file_name, &mut ledger_cache) {
+              break 'command false;
+            }
+            session_num_lst.push(lend_num);
+            undo_stack.push(vec![lend_num]);
+            redo_stack.clear();
+            let ledger = load_ledger(data_file_name, &mut ledger_cache);
+            if let Some(lend_data) = ledger.state().get(lend_num) {
+              print_message::print_out_of_circulation_success(lend_data, &config_data)
+            }
+            true
+          }
+          Err(err) => {
+            print_message::print_ledger_error(&err, &config_data);
+            false
+          }
+        }
+      }
+      lib::DlmArg::Retire(product_num) => 'command: {
+        // Lostとほぼ同じ
+        let _lock = match lock::lock_data_file(data_file_name) {
+          None => {
+            print_message::print_data_file_locked();
+            break 'command false;
+          }
+          Some(lock) => lock,
+        };
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        match ledger.retire(&product_num, operator_opt.as_deref()) {
+          Ok(lend_num) => {
+            if !output_ledger(data_file_name, &mut ledger_cache) {
+              break 'command false;
+            }
+            session_num_lst.push(lend_num);
+            undo_stack.push(vec![lend_num]);
+            redo_stack.clear();
+            let ledger = load_ledger(data_file_name, &mut ledger_cache);
+            if let Some(lend_data) = ledger.state().get(lend_num) {
+              print_message::print_out_of_circulation_success(lend_data, &config_data)
+            }
+            true
+          }
+          Err(err) => {
+            print_message::print_ledger_error(&err, &config_data);
+            false
+          }
+        }
+      }
+      lib::DlmArg::Edit(num, edit_data, note_opt) => 'command: {
+        // 編集する対象の操作が未来のものであった場合などは不正とみなしてメッセージを表示して終了
         // また、本当に意図した編集内容になっているかを確認するためのメッセージを表示する
         // 'n'または'N'が入力された場合のみ操作を中止するが、それ以外の任意の文字列だった場合は編集を行う
-        let mut lend_data = csv_file_name_to_lend_data(data_file_name.to_owned());
-        let lend_data_num_max = lend_data
-          .iter()
-          .max_by_key(|x| x.num)
-          .map(|data_opt| data_opt.num)
-          .unwrap_or(0);
-        let lend_num = lend_data_num_max + 1;
-        if num > lend_data_num_max {
-          eprintln!("!  未来の操作を編集することは出来ません\n");
-        } else {
-          let time_fixed_offset = Utc::now().with_timezone(&FixedOffset::east(9 * 3600));
-          let data = lib::get_lend_data(&lend_data, num).unwrap();
-          match data.lend_type {
-            // 対象がEditとRemoveの時は不正とみなして終了
-            lib::LendType::Edit(_, _, _) | lib::LendType::Remove(_) => {
-              eprintln!("!  'remove'または'edit'で行った操作を編集することは出来ません");
-            }
-            _ => {
-              let data_str = lib::lend_data_to_message_with_config_data(&data, &config_data);
-              println!(
-                "{}\nという{}番の操作の品名を\"{}\"に、相手を\"{}\"に変更します\n本当に良いですか？[Y/n]\n    >",
-                data_str,
-                num,
-                new_product_num,
-                new_destination_num
-              );
-              let mut s = String::new();
-              std::io::stdin().read_line(&mut s).ok();
-              let s: &str = &s.trim().to_owned().to_ascii_lowercase();
-              match s {
-                "n" => println!("操作を中止しました"),
-                _ => {
-                  lend_data.push(lib::LendData {
-                    time: time_fixed_offset,
-                    lend_type: lib::LendType::Edit(
-                      num,
-                      new_product_num.clone(),
-                      new_destination_num.clone(),
-                    ),
-                    num: lend_num,
-                  });
-                  lend_data_lst_to_output(data_file_name, lend_data);
-                  print_message::print_edit_success(
-                    &num,
-                    &new_product_num,
-                    &new_destination_num,
-                    &lend_num,
-                  );
+        // 確認を待っている間はロックせず、書き込む直前にロックしてから他の端末での変更を確かめる
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        match ledger.check_edit(num) {
+          Err(err) => {
+            print_message::print_ledger_error(&err, &config_data);
+            false
+          }
+          Ok((data, now_data)) => {
+            let data_str = lib::lend_data_to_message_with_config_data(&data, &config_data);
+            // 以前の編集を反映させた現在の内容と比べた差分を表示する
+            println!(
+              "{}",
+              fill(
+                "edit.confirm",
+                &[
+                  &data_str,
+                  &num,
+                  &lib::make_edit_diff_str(&now_data, &edit_data, &config_data)
+                ]
+              )
+            );
+            let mut s = String::new();
+            std::io::stdin().read_line(&mut s).ok();
+            let s: &str = &s.trim().to_owned().to_ascii_lowercase();
+            match s {
+              "n" => {
+                println!("{}", text("cancelled"));
+                true
+              }
+              _ => {
+                let _lock = match lock::lock_data_file(data_file_name) {
+                  None => {
+                    print_message::print_data_file_locked();
+                    break 'command false;
+                  }
+                  Some(lock) => lock,
+                };
+                // 読み込み直した結果、対象の操作やその現在の内容が変わっていたら中止する
+                let ledger = reload_ledger_if_changed(data_file_name, &mut ledger_cache);
+                if ledger.check_edit(num) != Ok((data, now_data)) {
+                  print_message::print_target_changed(&num);
+                  break 'command false;
+                }
+                match ledger.edit(
+                  num,
+                  edit_data.clone(),
+                  operator_opt.as_deref(),
+                  note_opt.as_deref(),
+                ) {
+                  Err(err) => {
+                    print_message::print_ledger_error(&err, &config_data);
+                    false
+                  }
+                  Ok(lend_num) => {
+                    if !output_ledger(data_file_name, &mut ledger_cache) {
+                      break 'command false;
+                    }
+                    session_num_lst.push(lend_num);
+                    undo_stack.push(vec![lend_num]);
+                    redo_stack.clear();
+                    print_message::print_edit_success(
+                      &num,
+                      edit_data.product_num.as_deref(),
+                      edit_data.destination_num.as_deref(),
+                      edit_data
+                        .time
+                        .map(|time| time.format("%Y/%m/%d %H:%M").to_string())
+                        .as_deref(),
+                      &lend_num,
+                    );
+                    true
+                  }
                 }
               }
             }
           }
         }
       }
-      lib::DlmArg::Remove(num) => {
+      lib::DlmArg::Remove(num, note_opt) => 'command: {
         // Editとほぼ同じ
-        let mut lend_data = csv_file_name_to_lend_data(data_file_name.to_owned());
-        let lend_data_num_max = lend_data
-          .iter()
-          .max_by_key(|x| x.num)
-          .map(|data_opt| data_opt.num)
-          .unwrap_or(0);
-        let lend_num = lend_data_num_max + 1;
-        if num > lend_data_num_max {
-          eprintln!("!  未来の操作を削除することは出来ません\n");
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        match ledger.check_remove(num) {
+          Err(err) => {
+            print_message::print_ledger_error(&err, &config_data);
+            false
+          }
+          Ok(data) => {
+            let data_str = lib::lend_data_to_message_with_config_data(&data, &config_data);
+            println!("{}", fill("remove.confirm", &[&data_str, &num]));
+            let mut s = String::new();
+            std::io::stdin().read_line(&mut s).ok();
+            let s: &str = &s.trim().to_owned().to_ascii_lowercase();
+            match s {
+              "n" => {
+                println!("{}", text("cancelled"));
+                true
+              }
+              _ => {
+                let _lock = match lock::lock_data_file(data_file_name) {
+                  None => {
+                    print_message::print_data_file_locked();
+                    break 'command false;
+                  }
+                  Some(lock) => lock,
+                };
+                let ledger = reload_ledger_if_changed(data_file_name, &mut ledger_cache);
+                if ledger.check_remove(num) != Ok(data) {
+                  print_message::print_target_changed(&num);
+                  break 'command false;
+                }
+                match ledger.remove(num, operator_opt.as_deref(), note_opt.as_deref()) {
+                  Err(err) => {
+                    print_message::print_ledger_error(&err, &config_data);
+                    false
+                  }
+                  Ok(lend_num) => {
+                    if !output_ledger(data_file_name, &mut ledger_cache) {
+                      break 'command false;
+                    }
+                    session_num_lst.push(lend_num);
+                    undo_stack.push(vec![lend_num]);
+                    redo_stack.clear();
+                    print_message::print_remove_success(&num, &lend_num);
+                    true
+                  }
+                }
+              }
+            }
+          }
+        }
+      }
+      lib::DlmArg::Scan(is_return) => {
+        // バーコードやQRコードのスキャナーからの入力を受け付ける
+        // 最初に貸出先（返却元）を読み取り、その後は品名を読み取るたびにすぐ貸出（返却）を記録する
+        // 途中で団体の番号を読み取った場合は、それ以降はその団体への貸出（からの返却）にする
+        // 品名か団体かは設定ファイルで見分け、設定ファイルに登録が無い場合は読み取った順番で決める
+        // 'end'を読み取るか空行で終了する
+        if is_return {
+          print_message::print_scan_start_return()
         } else {
-          let time_fixed_offset = Utc::now().with_timezone(&FixedOffset::east(9 * 3600));
-          let data = lib::get_lend_data(&lend_data, num).unwrap();
-          let data_str = lib::lend_data_to_message_with_config_data(&data, &config_data);
-          println!(
-            "{}\nという{}番の操作を無かったことにします\n本当に良いですか？[Y/n] >",
-            data_str, num
+          print_message::print_scan_start_lend()
+        }
+        let at_opt = backfill_time_opt;
+        let mut destination_opt: Option<String> = None;
+        let (mut ok_count, mut err_count) = (0, 0);
+        while let Some(line) = read_scan_line(&mut editor_opt) {
+          let code = lib::classify_scan_code(&line, &config_data);
+          let product_num = match (code, &destination_opt) {
+            (lib::ScanCode::End, _) => break,
+            (lib::ScanCode::Group(num), _) => {
+              print_message::print_scan_destination(&num, is_return, &config_data);
+              destination_opt = Some(num);
+              continue;
+            }
+            (lib::ScanCode::Unknown(num), None) if !has_config_entry(&config_data.sandan) => {
+              print_message::print_scan_destination(&num, is_return, &config_data);
+              destination_opt = Some(num);
+              continue;
+            }
+            (lib::ScanCode::Item(_), None) | (lib::ScanCode::Unknown(_), None) => {
+              print_message::print_scan_need_destination();
+              print_message::print_beep();
+              err_count += 1;
+              continue;
+            }
+            (lib::ScanCode::Unknown(num), Some(_)) if has_config_entry(&config_data.sizai) => {
+              // 登録されていない番号は記録しない
+              print_message::print_scan_unknown(&num);
+              None
+            }
+            (lib::ScanCode::Item(num), Some(_)) | (lib::ScanCode::Unknown(num), Some(_)) => {
+              Some(num)
+            }
+          };
+          let destination_num = destination_opt.clone().unwrap_or_default();
+          let succeeded = match &product_num {
+            None => false,
+            Some(product_num) => match lock::lock_data_file(data_file_name) {
+              None => {
+                print_message::print_data_file_locked();
+                false
+              }
+              Some(_lock) => {
+                let ledger = load_ledger(data_file_name, &mut ledger_cache);
+                let product_num_lst = vec![product_num.to_string()];
+                // 読み取りでは制限を超えて貸し出せないので、超える場合は'lend --override'を使ってもらう
+                let violation_lst = if is_return || at_opt.is_some() {
+                  Vec::new()
+                } else {
+                  policy.check_lend(
+                    &ledger.outstanding(),
+                    &config_data,
+                    &product_num_lst,
+                    &destination_num,
+                  )
+                };
+                if !violation_lst.is_empty() {
+                  for violation in violation_lst.iter() {
+                    print_message::print_policy_violation(violation, &config_data)
+                  }
+                  false
+                } else {
+                  let result = if is_return {
+                    ledger.return_items(
+                      &product_num_lst,
+                      &destination_num,
+                      at_opt,
+                      operator_opt.as_deref(),
+                      None,
+                      None,
+                    )
+                  } else {
+                    ledger.lend(
+                      &product_num_lst,
+                      &destination_num,
+                      at_opt,
+                      operator_opt.as_deref(),
+                      false,
+                      None,
+                    )
+                  };
+                  match result {
+                    Ok(num_lst) => {
+                      if !output_ledger(data_file_name, &mut ledger_cache) {
+                        false
+                      } else {
+                        session_num_lst.extend(num_lst.iter());
+                        undo_stack.push(num_lst.clone());
+                        redo_stack.clear();
+                        for lend_num in num_lst.iter() {
+                          if is_return {
+                            print_message::print_return_success(
+                              product_num,
+                              &destination_num,
+                              lend_num,
+                            )
+                          } else {
+                            print_message::print_lend_success(
+                              product_num,
+                              &destination_num,
+                              lend_num,
+                            )
+                          }
+                        }
+                        true
+                      }
+                    }
+                    Err(err) => {
+                      print_message::print_ledger_error(&err, &config_data);
+                      false
+                    }
+                  }
+                }
+              }
+            },
+          };
+          // 読み取った番号ごとに、同じ内容の'lend'や'return'を入力したものとして履歴に残す
+          // エラーになったものは'history --failed'で確かめられる
+          if succeeded {
+            ok_count += 1
+          } else {
+            print_message::print_beep();
+            err_count += 1
+          }
+          arg_command_history_vec.push(lib::HistoryEntry {
+            command: format!(
+              "{} {} {}",
+              if is_return { "return" } else { "lend" },
+              product_num.unwrap_or_else(|| line.trim().to_string()),
+              destination_num
+            ),
+            failed: !succeeded,
+          });
+        }
+        if let Some(at) = at_opt {
+          print_message::print_backfill_success(&at.format("%Y/%m/%d %H:%M").to_string())
+        }
+        print_message::print_scan_end(ok_count, err_count);
+        true
+      }
+      lib::DlmArg::Stocktake => {
+        // 手元にある品名を全て読み取ってもらい、
+        // 設定ファイルにある品名から貸出中と紛失・使用終了のものを除いたものと突き合わせる
+        // 'end'を読み取るか空行で終了し、結果を表示して'<CSVファイル名>.stocktake'に保存する
+        print_message::print_stocktake_start();
+        let mut scanned_lst: Vec<String> = Vec::new();
+        while let Some(line) = read_scan_line(&mut editor_opt) {
+          let code = line.trim();
+          if code.is_empty() || code.eq_ignore_ascii_case("end") {
+            break;
+          }
+          if scanned_lst.iter().any(|scanned| scanned == code) {
+            print_message::print_stocktake_duplicate(code);
+            continue;
+          }
+          let ledger = load_ledger(data_file_name, &mut ledger_cache);
+          let stocktake_code = lib::stocktake::classify_code(
+            code,
+            &ledger.outstanding(),
+            &ledger.retired(),
+            &config_data,
           );
-          let mut s = String::new();
-          std::io::stdin().read_line(&mut s).ok();
-          let s: &str = &s.trim().to_owned().to_ascii_lowercase();
-          match s {
-            "n" => println!("操作を中止しました"),
-            _ => {
-              lend_data.push(lib::LendData {
-                time: time_fixed_offset,
-                lend_type: lib::LendType::Remove(num),
-                num: lend_num,
-              });
-              lend_data_lst_to_output(data_file_name, lend_data);
-              print_message::print_remove_success(&num, &lend_num)
-            }
-          }
-        }
-      }
-      lib::DlmArg::AllPrint => {
-        // CSVファイルへのパスから生成したデータ群を文字列化してそのまま出力
-        let lend_data_lst = csv_file_name_to_lend_data(data_file_name.to_owned());
-        for lend_data in lend_data_lst {
+          print_message::print_stocktake_code(&stocktake_code, &config_data);
+          scanned_lst.push(code.to_string());
+        }
+        // 読み取っている間に他の端末で行われた貸出と返却も反映させる
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        let stocktake = lib::stocktake::make_stocktake(
+          &scanned_lst,
+          &ledger.outstanding(),
+          &ledger.retired(),
+          &config_data,
+          Utc::now().with_timezone(&FixedOffset::east(9 * 3600)),
+          operator_opt.clone(),
+        );
+        let s = lib::stocktake::render_text(&stocktake, &config_data);
+        print!("{}", s);
+        let dir = std::path::PathBuf::from(format!("{}.stocktake", data_file_name));
+        let path = dir.join(lib::stocktake::stocktake_file_name(&stocktake));
+        match fs::create_dir_all(&dir).and_then(|_| fs::write(&path, s)) {
+          Err(_) => {
+            print_message::print_stocktake_write_failed(&path.to_string_lossy());
+            false
+          }
+          Ok(_) => {
+            print_message::print_stocktake_success(&path.to_string_lossy());
+            true
+          }
+        }
+      }
+      lib::DlmArg::Backfill(time_opt) => {
+        // 後日入力モードの開始と終了
+        // 開始している間は'lend'と'return'に'--at'で時刻を与えたのと同じになる
+        match time_opt {
+          Some(time) if time > Utc::now() => {
+            print_message::print_ledger_error(&lib::LedgerError::FutureTime(time), &config_data);
+            false
+          }
+          Some(time) => {
+            backfill_time_opt = Some(time);
+            println!(
+              "{}",
+              fill("backfill.start", &[&time.format("%Y/%m/%d %H:%M")])
+            );
+            true
+          }
+          None => {
+            backfill_time_opt = None;
+            println!("{}", text("backfill.end"));
+            true
+          }
+        }
+      }
+      lib::DlmArg::Undo(n) => {
+        // このセッションで行った直近n回分のコマンドによる操作を、'remove'操作を記録することで取り消す
+        // 取り消しに使った'remove'操作は'redo'で取り消せるように記録しておく
+        let undo_stack_len = undo_stack.len();
+        if undo_stack_len < n {
+          print_message::print_undo_limit(undo_stack_len);
+          false
+        } else {
+          // 新しい操作から順番に取り消す
+          let target_lst: Vec<Vec<isize>> = undo_stack.iter().rev().take(n).cloned().collect();
+          match remove_session_lend_data(
+            data_file_name,
+            &mut ledger_cache,
+            &config_data,
+            &target_lst,
+            &session_num_lst,
+            &operator_opt,
+          ) {
+            Err(()) => false,
+            Ok(None) => true,
+            Ok(Some(removed_lst)) => {
+              undo_stack.truncate(undo_stack_len - n);
+              session_num_lst.extend(removed_lst.iter().flatten());
+              redo_stack.extend(removed_lst);
+              true
+            }
+          }
+        }
+      }
+      lib::DlmArg::Redo(n) => {
+        // 'undo'で記録した'remove'操作をさらに'remove'することで、取り消した操作を元に戻す
+        let redo_stack_len = redo_stack.len();
+        if redo_stack_len < n {
+          print_message::print_redo_limit(redo_stack_len);
+          false
+        } else {
+          let target_lst: Vec<Vec<isize>> = redo_stack.iter().rev().take(n).cloned().collect();
+          match remove_session_lend_data(
+            data_file_name,
+            &mut ledger_cache,
+            &config_data,
+            &target_lst,
+            &session_num_lst,
+            &operator_opt,
+          ) {
+            Err(()) => false,
+            Ok(None) => true,
+            Ok(Some(removed_lst)) => {
+              redo_stack.truncate(redo_stack_len - n);
+              session_num_lst.extend(removed_lst.iter().flatten());
+              undo_stack.extend(removed_lst);
+              true
+            }
+          }
+        }
+      }
+      lib::DlmArg::AllPrint(filter) => {
+        // CSVファイルへのパスから生成したデータ群を条件で絞り込み、文字列化してそのまま出力
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        for lend_data in lib::filter_lend_data(ledger.state(), &filter) {
           println!(
             "{}",
             lib::lend_data_to_message_with_config_data(&lend_data, &config_data)
           )
         }
+        true
+      }
+      lib::DlmArg::Verify => {
+        // CSVファイルのハッシュチェーンを検査する
+        print_verify_hash_chain(data_file_name, &backup_config);
+        true
+      }
+      lib::DlmArg::Migrate => {
+        // 古い形式のCSVファイルを最新の形式で書き直す
+        // 元のファイルはlend_data_lst_to_outputの中で保存される
+        match read_csv_format_version(data_file_name) {
+          None => {
+            println!("{}", text("migrate.no_file"));
+            true
+          }
+          Some(version) if version >= CSV_FORMAT_VERSION => {
+            println!("{}", fill("migrate.up_to_date", &[&version]));
+            true
+          }
+          Some(_) => match lock::lock_data_file(data_file_name) {
+            None => {
+              print_message::print_data_file_locked();
+              false
+            }
+            Some(_lock) => match csv_file_name_to_lend_data(data_file_name) {
+              Err(err) => {
+                print_message::print_invalid_row(err.line, &err.reason);
+                false
+              }
+              Ok(lend_data) => match lend_data_lst_to_output(data_file_name, &lend_data) {
+                Err(err) => {
+                  print_message::print_data_file_write_failed(data_file_name, &err);
+                  false
+                }
+                Ok(()) => true,
+              },
+            },
+          },
+        }
+      }
+      lib::DlmArg::Stats(window, category_opt, out_opt) => {
+        // 貸出と返却の組から、品名ごとの貸出時間や混み合う時間帯などを集計する
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        let now = Utc::now().with_timezone(&FixedOffset::east(9 * 3600));
+        let stats = lib::stats::make_stats(
+          ledger.lend_data_lst(),
+          &config_data,
+          window,
+          category_opt.as_deref(),
+          now,
+        );
+        match out_opt {
+          None => {
+            print!("{}", lib::stats::render_text(&stats, &config_data));
+            true
+          }
+          Some(file_name) => {
+            let s = match lib::stats::StatsFormat::from_file_name(&file_name) {
+              Some(lib::stats::StatsFormat::Json) => lib::stats::render_json(&stats),
+              _ => lib::stats::render_csv(&stats),
+            };
+            match fs::write(&file_name, s) {
+              Err(_) => {
+                print_message::print_stats_write_failed(&file_name);
+                false
+              }
+              Ok(_) => {
+                print_message::print_stats_success(&file_name);
+                true
+              }
+            }
+          }
+        }
+      }
+      lib::DlmArg::ReportFinal(out_opt) => {
+        // 行事の終わりに、全て返ってきたかを突き合わせた報告書を作る
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        let report = lib::report::make_final_report(ledger, &config_data);
+        let generated = Utc::now().with_timezone(&FixedOffset::east(9 * 3600));
+        match out_opt {
+          None => {
+            print!(
+              "{}",
+              lib::report::render_markdown(&report, &config_data, generated)
+            );
+            true
+          }
+          Some(file_name) => {
+            let s = match lib::report::ReportFormat::from_file_name(&file_name) {
+              Some(lib::report::ReportFormat::Html) => {
+                lib::report::render_html(&report, &config_data, generated)
+              }
+              Some(lib::report::ReportFormat::Csv) => {
+                lib::report::render_csv(&report, &config_data)
+              }
+              _ => lib::report::render_markdown(&report, &config_data, generated),
+            };
+            match fs::write(&file_name, s) {
+              Err(_) => {
+                print_message::print_report_write_failed(&file_name);
+                false
+              }
+              Ok(_) => {
+                print_message::print_report_success(&file_name);
+                true
+              }
+            }
+          }
+        }
+      }
+      lib::DlmArg::Receipt(num_lst, format_opt) => {
+        // 記録済みの操作から貸出票を作り直す
+        let ledger = load_ledger(data_file_name, &mut ledger_cache);
+        issue_receipt(ledger, &num_lst, format_opt, &receipt_config, &config_data)
+      }
+      lib::DlmArg::Labels(option) => {
+        // 設定ファイルの資材と参団からラベルを作り、ファイルに書き出す
+        let mut label_lst_lst = Vec::new();
+        if option.items {
+          label_lst_lst.push(lib::label::item_label_lst(&config_data))
+        }
+        if option.groups {
+          label_lst_lst.push(lib::label::group_label_lst(&config_data))
+        }
+        label_lst_lst.retain(|label_lst| !label_lst.is_empty());
+        let label_count: usize = label_lst_lst.iter().map(|label_lst| label_lst.len()).sum();
+        if label_count == 0 {
+          print_message::print_labels_empty();
+          false
+        } else {
+          let rendered = match option.format {
+            lib::label::LabelFormat::Svg => {
+              lib::label::render_svg(&label_lst_lst, option.code_type, &option.grid).map(
+                |svg_lst| {
+                  svg_file_name_lst(&option.out, svg_lst.len())
+                    .into_iter()
+                    .zip(svg_lst.into_iter().map(|svg| svg.into_bytes()))
+                    .collect::<Vec<(String, Vec<u8>)>>()
+                },
+              )
+            }
+            lib::label::LabelFormat::Pdf => {
+              lib::label::render_pdf(&label_lst_lst, option.code_type, &option.grid)
+                .map(|pdf| vec![(option.out.clone(), pdf)])
+            }
+          };
+          match rendered {
+            Err(code) => {
+              print_message::print_labels_unencodable(&code);
+              false
+            }
+            Ok(file_lst) => {
+              match file_lst
+                .iter()
+                .find(|(file_name, bytes)| fs::write(file_name, bytes).is_err())
+              {
+                Some((file_name, _)) => {
+                  print_message::print_labels_write_failed(file_name);
+                  false
+                }
+                None => {
+                  let file_name_lst: Vec<String> = file_lst
+                    .into_iter()
+                    .map(|(file_name, _)| file_name)
+                    .collect();
+                  print_message::print_labels_success(label_count, &file_name_lst);
+                  true
+                }
+              }
+            }
+          }
+        }
+      }
+      lib::DlmArg::Backup => {
+        // 今すぐバックアップを取る
+        match backup::make_snapshot(&backup_config, data_file_name) {
+          None => {
+            print_message::print_backup_failed();
+            false
+          }
+          Some(snapshot) => {
+            backup_session_num_len = session_num_lst.len();
+            print_message::print_backup_success(&snapshot.id, &snapshot.path.to_string_lossy());
+            true
+          }
+        }
+      }
+      lib::DlmArg::BackupList => {
+        // バックアップごとに、操作の数と最後の操作を表示する
+        let snapshot_lst = backup::snapshot_lst(&backup_config, data_file_name);
+        if snapshot_lst.is_empty() {
+          println!("{}", text("backup.empty"));
+        }
+        for (i, snapshot) in snapshot_lst.iter().enumerate() {
+          // 読み取れない行があるバックアップは、操作が無いものとして表示する
+          let lend_data =
+            csv_file_name_to_lend_data(&snapshot.path.to_string_lossy()).unwrap_or_default();
+          println!(
+            "{:>3}: {}  {}",
+            i + 1,
+            snapshot.id,
+            fill("backup.op_count", &[&lend_data.len()])
+          );
+          if let Some(last_lend_data) = lend_data.iter().max_by_key(|data| data.num) {
+            println!(
+              "     {}",
+              fill(
+                "backup.last_op",
+                &[&lib::lend_data_to_message_with_config_data(
+                  last_lend_data,
+                  &config_data
+                )]
+              )
+            )
+          }
+        }
+        true
+      }
+      lib::DlmArg::BackupRestore(id) => 'command: {
+        // バックアップの中身を確認してからCSVファイルに書き戻す
+        // 書き戻す前に現在のCSVファイルのバックアップを取っておくので、復元自体も元に戻せる
+        match backup::find_snapshot(&backup_config, data_file_name, &id) {
+          None => {
+            print_message::print_backup_not_found(&id);
+            false
+          }
+          Some(snapshot) => {
+            let lend_data =
+              csv_file_name_to_lend_data(&snapshot.path.to_string_lossy()).unwrap_or_default();
+            println!(
+              "{}",
+              fill("backup.restore_op_count", &[&snapshot.id, &lend_data.len()])
+            );
+            if let Some(last_lend_data) = lend_data.iter().max_by_key(|data| data.num) {
+              println!(
+                "{}",
+                fill(
+                  "backup.last_op",
+                  &[&format!(
+                    "\n{}",
+                    lib::lend_data_to_message_with_config_data(last_lend_data, &config_data)
+                  )]
+                )
+              )
+            }
+            println!("{}", text("backup.restore_confirm"));
+            let mut s = String::new();
+            std::io::stdin().read_line(&mut s).ok();
+            let s: &str = &s.trim().to_owned().to_ascii_lowercase();
+            match s {
+              "n" => {
+                println!("{}", text("cancelled"));
+                true
+              }
+              _ => {
+                let _lock = match lock::lock_data_file(data_file_name) {
+                  None => {
+                    print_message::print_data_file_locked();
+                    break 'command false;
+                  }
+                  Some(lock) => lock,
+                };
+                if let Some(current_snapshot) =
+                  backup::make_snapshot(&backup_config, data_file_name)
+                {
+                  print_message::print_backup_success(
+                    &current_snapshot.id,
+                    &current_snapshot.path.to_string_lossy(),
+                  )
+                }
+                match backup::restore_snapshot(&snapshot, data_file_name) {
+                  Err(_) => {
+                    print_message::print_restore_failed();
+                    false
+                  }
+                  Ok(()) => {
+                    // このセッションで行った操作は無くなっている可能性があるので、取り消せないようにする
+                    undo_stack.clear();
+                    redo_stack.clear();
+                    println!("{}", fill("backup.restored", &[&snapshot.id]));
+                    true
+                  }
+                }
+              }
+            }
+          }
+        }
+      }
+      lib::DlmArg::Login(operator) => {
+        // 担当者を変更する
+        match &operator {
+          None => println!("{}", text("logout")),
+          Some(name) => println!("{}", fill("login", &[name])),
+        }
+        operator_opt = operator;
+        true
       }
     };
+    if !succeeded {
+      mark_last_failed(&mut arg_command_history_vec)
+    }
+    // 一定回数の操作ごとにバックアップを取る
+    if backup_config.every > 0
+      && session_num_lst.len() >= backup_session_num_len + backup_config.every
+    {
+      backup::make_snapshot(&backup_config, data_file_name);
+      backup_session_num_len = session_num_lst.len();
+    }
+  }
+  // 終了時にも、前回のバックアップ以降に操作を行っていたらバックアップを取る
+  if session_num_lst.len() > backup_session_num_len {
+    if let Some(snapshot) = backup::make_snapshot(&backup_config, data_file_name) {
+      print_message::print_backup_success(&snapshot.id, &snapshot.path.to_string_lossy())
+    }
   }
 }
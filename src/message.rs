@@ -0,0 +1,190 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+mod en;
+mod ja;
+
+// 表示に使う言語
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+  Ja,
+  En,
+}
+
+impl Lang {
+  // "ja"・"en"や、環境変数LANGの"en_US.UTF-8"のような文字列から言語を決める
+  pub fn from_name(name: &str) -> Option<Lang> {
+    let name = name.to_ascii_lowercase();
+    if name.starts_with("ja") {
+      Some(Lang::Ja)
+    } else if name.starts_with("en") {
+      Some(Lang::En)
+    } else {
+      None
+    }
+  }
+
+  fn bundle(self) -> &'static [(&'static str, &'static str)] {
+    match self {
+      Lang::Ja => ja::BUNDLE,
+      Lang::En => en::BUNDLE,
+    }
+  }
+}
+
+// 起動時に一度だけ決めて、全ての表示で使う
+static LANG: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_lang(lang: Lang) {
+  let n = match lang {
+    Lang::Ja => 0,
+    Lang::En => 1,
+  };
+  LANG.store(n, Ordering::Relaxed)
+}
+
+pub fn lang() -> Lang {
+  match LANG.load(Ordering::Relaxed) {
+    1 => Lang::En,
+    _ => Lang::Ja,
+  }
+}
+
+// '--lang'・設定ファイルの"lang"・環境変数LANGの順に見て言語を決める
+// どれも無いか分からない場合は日本語にする
+pub fn select_lang(arg_opt: Option<&str>, config_opt: Option<&str>, env_opt: Option<&str>) -> Lang {
+  arg_opt
+    .and_then(Lang::from_name)
+    .or_else(|| config_opt.and_then(Lang::from_name))
+    .or_else(|| env_opt.and_then(Lang::from_name))
+    .unwrap_or(Lang::Ja)
+}
+
+// 現在の言語での文章
+// 見つからない場合は日本語の文章を、それも無ければキーそのものを返す
+pub fn text(key: &'static str) -> &'static str {
+  let find = |lang: Lang| {
+    lang
+      .bundle()
+      .iter()
+      .find(|(k, _)| *k == key)
+      .map(|(_, v)| *v)
+  };
+  find(lang()).or_else(|| find(Lang::Ja)).unwrap_or(key)
+}
+
+// 文章の中の'{0}'・'{1}'などを引数で置き換える
+// 言語によって語順が変わるので、番号で指定する
+pub fn fill(key: &'static str, args: &[&dyn fmt::Display]) -> String {
+  let template = text(key);
+  let mut s = String::new();
+  let mut rest = template;
+  // 引数の中に'{0}'などがあっても置き換えないように、先頭から一度だけ読む
+  while let Some(start) = rest.find('{') {
+    s.push_str(&rest[..start]);
+    let after = &rest[start + 1..];
+    let arg_opt = after.find('}').and_then(|end| {
+      after[..end]
+        .parse::<usize>()
+        .ok()
+        .and_then(|i| args.get(i))
+        .map(|arg| (arg, end))
+    });
+    match arg_opt {
+      Some((arg, end)) => {
+        s.push_str(&arg.to_string());
+        rest = &after[end + 1..];
+      }
+      None => {
+        s.push('{');
+        rest = after;
+      }
+    }
+  }
+  s.push_str(rest);
+  s
+}
+
+// 文章の中で使われている'{0}'などの番号のリスト
+#[cfg(test)]
+fn placeholder_lst(template: &str) -> Vec<usize> {
+  let re = regex::Regex::new(r"\{(\d)\}").unwrap();
+  let mut lst: Vec<usize> = re
+    .captures_iter(template)
+    .map(|cap| cap[1].parse().unwrap())
+    .collect();
+  lst.sort_unstable();
+  lst.dedup();
+  lst
+}
+
+#[test]
+fn check_bundle_keys() {
+  let lang_lst = [Lang::Ja, Lang::En];
+  for lang in lang_lst.iter() {
+    let bundle = lang.bundle();
+    for (i, (key, _)) in bundle.iter().enumerate() {
+      assert!(
+        !bundle[..i].iter().any(|(k, _)| k == key),
+        "{:?}: {} is duplicated",
+        lang,
+        key
+      );
+    }
+    // 全ての言語に全てのキーがあり、同じ引数を使っている
+    for other in lang_lst.iter() {
+      for (key, template) in bundle.iter() {
+        match other.bundle().iter().find(|(k, _)| k == key) {
+          None => panic!("{:?}: {} is missing", other, key),
+          Some((_, other_template)) => assert_eq!(
+            placeholder_lst(template),
+            placeholder_lst(other_template),
+            "{}",
+            key
+          ),
+        }
+      }
+    }
+  }
+}
+
+#[test]
+fn check_used_keys() {
+  // ソースコードで使われているキーが全て日本語の文章にあるかを調べる
+  let re = regex::Regex::new(r#"(?:text|fill)\(\s*"([a-z0-9_.]+)""#).unwrap();
+  let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+  let mut count = 0;
+  for entry in std::fs::read_dir(dir).unwrap() {
+    let path = entry.unwrap().path();
+    if path.extension().and_then(|s| s.to_str()) != Some("rs") {
+      continue;
+    }
+    let source = std::fs::read_to_string(&path).unwrap();
+    for cap in re.captures_iter(&source) {
+      let key = &cap[1];
+      assert!(
+        ja::BUNDLE.iter().any(|(k, _)| *k == key),
+        "{}: {} is missing",
+        path.display(),
+        key
+      );
+      count += 1;
+    }
+  }
+  assert!(count > 0);
+}
+
+#[test]
+fn check_fill() {
+  assert_eq!(Lang::from_name("en_US.UTF-8"), Some(Lang::En));
+  assert_eq!(Lang::from_name("C"), None);
+  assert_eq!(select_lang(None, Some("en"), Some("ja_JP.UTF-8")), Lang::En);
+  assert_eq!(select_lang(Some("ja"), Some("en"), None), Lang::Ja);
+  assert_eq!(select_lang(None, None, Some("C")), Lang::Ja);
+  assert_eq!(
+    fill("lend_success", &[&"0001", &"{1}", &3]),
+    "(3): 0001を{1}に貸し出しました"
+  );
+  // 引数の無い'{3}'などはそのまま残す
+  assert_eq!(fill("help", &[]), text("help"));
+}
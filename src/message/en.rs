@@ -0,0 +1,287 @@
+// 英語の文章
+// キーと引数は日本語の文章と揃える
+pub const BUNDLE: &[(&str, &str)] = &[
+  ("start", "This is the lending management software used by the electrical team of the Kaisei school festival preparation committee.\nIt was written in January 2021 by the electrical team's sub-chief of the committee that held the 149th anniversary Kaisei festival.\nIt comes with no warranty whatsoever.\nRepository: https://github.com/puripuri2100/dlm"),
+  ("restart", "\nEnter a command\nType help if you don't know how to use this"),
+  ("operator_banner", "\n[operator: {0}]"),
+  ("backfill_banner", "\n[backfill mode: {0}]"),
+  ("help", "These are the commands you can use in this software and what they do.\n\n  help      : Shows what you can enter and what it does (this message)\n\n  exit      : Quits\n\n  lend      : 'lend <item 1> <item 2> .. <item n> <group>' records a loan\n\n  l         : Short form of 'lend'\n              Used the same way as 'lend'\n\n  return    : 'return <item 1> <item 2> .. <item n> <group>' records a return\n\n  r         : Short form of 'return'\n              Used the same way as 'return'\n\n              Adding '--at <time>' to the end of 'lend' or 'return' records the operation as done at that time\n              Use this when entering operations that were written down on paper\n              For example, 'lend 0001 12 --at \"2020/11/23 17:40\"'\n\n              Add '--receipt' to make a receipt and save it in '<CSV file>.receipts'\n              With '--override', 'lend' lends beyond the per-group limits set in \"policy\" in the config file and records that it did\n              With '--condition <ok|damaged|missing-parts>', 'return' records the condition of the returned items\n              '--condition-note <note>' adds a note on the condition (e.g. 'return 0001 12 --condition damaged --condition-note \"broken cable\"')\n              The condition note is shown together with the condition and is kept apart from the operation note given with '-- <note>' at the end\n              Items returned as damaged or missing-parts cannot be lent until 'repair'\n\n  backfill  : 'backfill <time>' starts backfill mode\n              Until 'backfill end', every 'lend' and 'return' behaves as if '--at <time>' were given\n\n  edit      : 'edit <operation number> <new item> <new group>'\n              changes an earlier operation\n              'edit <operation number> --product <item> --destination <group> --time <time>'\n              changes only the fields you give (give at least one)\n              Give the time like 'edit 12 --time \"2020/11/23 17:40\"'\n              Operations made by 'remove' and 'edit' cannot be edited\n\n  remove    : 'remove <operation number>'\n              cancels an earlier operation\n              Adding '-- <note>' to the end of 'lend', 'return', 'edit', 'remove' or 'lost' records a note with the operation\n              For example, 'lend 0001 12 -- \"with adapter\"' (notes are shown in 'all' and 'all --item')\n\n  undo      : 'undo' alone undoes the operations of the last command in this session\n              'undo <n>' undoes the last n commands\n              Undoing is done by recording the same operation as 'remove'\n              It cannot undo when, for example, the same item was handled on another terminal\n\n  redo      : Redoes operations undone with 'undo'\n              'redo <n>' redoes n of them\n\n  show      : Shows the items that are currently lent and who has them\n              Item and group numbers are converted using the JSON file given at startup\n              'show --category <category>' shows only items of that category or tag\n              It ends with how many items of each category are out, like 'reel: 12 of 20 out' (without a category, only categories with items out)\n              Lost and retired items are not counted in the total\n              'show <item filter> <group filter>' shows only the matching ones\n              Filters are regular expressions\n              For example, 'show 0\\d{3} \\d' shows the reels that are lent\n              '\\d' means \"any digits\", '\\d{<number>}' means \"exactly that many digits\"\n              '^0' means \"starts with 0\", '0$' means \"ends with 0\"\n              Combined as '^0$', it means exactly \"0\"\n\n  all       : Shows all operations\n              'all --operator <name>' shows only the operations done by that operator\n              'all --item <item>' shows only the operations on that item (its history)\n              'all --overridden' shows only the loans made beyond the limits\n              'all --note <text>' shows only the operations whose note contains that text\n\n  login     : 'login <name>' sets the operator\n              The operator's name is recorded in every operation from then on\n              It can also be set with '--operator <name>' at startup\n\n  logout    : Clears the operator\n\n  check     : Checks for mismatches between loans and returns\n              Also checks whether the CSV file was changed (the same as 'verify')\n              'check --category <category>' shows only mismatches on items of that category or tag\n\n  verify    : Checks the hash recorded on each line of the CSV file for lines changed after they were recorded\n              Shows the first problem line and what changed compared with the backups\n\n  migrate   : Converts an old-format CSV file to the latest format\n              The file before conversion is saved as '<CSV file name>.v<version>.bak'\n\n  backup    : 'backup' alone makes a backup of the CSV file now\n              Backups are also made automatically every few operations and on exit\n              'backup list' shows the saved backups with their operation counts and last operations\n              'backup restore <id>' restores a backup\n              You can give the number shown by 'backup list' instead of the id\n\n  scan      : Starts a mode for recording loans with a barcode or QR code scanner\n              Scan the group first, then each scanned item is lent right away\n              'scan return' records returns instead\n              Items and groups are told apart using the config file, and scanning another group switches to it\n              Scan 'end' or enter a blank line to finish\n\n  labels    : Makes scanner labels from the items and groups in the config file\n              'labels items' makes only item labels, 'labels groups' only group cards\n              '--code qr' uses QR codes (Code128 by default), '--grid A5-2x5' changes the paper and layout (A4-3x8 by default)\n              '--out <file>' sets the output (.pdf or .svg, labels.pdf by default)\n\n  receipt   : 'receipt <op1> .. <opn>' makes the receipt for recorded operations again\n              '--format <text|html|pdf>' sets the format (the config file's format or text by default)\n              One receipt can only contain lends to, or returns from, a single group\n\n  report    : 'report final' shows the end-of-event reconciliation report\n              It lists loans per group, unreturned items with their last holder and room, items waiting for repair and who returned them, lost or retired items, lent items not in the config file,\n              catalog items never lent, every edit and removal with who and when, loans over the limits, and the findings of 'check'\n              '--out <file>' writes it to a file (.md, .html or .csv by extension)\n\n  stats     : Computes usage statistics from lend/return pairs\n              Shows loans and total/average time lent per item, peak simultaneous loans per category,\n              the busiest hours and loans per group (unreturned items count as lent until now)\n              '--from <time>' and '--to <time>' limit the period\n              '--category <category>' counts only items of that category or tag\n              '--out <file>' writes them to a file (.csv or .json by extension)\n\n  catalog   : Lists the items in the config file with their category, tags, attributes and status\n              'catalog --category <category>' lists only items of that category or tag\n\n  repair    : 'repair <item 1> .. <item n>' records that items waiting for repair were repaired so they can be lent again\n              Items waiting for repair are listed with the group that returned them in 'check' and 'report final'\n\n  lost      : 'lost <item> [note]' records that the item was lost\n              If it was lent, the loan is closed without a return and the loss is recorded against the holder\n\n  retire    : 'retire <item>' records that the item is no longer used\n              Items recorded by 'lost' or 'retire' cannot be lent after that\n              They are shown in 'check', 'report final', 'all --item' and 'catalog'\n\n  stocktake : Starts a stocktake\n              Scan or type every item at the desk, then scan 'end' or enter a blank line to finish\n              The scans are compared with the items in the config file that are not lent, lost or retired,\n              and it shows items not scanned, items scanned but recorded as lent (with the holder) and unknown codes\n              The result is saved in '<CSV file name>.stocktake'\n\n  history   : 'history' alone shows the last 10 inputs\n              'history <n>' shows the last n inputs\n              'history --failed' shows only the inputs that ended in an error\n              '!<n>' runs the n-th input shown by 'history' again, and '!!' runs the previous input again\n              Inputs are saved in '<CSV file name>.history' and can be recalled with the up/down keys or Ctrl-R search, even after restarting\n              The Tab key completes command names, items and groups ('return' only offers items that are lent)\n"),
+  ("not_found_command_name", "\n  There is no command named {0}.\n  See help for the commands you can use.\n  "),
+  ("missing_argument", "\n  The arguments are wrong.\n  {0}\n  Type help to check how to use the command\n"),
+  ("lend_success", "({2}): lent {0} to {1}"),
+  ("return_success", "({2}): {0} was returned by {1}"),
+  ("backfill_success", "The operations above were recorded as done at {0}"),
+  ("edit_success", "({2}): changed {1} of operation {0}"),
+  ("edit_success.product", "the item to \"{0}\""),
+  ("edit_success.destination", "the group to \"{0}\""),
+  ("edit_success.time", "the time to \"{0}\""),
+  ("list_separator", ", "),
+  ("old_format", "\nThe CSV file is written in an old format (version {0}).\nIt will be converted to the latest format (version {1}) when you run 'migrate' or record the next operation.\nThe file before conversion is kept under another name."),
+  ("read_only", "!  Operations that change the CSV file are not allowed in read-only mode\n"),
+  ("data_file_locked", "!  Another terminal is writing to the CSV file, so this operation was not done\n   Wait a moment and try again\n"),
+  ("data_file_changed", "The CSV file was updated on another terminal, so it was reloaded"),
+  ("target_changed", "!  Operation {0} was changed on another terminal while you were confirming, so this operation was cancelled\n   Please run it again\n"),
+  ("migrate_success", "Converted the CSV file from format version {0} to version {1}\nThe file before conversion was saved as {2}"),
+  ("backup_success", "Saved backup {0} to {1}"),
+  ("remove_success", "({1}): removed operation {0}"),
+  ("error.future_time", "Operations cannot be recorded at a future time"),
+  ("error.already_lent", "{0} is already lent to {1}, so this operation cannot be done"),
+  ("error.not_lent", "{0} is not lent, so this operation cannot be done"),
+  ("error.wrong_returner", "{0} was lent to {1} but is being returned by {2}, so this operation cannot be done"),
+  ("error.conflicts_with_later", "{0} conflicts with the following later operation, so this operation cannot be done\n{1}"),
+  ("error.unknown_operation", "Operation {0} was not found, so this operation cannot be done"),
+  ("error.future_operation", "Operation {0} has not happened yet, so it cannot be specified"),
+  ("error.cannot_edit_edit", "Operation {0} was made by 'remove' or 'edit', so it cannot be edited"),
+  ("error.has_dependent", "The following operation made on another terminal depends on the operations above, so this operation cannot be done\n{0}"),
+  ("lend_aborted", "All operations in this command were cancelled. Please enter the correct command again."),
+  ("check.already_lent", "{0} is lent twice"),
+  ("check.not_lent", "{0} is recorded as returned although it was not lent"),
+  ("undo_limit", "Only {0} commands can be undone in this session"),
+  ("redo_limit", "Only {0} commands can be redone in this session"),
+  ("ledger_error.future_time", "{0} is in the future"),
+  ("ledger_error.already_lent", "{0} is already lent to {1}"),
+  ("ledger_error.not_lent", "{0} is not lent"),
+  ("ledger_error.wrong_returner", "{0} is lent to {1} but was returned by {2}"),
+  ("ledger_error.conflicts_with_later", "{0} conflicts with later operation {1}"),
+  ("ledger_error.unknown_operation", "There is no operation {0}"),
+  ("ledger_error.future_operation", "Operation {0} has not happened yet"),
+  ("ledger_error.cannot_edit_edit", "Operation {0} was made by 'remove' or 'edit'"),
+  ("ledger_error.has_dependent", "Operation {1} depends on operation {0}"),
+  ("lend_data.lend", "lend {0} to {1}"),
+  ("lend_data.return", "{0} returned by {1}"),
+  ("lend_data.lend_quoted", "lend \"{0}\" to \"{1}\""),
+  ("lend_data.return_quoted", "\"{0}\" returned by \"{1}\""),
+  ("lend_data.edit", "change {1} in operation {0}"),
+  ("lend_data.remove", "cancel operation {0}"),
+  ("lend_data.backfilled", " (backfilled)"),
+  ("lend_data.operator", "  operator: {0}"),
+  ("lend_data.quote", "[{0}]"),
+  ("edit_data.product", "the item to \"{0}\""),
+  ("edit_data.destination", "the group to \"{0}\""),
+  ("edit_data.time", "the time to \"{0}\""),
+  ("edit_diff.product", "  item  : {0} → {1}\n"),
+  ("edit_diff.destination", "  group : {0} → {1}\n"),
+  ("edit_diff.time", "  time  : {0} → {1}\n"),
+  ("arg.option_value_missing", "Give a value for {0}"),
+  ("arg.unknown_option", "There is no option named {0}"),
+  ("arg.time_format", "Give the time like \"2020/11/23 17:40\""),
+  ("arg.no_argument", "This command takes no arguments"),
+  ("arg.at_most_one", "Give at most one argument"),
+  ("arg.at_most_two", "Give at most two arguments"),
+  ("arg.at_most_three", "Give at most three arguments"),
+  ("arg.number", "Give a number"),
+  ("arg.positive_number", "Give a number of 1 or more"),
+  ("arg.invalid_regex", "The argument is not a valid regular expression"),
+  ("arg.filter_needs_two", "A filter needs two arguments"),
+  ("arg.operator_name", "Give the operator's name"),
+  ("arg.backup_id", "Give the id of the backup to restore"),
+  ("arg.backup_usage", "Use one of 'backup', 'backup list' or 'backup restore <id>'"),
+  ("arg.lend_item", "Give the items to lend"),
+  ("arg.lend_destination", "Give the group to lend to"),
+  ("arg.return_item", "Give the items to return"),
+  ("arg.return_destination", "Give the group returning the items"),
+  ("arg.backfill_time", "Give a time or 'end'"),
+  ("arg.edit_target", "Give the number of the operation to edit"),
+  ("arg.edit_product", "Give the new item"),
+  ("arg.edit_destination", "Give the new group"),
+  ("arg.remove_target", "Give the number of the operation to remove"),
+  ("verify.field", "{0}: {1}"),
+  ("verify.field_diff", "{0}: {1} → {2}"),
+  ("verify.no_hash", "The CSV file has no hashes recorded\nConvert it to the latest format with 'migrate' to record them"),
+  ("verify.ok", "The hash chain of all {0} operations is intact"),
+  ("verify.missing_hash", "Line {0} (operation {1}) has no hash recorded\n  {2}"),
+  ("verify.link_broken", "The hash chain is broken at line {0} (operation {1})\n  The line before it was deleted, a line was inserted, or lines were reordered\n  {2}"),
+  ("verify.content_changed", "Line {0} (operation {1}) was changed after it was recorded\n  {2}"),
+  ("verify.prev_not_found", "The line that used to come before it was not found in the backups"),
+  ("verify.prev_found", "According to backup {0}, the line that used to come before it is\n  {1}"),
+  ("verify.original_not_found", "The original line was not found in the backups, so what changed is unknown"),
+  ("verify.original_found", "Compared with backup {0}, these columns have changed"),
+  ("undo.confirm", "The operations above will be cancelled\nAre you sure? [Y/n] >"),
+  ("cancelled", "Cancelled"),
+  ("no_data_file_name", "Give a file name!"),
+  ("newer_format", "The CSV file is written in a newer format than this dlm supports, so it cannot be read"),
+  ("config_error", "Failed to read or parse the JSON file"),
+  ("show.item", "Item"),
+  ("show.header", "     No.   Time               {0}   Group (name) (room)"),
+  ("check.start", "Starting the check"),
+  ("check.hash_chain", "Hash chain check"),
+  ("check.end", "Check finished"),
+  ("edit.confirm", "{0}\nOperation {1} will be changed as follows\n{2}Are you sure? [Y/n]\n    >"),
+  ("remove.confirm", "{0}\nOperation {1} will be cancelled\nAre you sure? [Y/n] >"),
+  ("backfill.start", "Started backfill mode\nUntil 'backfill end', 'lend' and 'return' are recorded as done at {0}"),
+  ("backfill.end", "Ended backfill mode"),
+  ("migrate.no_file", "The CSV file does not exist yet, so there is nothing to convert"),
+  ("migrate.up_to_date", "The CSV file is already in the latest format (version {0})"),
+  ("backup.failed", "Could not make a backup"),
+  ("backup.empty", "There are no backups yet"),
+  ("backup.op_count", "operations: {0}"),
+  ("backup.last_op", "last operation: {0}"),
+  ("backup.not_found", "No backup named {0} was found"),
+  ("backup.restore_op_count", "Operations in backup {0}: {1}"),
+  ("backup.restore_confirm", "The CSV file will be overwritten with this backup\nAre you sure? [Y/n] >"),
+  ("backup.restore_failed", "Failed to restore the backup"),
+  ("backup.restored", "Restored backup {0}"),
+  ("logout", "Logged out the operator"),
+  ("login", "Set the operator to \"{0}\"\nOperations from now on are recorded as done by {0}"),
+  ("history.not_found", "There is no input in the history for {0}"),
+  ("arg.scan_usage", "Use 'scan' or 'scan return'"),
+  ("scan.start_lend", "Started scan mode (lend)\nScan the group first, then the items to lend\nScan 'end' or enter a blank line to finish"),
+  ("scan.start_return", "Started scan mode (return)\nScan the group first, then the returned items\nScan 'end' or enter a blank line to finish"),
+  ("scan.destination_lend", "Lending to: {0}"),
+  ("scan.destination_return", "Returned by: {0}"),
+  ("scan.need_destination", "Scan a group first"),
+  ("scan.unknown", "{0} is not registered in the config file"),
+  ("scan.end", "Ended scan mode (recorded: {0}, errors: {1})"),
+  ("arg.label_code", "Use 'code128' or 'qr' for '--code'"),
+  ("arg.label_grid", "Give '--grid' a paper and columns x rows such as 'A4-3x8' (paper: A3, A4, A5, B5, Letter)"),
+  ("arg.label_out", "Give '--out' a file name ending in '.svg' or '.pdf'"),
+  ("labels.success", "Wrote {0} labels to {1}"),
+  ("labels.empty", "The config file has no items or groups to make labels for"),
+  ("labels.unencodable", "{0} contains characters Code128 cannot encode (use '--code qr')"),
+  ("labels.write_failed", "Could not write to {0}"),
+  ("arg.receipt_num", "Give the operation numbers to make a receipt for"),
+  ("arg.receipt_format", "Use 'text', 'html' or 'pdf' for '--format'"),
+  ("receipt.title_lend", "Lending receipt"),
+  ("receipt.title_return", "Return receipt"),
+  ("receipt.group", "Group: {0}"),
+  ("receipt.room", "Room: {0}"),
+  ("receipt.operator", "Operator: {0}"),
+  ("receipt.issued", "Issued: {0}"),
+  ("receipt.column_num", "Operation"),
+  ("receipt.column_time", "Time"),
+  ("receipt.column_item", "Item"),
+  ("receipt.count", "{0} item(s) in total"),
+  ("receipt.sign_lend", "Received by"),
+  ("receipt.sign_return", "Returned by"),
+  ("receipt.sign_operator", "Operator"),
+  ("receipt.success", "Saved the receipt to {0}"),
+  ("receipt.not_found", "{0} is not a lend or return operation, or has been removed"),
+  ("receipt.mixed", "A receipt can only contain lends to, or returns from, a single group"),
+  ("receipt.write_failed", "Could not write the receipt to {0}"),
+  ("arg.report_usage", "Use 'report final' or 'report final --out <file>'"),
+  ("arg.report_out", "Give '--out' a file name ending in '.md', '.html' or '.csv'"),
+  ("report.title", "Final lending report"),
+  ("report.summary", "Generated: {0}  Loans: {1}  Unreturned: {2}  Check findings: {3}"),
+  ("report.none", "None"),
+  ("report.group_title", "Loans per group"),
+  ("report.unreturned_title", "Items not returned"),
+  ("report.uncatalogued_title", "Lent items not in the catalog"),
+  ("report.unused_title", "Catalog items never lent"),
+  ("report.correction_title", "Edits and removals"),
+  ("report.finding_title", "Check findings"),
+  ("report.column_group", "Group"),
+  ("report.column_room", "Room"),
+  ("report.column_lend", "Lends"),
+  ("report.column_return", "Returns"),
+  ("report.column_outstanding", "Unreturned"),
+  ("report.column_item", "Item"),
+  ("report.column_holder", "Last holder"),
+  ("report.column_time", "Time"),
+  ("report.column_num", "Operation"),
+  ("report.column_operator", "Operator"),
+  ("report.column_detail", "Detail"),
+  ("report.success", "Wrote the report to {0}"),
+  ("report.write_failed", "Could not write the report to {0}"),
+  ("arg.stats_out", "Give '--out' a file name ending in '.csv' or '.json'"),
+  ("stats.window", "Period: {0} - {1}"),
+  ("stats.duration", "{0}h {1}m"),
+  ("stats.item_title", "Loans per item (longest total time first)"),
+  ("stats.item", "{0}: {1} loan(s), total {2}, average {3}"),
+  ("stats.category_title", "Peak simultaneous loans per category"),
+  ("stats.category", "{0}: peak {1} (of {2})  {3}"),
+  ("stats.hour_title", "Busiest hours"),
+  ("stats.hour", "{0}:00-: {1} operation(s)"),
+  ("stats.group_title", "Loans per group"),
+  ("stats.group", "{0}: {1} loan(s)"),
+  ("stats.success", "Wrote the statistics to {0}"),
+  ("stats.write_failed", "Could not write the statistics to {0}"),
+  ("show.category_summary", "{0}: {2} of {1} out"),
+  ("catalog.item", "{0}  category: {1}  {2}"),
+  ("catalog.tags", "    tags: {0}"),
+  ("catalog.attrs", "    attributes: {0}"),
+  ("catalog.available", "available"),
+  ("catalog.lent", "lent to {0}"),
+  ("catalog.empty", "No matching items"),
+  ("lend_data.overridden", " (over limit)"),
+  ("policy.too_many_items", "{0} may hold at most {1} items at once (this loan would make {2})"),
+  ("policy.too_many_in_category", "{0} may hold at most {2} items of {1} at once (this loan would make {3})"),
+  ("policy.not_allowed", "{0} may not borrow {2} items ({1})"),
+  ("policy.override_hint", "To lend beyond the limits, run the command again with '--override' (this is recorded)"),
+  ("policy.overridden", "Recorded that this loan exceeds the limits"),
+  ("report.overridden_title", "Loans over the limits"),
+  ("condition.ok", "ok"),
+  ("condition.damaged", "damaged"),
+  ("condition.missing_parts", "missing parts"),
+  ("lend_data.condition", " (condition: {0})"),
+  ("lend_data.condition_with_note", " (condition: {0}, note: {1})"),
+  ("lend_data.repair", "repair {0}"),
+  ("lend_data.repair_quoted", "repair \"{0}\""),
+  ("ledger_error.needs_repair", "{0} needs repair since return {1} and has not been repaired"),
+  ("ledger_error.not_damaged", "{0} is not waiting for repair"),
+  ("error.needs_repair", "{0} needs repair since the following return and has not been repaired, so it cannot be lent\n{1}"),
+  ("error.not_damaged", "{0} is not waiting for repair, so this operation cannot be done"),
+  ("check.needs_repair", "{0} is lent without being repaired after return {1}"),
+  ("check.damaged", "Items waiting for repair"),
+  ("check.damaged_item", "{0}: returned by {1} ({2}, operation {3})"),
+  ("return.needs_repair_notice", "Returned in a condition needing repair; it cannot be lent until 'repair'"),
+  ("repair_success", "({1}): {0} was repaired"),
+  ("arg.return_condition", "Give one of ok, damaged or missing-parts to '--condition' (given: {0})"),
+  ("arg.repair_item", "Give the items repaired"),
+  ("report.damaged_title", "Items waiting for repair"),
+  ("report.column_condition", "Condition"),
+  ("report.column_note", "Note"),
+  ("lend_data.lost", "{0} lost"),
+  ("lend_data.lost_by", "{0} lost by {1}"),
+  ("lend_data.retire", "retire {0}"),
+  ("lend_data.retire_from", "retire {0} (lent to {1})"),
+  ("lend_data.lost_quoted", "\"{0}\" lost"),
+  ("lend_data.lost_by_quoted", "\"{0}\" lost by \"{1}\""),
+  ("lend_data.retire_quoted", "retire \"{0}\""),
+  ("lend_data.retire_from_quoted", "retire \"{0}\" (lent to \"{1}\")"),
+  ("lend_data.note", " (note: {0})"),
+  ("ledger_error.out_of_circulation", "{0} was marked lost or retired by operation {1}"),
+  ("error.out_of_circulation", "{0} was marked lost or retired by the following operation, so this operation cannot be done\n{1}"),
+  ("check.out_of_circulation", "{0} is still handled after being marked lost or retired by operation {1}"),
+  ("check.retired", "Lost or retired items"),
+  ("out_of_circulation_success", "({0}): recorded {1}"),
+  ("arg.lost_item", "Give the item that was lost"),
+  ("arg.retire_item", "Give the item to retire"),
+  ("catalog.lost", "lost"),
+  ("catalog.retired", "retired"),
+  ("report.retired_title", "Lost or retired items"),
+  ("report.column_lost", "Lost"),
+  ("report.column_kind", "Kind"),
+  ("report.lost", "lost"),
+  ("report.retire", "retired"),
+  ("stocktake.start", "Stocktake started\nScan or type every item at the desk\nScan 'end' or enter a blank line to finish"),
+  ("stocktake.duplicate", "{0} was already scanned"),
+  ("stocktake.present", "{0}"),
+  ("stocktake.lent", "{0} is recorded as lent to {1}"),
+  ("stocktake.retired", "{0} is recorded as lost or retired"),
+  ("stocktake.title", "Stocktake: {0}"),
+  ("stocktake.title_with_operator", "Stocktake: {0}  operator: {1}"),
+  ("stocktake.scanned", "Codes scanned: {0}"),
+  ("stocktake.missing_title", "Items that should be here but were not scanned ({0})"),
+  ("stocktake.lent_title", "Items scanned but recorded as lent ({0})"),
+  ("stocktake.lent_item", "{0}: lent to {1}"),
+  ("stocktake.retired_title", "Items scanned but recorded as lost or retired ({0})"),
+  ("stocktake.unknown_title", "Codes not in the config file ({0})"),
+  ("stocktake.none", "none"),
+  ("stocktake.saved", "Saved the stocktake result to {0}"),
+  ("stocktake.write_failed", "Could not write the stocktake result to {0}"),
+  ("arg.note_missing", "Give a note after '--'"),
+  ("csv.missing_field", "The '{0}' column is missing"),
+  ("csv.invalid_field", "Cannot read '{1}' in the '{0}' column"),
+  ("csv.unknown_type", "There is no operation type '{0}'"),
+  ("invalid_row", "!  Cannot read line {0} of the CSV file ({1})\n   Fix that line or restore a backup with 'backup restore'\n   'verify' finds lines changed after they were recorded\n"),
+  ("invalid_row_write", "!  Operations that change the CSV file are not allowed while it has a line that cannot be read\n"),
+  ("verify.invalid_row", "Cannot read the operation on line {0} ({1})"),
+  ("cli.about", "Lending management software used by the electrical team"),
+  ("cli.data_file", "File that records the lending data such as groups and times (CSV)"),
+  ("cli.operator", "Name of the operator (can be changed later with the 'login' command)"),
+  ("cli.read_only", "Only show and check the data without writing to the CSV file"),
+  ("cli.backup_dir", "Directory to store the backups of the CSV file"),
+  ("cli.lang", "Language of the messages (ja or en)"),
+  ("cli.config", "File that maps numbers to item and group names (JSON)"),
+  ("data_file_write_failed", "Could not write to the CSV file {0} ({1})\nThe CSV file is left as it was, and this operation was not recorded"),
+];
@@ -0,0 +1,287 @@
+// 日本語の文章
+// '{0}'・'{1}'などは引数で置き換えられる
+pub const BUNDLE: &[(&str, &str)] = &[
+  ("start", "このソフトウェアは開成学園文化祭準備員会電気係で使用した、貸出品の管理ソフトウェアです。\n作成は2021年1月に創立149周年記念開成祭を開催した準備員会電気係サブチーフが行いました。\n使用についての保証は一切ありません。\nリポジトリ：https://github.com/puripuri2100/dlm"),
+  ("restart", "\n操作を行ってください\n操作方法がわからない場合は help と入力してください"),
+  ("operator_banner", "\n[担当者：{0}]"),
+  ("backfill_banner", "\n[後日入力モード：{0}]"),
+  ("help", "このソフトウェア上で使うことのできるコマンドとその役割は以下の通りです。\n\n  help      : 入力できる内容と役割（これです）を表示します\n\n  exit      : 終了します\n\n  lend      : 'lend <貸出品の番号1> <貸出品の番号2> .. <貸出品の番号n> <貸出先の番号>' で貸出を登録します\n\n  l         : 'lend' の省略形です\n              使い方は'lend'と変わりません\n\n  return    : 'return <返却品の番号1> <返却品の番号2> .. <返却品の番号n> <返却元の番号>' で返却を登録します\n\n  r         : 'return' の省略形です\n              使い方は'return'と変わりません\n\n              'lend'と'return'は最後に'--at <時刻>'を付けると、その時刻に行った操作として記録します\n              紙に記録していた操作を後から入力するときに使います\n              例えば'lend 0001 12 --at \"2020/11/23 17:40\"'のようにします\n\n              '--receipt' を付けると、貸出票・返却票を作って'<CSVファイル名>.receipts'に保存します\n              'lend'に'--override' を付けると、設定ファイルの\"policy\"で決めた団体ごとの制限を超えて貸し出し、そのことを記録します\n              'return'に'--condition <ok|damaged|missing-parts>' を付けると、返却されたときの品の状態を記録します\n              '--condition-note <備考>' で状態についての備考も記録できます（例：'return 0001 12 --condition damaged --condition-note \"ケーブルの断線\"'）\n              状態の備考は品の状態と一緒に表示され、最後に'-- <備考>'を付けて記録する操作の備考とは別に残ります\n              damagedかmissing-partsで返却された品は、'repair'するまで貸し出すことは出来ません\n\n  backfill  : 'backfill <時刻>' で後日入力モードを開始します\n              'backfill end' で終了するまで、全ての'lend'と'return'に'--at <時刻>'を付けたのと同じになります\n\n  edit      : 'edit <編集対象に付けられた通し番号> <編集後の品名の番号> <編集後の貸出先の番号>'\n              で以前に行った操作を改変できます\n              'edit <編集対象に付けられた通し番号> --product <品名の番号> --destination <貸出先の番号> --time <時刻>'\n              のようにすると、与えた項目だけを改変できます（どれか一つ以上を与えてください）\n              時刻は'edit 12 --time \"2020/11/23 17:40\"'のように与えます\n              'remove'と'edit'で行った操作を編集することは出来ません\n\n  remove    : 'remove <編集対象に付けられた通し番号>'\n              で以前に行った操作を無かったことにできます\n              'lend'・'return'・'edit'・'remove'・'lost'は最後に'-- <備考>'を付けると、操作に備考を付けて記録します\n              例えば'lend 0001 12 -- \"付属品あり\"'のようにします（備考は'all'と'all --item'に表示されます）\n\n  undo      : 'undo' 単体ではこのセッションで直前に行ったコマンドの操作を取り消します\n              'undo <n>' と、数字を与えるとその分だけ直近のコマンドの操作を取り消します\n              取り消しは'remove'と同じ操作を記録することで行われます\n              他の端末で同じ品名が扱われていた場合などは取り消すことが出来ません\n\n  redo      : 'undo'で取り消した操作をやり直します\n              'redo <n>' と、数字を与えるとその分だけやり直します\n\n  show      : 現在貸し出されているものと貸出先を表示します\n              品名と貸出先の番号は実行時に与えたJSONファイルに基づいて変換されます\n              'show --category <種類>' でその種類かタグの品名だけを表示します\n              最後に種類ごとに「全体の何点中何点が貸出中か」を表示します（種類を指定しない場合は貸出中のものがある種類だけ）\n              紛失・使用終了した品は全体の数に入りません\n              'show <品名の検索> <貸出先の検索>'で条件にあうもののみ表示することができます\n              検索の条件指定は正規表現で行います\n              例えば、'show 0\\d{3} \\d' で貸し出しているリールが分かります\n              '\\d'で「任意の数字列」、'\\d{<数字>}'で「指定した桁数の数字列」です\n              '^0'で「0から始まる文字列」、'0$'で「0で終わる文字列」です\n              組み合わせて'^0$'のようにすると、「0」を表すことができます\n\n  all       : 全ての操作を表示します\n              'all --operator <担当者の名前>'でその担当者が行った操作のみを表示します\n              'all --item <品名の番号>'でその品名についての操作（品名の履歴）のみを表示します\n              'all --overridden'で制限を超えて行った貸出のみを表示します\n              'all --note <文字列>'で備考にその文字列を含む操作のみを表示します\n\n  login     : 'login <担当者の名前>'で担当者を登録します\n              登録した担当者の名前は、これ以降の全ての操作に記録されます\n              起動時に'--operator <担当者の名前>'を与えることでも登録できます\n\n  logout    : 担当者の登録を解除します\n\n  check     : 貸出と返却が食い違っているものが無いかをチェックします\n              CSVファイルが書き換えられていないかのチェック（'verify'と同じもの）も行います\n              'check --category <種類>' でその種類かタグの品名に関わる食い違いだけを表示します\n\n  verify    : CSVファイルの各行に記録されたハッシュを検査し、記録された後に書き換えられた行が無いかをチェックします\n              最初に見つかった問題のある行と、バックアップと比べて何が変わったのかを表示します\n\n  migrate   : 古い形式のCSVファイルを最新の形式に変換します\n              変換前のファイルは'<CSVファイル名>.v<バージョン>.bak'という名前で保存されます\n\n  backup    : 'backup' 単体ではCSVファイルのバックアップを今すぐ取ります\n              バックアップは一定回数の操作ごとと終了時にも自動で取られます\n              'backup list' で保存されているバックアップと、その操作数と最後の操作を表示します\n              'backup restore <識別子>' でバックアップを復元します\n              識別子の代わりに'backup list'で表示される番号を与えることもできます\n\n  scan      : バーコードやQRコードのスキャナーで貸出を記録するモードを開始します\n              最初に貸出先を、続けて品名をスキャンすると、品名を読み取るたびに貸し出されます\n              'scan return' では返却を記録します\n              品名か団体かは設定ファイルで見分け、途中で団体の番号を読み取ると相手が切り替わります\n              'end'をスキャンするか空行を入力すると終了します\n\n  labels    : 設定ファイルの資材と参団から、スキャナーで読み取るためのラベルを作ります\n              'labels items' で品名のラベルだけを、'labels groups' で団体のカードだけを作ります\n              '--code qr' でQRコードに（標準はCode128）、'--grid A5-2x5' で用紙と並べ方を変えられます（標準はA4-3x8）\n              '--out <ファイル名>' で出力先を指定します（拡張子は.pdfか.svg、標準はlabels.pdf）\n\n  receipt   : 'receipt <操作番号1> .. <操作番号n>' で記録済みの操作から貸出票・返却票を作り直します\n              '--format <text|html|pdf>' で形式を指定できます（標準は設定ファイルの形式かtext）\n              一枚に載せられるのは、同じ団体への貸出か、同じ団体からの返却だけです\n\n  report    : 'report final' で行事の終わりの最終報告を表示します\n              団体ごとの貸出数、返却されていない品名と最後の貸出先・場所、修理待ちの品と返却した団体、紛失・使用終了した品、設定ファイルに無い品名の貸出、\n              一度も貸し出されなかった品名、全ての編集と削除（担当者と時刻）、制限を超えた貸出、'check'で見つかった問題をまとめます\n              '--out <ファイル名>' でファイルに書き出します（拡張子で.md・.html・.csvを選びます）\n\n  stats     : 貸出と返却の組から利用状況を集計します\n              品名ごとの貸出回数・貸し出されていた時間の合計と平均、種類ごとの同時に貸し出された数の最大、\n              操作の多い時間帯、団体ごとの貸出回数を表示します（返却されていないものは今まで貸し出されていたものとします）\n              '--from <時刻>'・'--to <時刻>' で集計する期間を絞り込めます\n              '--category <種類>' でその種類かタグの品名だけを集計します\n              '--out <ファイル名>' でファイルに書き出します（拡張子で.csvか.jsonを選びます）\n\n  catalog   : 設定ファイルにある品名を、種類・タグ・属性・貸出状況と共に一覧にします\n              'catalog --category <種類>' でその種類かタグの品名だけを表示します\n\n  repair    : 'repair <品名の番号1> .. <品名の番号n>' で修理を待っている品を修理したことを記録し、再び貸し出せるようにします\n              修理を待っている品は'check'と'report final'に、返却した団体と共に表示されます\n\n  lost      : 'lost <品名の番号> [備考]' でその品を紛失したことを記録します\n              貸し出されていた場合は返却の代わりに貸出を終わらせ、そのときの貸出先が紛失したものとして記録します\n\n  retire    : 'retire <品名の番号>' でその品を使わなくなったことを記録します\n              'lost'と'retire'を記録した品は、それ以降貸し出すことは出来ません\n              'check'・'report final'・'all --item'・'catalog'に表示されます\n\n  stocktake : 棚卸しを開始します\n              手元にある品名を全てスキャンするか入力し、'end'をスキャンするか空行を入力すると終了します\n              設定ファイルにある品名から貸出中と紛失・使用終了のものを除いたものと突き合わせ、\n              読み取られなかった品、貸出中の記録になっている品（と貸出先）、設定ファイルに無い番号を表示します\n              結果は'<CSVファイル名>.stocktake'に保存されます\n\n  history   : 'history' 単体では直近10件の入力を表示します\n              'history <n>' と、数字を与えるとその分だけ直近の入力を表示します\n              'history --failed' でエラーになった入力だけを表示します\n              '!<n>' で'history'に表示されるn番目の入力を、'!!' で直前の入力をもう一度実行します\n              入力の履歴は'<CSVファイル名>.history'に保存され、次に起動したときも↑↓キーやCtrl-Rの検索で呼び出せます\n              Tabキーでコマンド名・品名・貸出先を補完できます（'return'では貸出中のものだけが候補になります）\n"),
+  ("not_found_command_name", "\n  {0}というコマンド名は見つかりませんでした。\n  使うことのできるコマンド名は help を見てください。\n  "),
+  ("missing_argument", "\n  引数を間違えています。\n  {0}\n  helpを入力して使い方を確認してください\n"),
+  ("lend_success", "({2}): {0}を{1}に貸し出しました"),
+  ("return_success", "({2}): {0}が{1}から返却されました"),
+  ("backfill_success", "上記の操作は{0}に行われたものとして記録しました"),
+  ("edit_success", "({2}): 操作番号{0}の{1}書き換えました"),
+  ("edit_success.product", "品名を「{0}」に"),
+  ("edit_success.destination", "貸出先を「{0}」に"),
+  ("edit_success.time", "時刻を「{0}」に"),
+  ("list_separator", "、"),
+  ("old_format", "\nCSVファイルは古い形式（バージョン{0}）で書かれています。\n'migrate'コマンドを実行するか、次に操作を記録した時に最新の形式（バージョン{1}）へ変換されます。\n変換前のファイルは別の名前で保存されます。"),
+  ("read_only", "!  読み取り専用モードのため、CSVファイルを書き換える操作は行えません\n"),
+  ("data_file_locked", "!  他の端末でCSVファイルに書き込み中のため、この操作を行うことは出来ませんでした\n   少し待ってから再度実行してください\n"),
+  ("data_file_changed", "他の端末でCSVファイルが更新されていたため、読み込み直しました"),
+  ("target_changed", "!  確認している間に他の端末で{0}番の操作が変更されたため、この操作を中止しました\n   再度実行してください\n"),
+  ("migrate_success", "CSVファイルをバージョン{0}からバージョン{1}の形式に変換しました\n変換前のファイルは{2}に保存しました"),
+  ("backup_success", "バックアップ{0}を{1}に保存しました"),
+  ("remove_success", "({1}): 操作番号{0}を削除しました"),
+  ("error.future_time", "未来の時刻で操作を記録することは出来ません"),
+  ("error.already_lent", "{0}が既に{1}に貸し出されているのでこの操作を行うことはできません"),
+  ("error.not_lent", "{0}がまだ貸し出されてないのでこの操作を行うことは出来ません"),
+  ("error.wrong_returner", "{0}の貸出先（{1}）と返却先（{2}）が一致していないため、この操作を行うことは出来ません"),
+  ("error.conflicts_with_later", "{0}はこの時刻より後の以下の操作と矛盾するため、この操作を行うことはできません\n{1}"),
+  ("error.unknown_operation", "{0}番の操作が見つからないため、この操作を行うことは出来ません"),
+  ("error.future_operation", "{0}番はまだ行われていない未来の操作のため、指定することは出来ません"),
+  ("error.cannot_edit_edit", "{0}番は'remove'または'edit'で行った操作のため、編集することは出来ません"),
+  ("error.has_dependent", "他の端末で行われた以下の操作が上の操作を前提としているため、この操作を行うことは出来ません\n{0}"),
+  ("lend_aborted", "今回行われた操作は全て中止されました。再度正しい貸出を実行してください。"),
+  ("check.already_lent", "{0}が2重に貸し出されています"),
+  ("check.not_lent", "{0}が貸し出されていないにもかかわらず返却されたことになっています"),
+  ("undo_limit", "このセッションで取り消すことのできる操作は{0}回分です"),
+  ("redo_limit", "このセッションでやり直すことのできる操作は{0}回分です"),
+  ("ledger_error.future_time", "{0}は未来の時刻です"),
+  ("ledger_error.already_lent", "{0}は既に{1}に貸し出されています"),
+  ("ledger_error.not_lent", "{0}は貸し出されていません"),
+  ("ledger_error.wrong_returner", "{0}の貸出先は{1}ですが、返却元は{2}です"),
+  ("ledger_error.conflicts_with_later", "{0}はこの時刻より後の{1}番の操作と矛盾します"),
+  ("ledger_error.unknown_operation", "{0}番の操作はありません"),
+  ("ledger_error.future_operation", "{0}番の操作はまだ行われていません"),
+  ("ledger_error.cannot_edit_edit", "{0}番は'remove'または'edit'で行った操作です"),
+  ("ledger_error.has_dependent", "{0}番の操作を前提とする{1}番の操作があります"),
+  ("lend_data.lend", "{0}を{1}へ貸出"),
+  ("lend_data.return", "{0}を{1}が返却"),
+  ("lend_data.lend_quoted", "\"{0}\"を\"{1}\"へ貸出"),
+  ("lend_data.return_quoted", "\"{0}\"を\"{1}\"が返却"),
+  ("lend_data.edit", "{0}番目の操作の{1}修正する"),
+  ("lend_data.remove", "{0}番目の操作を無かったことにする"),
+  ("lend_data.backfilled", "（後日入力）"),
+  ("lend_data.operator", "  担当者：{0}"),
+  ("lend_data.quote", "「{0}」"),
+  ("edit_data.product", "品名を\"{0}\"に"),
+  ("edit_data.destination", "相手を\"{0}\"に"),
+  ("edit_data.time", "時刻を\"{0}\"に"),
+  ("edit_diff.product", "  品名  : {0} → {1}\n"),
+  ("edit_diff.destination", "  相手  : {0} → {1}\n"),
+  ("edit_diff.time", "  時刻  : {0} → {1}\n"),
+  ("arg.option_value_missing", "{0}の値を与えてください"),
+  ("arg.unknown_option", "{0}というオプションはありません"),
+  ("arg.time_format", "時刻は\"2020/11/23 17:40\"のように与えてください"),
+  ("arg.no_argument", "引数は不要です"),
+  ("arg.at_most_one", "引数は1つまでです"),
+  ("arg.at_most_two", "引数は2つまでです"),
+  ("arg.at_most_three", "引数は3つまでです"),
+  ("arg.number", "数字を与えてください"),
+  ("arg.positive_number", "1以上の数字を与えてください"),
+  ("arg.invalid_regex", "正規表現として不正な引数です"),
+  ("arg.filter_needs_two", "フィルターを書く場合は引数は2つ必要です"),
+  ("arg.operator_name", "担当者の名前を与えてください"),
+  ("arg.backup_id", "復元するバックアップの識別子を与えてください"),
+  ("arg.backup_usage", "'backup'・'backup list'・'backup restore <識別子>'のいずれかを与えてください"),
+  ("arg.lend_item", "貸出品を与えてください"),
+  ("arg.lend_destination", "貸出先を与えてください"),
+  ("arg.return_item", "返却品を与えてください"),
+  ("arg.return_destination", "返却先を与えてください"),
+  ("arg.backfill_time", "時刻もしくは'end'を与えてください"),
+  ("arg.edit_target", "編集対象の通し番号を与えてください"),
+  ("arg.edit_product", "編集後の品名を与えてください"),
+  ("arg.edit_destination", "編集後の貸出先を与えてください"),
+  ("arg.remove_target", "削除対象の操作の番号を与えてください"),
+  ("verify.field", "{0}：{1}"),
+  ("verify.field_diff", "{0}：{1} → {2}"),
+  ("verify.no_hash", "CSVファイルにハッシュが記録されていません\n'migrate'で最新の形式に変換するとハッシュが記録されます"),
+  ("verify.ok", "全{0}件の操作のハッシュチェーンに問題はありませんでした"),
+  ("verify.missing_hash", "{0}行目（操作番号{1}）にハッシュが記録されていません\n  {2}"),
+  ("verify.link_broken", "{0}行目（操作番号{1}）でハッシュチェーンが途切れています\n  この行の直前の行が削除されたか、行が挿入されたか、順番が入れ替えられています\n  {2}"),
+  ("verify.content_changed", "{0}行目（操作番号{1}）の中身が記録された後に書き換えられています\n  {2}"),
+  ("verify.prev_not_found", "本来直前にあった行はバックアップに見つかりませんでした"),
+  ("verify.prev_found", "バックアップ{0}によると、本来直前にあったのは以下の行です\n  {1}"),
+  ("verify.original_not_found", "元の内容はバックアップに見つからなかったため、何が変わったのかは分かりません"),
+  ("verify.original_found", "バックアップ{0}の内容と比べると、以下の欄が変わっています"),
+  ("undo.confirm", "以上の操作を無かったことにします\n本当に良いですか？[Y/n] >"),
+  ("cancelled", "操作を中止しました"),
+  ("no_data_file_name", "ファイル名を入力してください！"),
+  ("newer_format", "CSVファイルがこのdlmよりも新しい形式で書かれているため、読み込むことが出来ません"),
+  ("config_error", "JSONファイルの読み込み・解析に失敗しました"),
+  ("show.item", "貸出品"),
+  ("show.header", "操作番号   時刻               {0}   貸出先（団体名）（場所）"),
+  ("check.start", "検査を開始します"),
+  ("check.hash_chain", "ハッシュチェーンの検査"),
+  ("check.end", "検査を終了しました"),
+  ("edit.confirm", "{0}\nという{1}番の操作を以下のように変更します\n{2}本当に良いですか？[Y/n]\n    >"),
+  ("remove.confirm", "{0}\nという{1}番の操作を無かったことにします\n本当に良いですか？[Y/n] >"),
+  ("backfill.start", "後日入力モードを開始しました\n'backfill end'で終了するまで、'lend'と'return'は{0}に行われたものとして記録されます"),
+  ("backfill.end", "後日入力モードを終了しました"),
+  ("migrate.no_file", "CSVファイルがまだ作られていないので、変換の必要はありません"),
+  ("migrate.up_to_date", "CSVファイルは既に最新の形式（バージョン{0}）です"),
+  ("backup.failed", "バックアップを取ることが出来ませんでした"),
+  ("backup.empty", "バックアップはまだありません"),
+  ("backup.op_count", "操作数：{0}"),
+  ("backup.last_op", "最後の操作：{0}"),
+  ("backup.not_found", "{0}というバックアップは見つかりませんでした"),
+  ("backup.restore_op_count", "バックアップ{0}の操作数：{1}"),
+  ("backup.restore_confirm", "このバックアップの内容でCSVファイルを上書きします\n本当に良いですか？[Y/n] >"),
+  ("backup.restore_failed", "バックアップの復元に失敗しました"),
+  ("backup.restored", "バックアップ{0}を復元しました"),
+  ("logout", "担当者の登録を解除しました"),
+  ("login", "担当者を「{0}」にしました\nこれ以降の操作は{0}が行ったものとして記録されます"),
+  ("history.not_found", "{0}に当たる入力は履歴にありません"),
+  ("arg.scan_usage", "'scan'または'scan return'を与えてください"),
+  ("scan.start_lend", "スキャンモード（貸出）を開始しました\n最初に貸出先を、続けて貸し出す品名をスキャンしてください\n'end'をスキャンするか空行を入力すると終了します"),
+  ("scan.start_return", "スキャンモード（返却）を開始しました\n最初に返却元を、続けて返却された品名をスキャンしてください\n'end'をスキャンするか空行を入力すると終了します"),
+  ("scan.destination_lend", "貸出先：{0}"),
+  ("scan.destination_return", "返却元：{0}"),
+  ("scan.need_destination", "先に団体の番号をスキャンしてください"),
+  ("scan.unknown", "{0}は設定ファイルに登録されていない番号です"),
+  ("scan.end", "スキャンモードを終了しました（記録：{0}件、エラー：{1}件）"),
+  ("arg.label_code", "'--code'には'code128'か'qr'を与えてください"),
+  ("arg.label_grid", "'--grid'には'A4-3x8'のように用紙と列数x行数を与えてください（用紙はA3・A4・A5・B5・Letter）"),
+  ("arg.label_out", "'--out'には拡張子が'.svg'か'.pdf'のファイル名を与えてください"),
+  ("labels.success", "{0}枚のラベルを{1}に書き出しました"),
+  ("labels.empty", "ラベルにする資材や参団が設定ファイルにありません"),
+  ("labels.unencodable", "{0}はCode128で表せない文字を含んでいます（'--code qr'を使ってください）"),
+  ("labels.write_failed", "{0}に書き込めませんでした"),
+  ("arg.receipt_num", "貸出票を作る操作番号を与えてください"),
+  ("arg.receipt_format", "'--format'には'text'・'html'・'pdf'のいずれかを与えてください"),
+  ("receipt.title_lend", "貸出票"),
+  ("receipt.title_return", "返却票"),
+  ("receipt.group", "団体：{0}"),
+  ("receipt.room", "場所：{0}"),
+  ("receipt.operator", "担当者：{0}"),
+  ("receipt.issued", "発行：{0}"),
+  ("receipt.column_num", "操作番号"),
+  ("receipt.column_time", "時刻"),
+  ("receipt.column_item", "品名"),
+  ("receipt.count", "計{0}点"),
+  ("receipt.sign_lend", "受け取った人の署名"),
+  ("receipt.sign_return", "返却した人の署名"),
+  ("receipt.sign_operator", "担当者の署名"),
+  ("receipt.success", "貸出票を{0}に保存しました"),
+  ("receipt.not_found", "{0}は貸出か返却の操作ではないか、削除されています"),
+  ("receipt.mixed", "一枚の貸出票には、同じ団体への貸出か、同じ団体からの返却だけを載せられます"),
+  ("receipt.write_failed", "{0}に貸出票を書き込めませんでした"),
+  ("arg.report_usage", "'report final'または'report final --out <ファイル名>'を与えてください"),
+  ("arg.report_out", "'--out'には拡張子が'.md'・'.html'・'.csv'のいずれかのファイル名を与えてください"),
+  ("report.title", "貸出の最終報告"),
+  ("report.summary", "作成：{0}　貸出：{1}件　未返却：{2}件　検査で見つかった問題：{3}件"),
+  ("report.none", "該当なし"),
+  ("report.group_title", "団体ごとの貸出"),
+  ("report.unreturned_title", "返却されていない品名"),
+  ("report.uncatalogued_title", "設定ファイルに無い品名の貸出"),
+  ("report.unused_title", "一度も貸し出されなかった品名"),
+  ("report.correction_title", "編集と削除"),
+  ("report.finding_title", "検査で見つかった問題"),
+  ("report.column_group", "団体"),
+  ("report.column_room", "場所"),
+  ("report.column_lend", "貸出"),
+  ("report.column_return", "返却"),
+  ("report.column_outstanding", "未返却"),
+  ("report.column_item", "品名"),
+  ("report.column_holder", "最後の貸出先"),
+  ("report.column_time", "時刻"),
+  ("report.column_num", "操作番号"),
+  ("report.column_operator", "担当者"),
+  ("report.column_detail", "内容"),
+  ("report.success", "報告書を{0}に書き出しました"),
+  ("report.write_failed", "{0}に報告書を書き込めませんでした"),
+  ("arg.stats_out", "'--out'には拡張子が'.csv'か'.json'のファイル名を与えてください"),
+  ("stats.window", "集計期間：{0} 〜 {1}"),
+  ("stats.duration", "{0}時間{1}分"),
+  ("stats.item_title", "品名ごとの貸出（貸し出されていた時間の長い順）"),
+  ("stats.item", "{0}：{1}回　合計{2}　平均{3}"),
+  ("stats.category_title", "種類ごとの同時に貸し出された数の最大"),
+  ("stats.category", "{0}：最大{1}点（全{2}点中）　{3}"),
+  ("stats.hour_title", "操作の多い時間帯"),
+  ("stats.hour", "{0}時台：{1}件"),
+  ("stats.group_title", "団体ごとの貸出の回数"),
+  ("stats.group", "{0}：{1}回"),
+  ("stats.success", "集計結果を{0}に書き出しました"),
+  ("stats.write_failed", "{0}に集計結果を書き込めませんでした"),
+  ("show.category_summary", "{0}：{1}点中{2}点が貸出中"),
+  ("catalog.item", "{0}  種類：{1}  {2}"),
+  ("catalog.tags", "    タグ：{0}"),
+  ("catalog.attrs", "    属性：{0}"),
+  ("catalog.available", "貸出可能"),
+  ("catalog.lent", "{0}に貸出中"),
+  ("catalog.empty", "該当する品名はありません"),
+  ("lend_data.overridden", "（制限超過）"),
+  ("policy.too_many_items", "{0}が同時に借りられるのは{1}点までです（貸し出すと{2}点になります）"),
+  ("policy.too_many_in_category", "{0}が同時に借りられる{1}は{2}点までです（貸し出すと{3}点になります）"),
+  ("policy.not_allowed", "{0}は{2}の品（{1}）を借りられません"),
+  ("policy.override_hint", "制限を超えて貸し出す場合は、'--override'を付けて実行してください（そのことが記録に残ります）"),
+  ("policy.overridden", "制限を超えて貸し出したことを記録しました"),
+  ("report.overridden_title", "制限を超えた貸出"),
+  ("condition.ok", "問題なし"),
+  ("condition.damaged", "破損"),
+  ("condition.missing_parts", "部品の欠け"),
+  ("lend_data.condition", "（状態：{0}）"),
+  ("lend_data.condition_with_note", "（状態：{0}、備考：{1}）"),
+  ("lend_data.repair", "{0}を修理"),
+  ("lend_data.repair_quoted", "\"{0}\"を修理"),
+  ("ledger_error.needs_repair", "{0}は{1}番の返却で修理が必要とされ、まだ修理されていません"),
+  ("ledger_error.not_damaged", "{0}は修理を待っていません"),
+  ("error.needs_repair", "{0}は以下の返却で修理が必要とされ、まだ修理されていないため、貸し出すことは出来ません\n{1}"),
+  ("error.not_damaged", "{0}は修理を待っていないため、この操作を行うことは出来ません"),
+  ("check.needs_repair", "{0}が{1}番の返却の後、修理されないまま貸し出されています"),
+  ("check.damaged", "修理待ちの品"),
+  ("check.damaged_item", "{0}：{1}が返却（{2}、操作番号{3}）"),
+  ("return.needs_repair_notice", "修理が必要な状態で返却されたので、'repair'するまで貸し出すことは出来ません"),
+  ("repair_success", "({1}): {0}を修理しました"),
+  ("arg.return_condition", "'--condition'には ok, damaged, missing-parts のいずれかを与えてください（与えられた値：{0}）"),
+  ("arg.repair_item", "修理した品を与えてください"),
+  ("report.damaged_title", "修理待ちの品"),
+  ("report.column_condition", "状態"),
+  ("report.column_note", "備考"),
+  ("lend_data.lost", "{0}を紛失"),
+  ("lend_data.lost_by", "{0}を{1}が紛失"),
+  ("lend_data.retire", "{0}の使用を終了"),
+  ("lend_data.retire_from", "{0}の使用を終了（{1}に貸出中）"),
+  ("lend_data.lost_quoted", "\"{0}\"を紛失"),
+  ("lend_data.lost_by_quoted", "\"{0}\"を\"{1}\"が紛失"),
+  ("lend_data.retire_quoted", "\"{0}\"の使用を終了"),
+  ("lend_data.retire_from_quoted", "\"{0}\"の使用を終了（\"{1}\"に貸出中）"),
+  ("lend_data.note", "（備考：{0}）"),
+  ("ledger_error.out_of_circulation", "{0}は{1}番の操作で紛失・使用終了とされています"),
+  ("error.out_of_circulation", "{0}は以下の操作で紛失・使用終了とされているため、この操作を行うことは出来ません\n{1}"),
+  ("check.out_of_circulation", "{0}は{1}番で紛失・使用終了とされた後も扱われています"),
+  ("check.retired", "紛失・使用終了した品"),
+  ("out_of_circulation_success", "({0}): 「{1}」と記録しました"),
+  ("arg.lost_item", "紛失した品を与えてください"),
+  ("arg.retire_item", "使用を終える品を与えてください"),
+  ("catalog.lost", "紛失"),
+  ("catalog.retired", "使用終了"),
+  ("report.retired_title", "紛失・使用終了した品"),
+  ("report.column_lost", "紛失"),
+  ("report.column_kind", "区分"),
+  ("report.lost", "紛失"),
+  ("report.retire", "使用終了"),
+  ("stocktake.start", "棚卸しを開始しました\n手元にある品名を全てスキャンするか入力してください\n'end'をスキャンするか空行を入力すると終了します"),
+  ("stocktake.duplicate", "{0}は既に読み取りました"),
+  ("stocktake.present", "{0}"),
+  ("stocktake.lent", "{0}は{1}に貸出中の記録になっています"),
+  ("stocktake.retired", "{0}は紛失・使用終了の記録になっています"),
+  ("stocktake.title", "棚卸し：{0}"),
+  ("stocktake.title_with_operator", "棚卸し：{0}　担当者：{1}"),
+  ("stocktake.scanned", "読み取った番号：{0}件"),
+  ("stocktake.missing_title", "手元にあるはずなのに読み取られなかった品（{0}点）"),
+  ("stocktake.lent_title", "読み取ったが貸出中の記録になっている品（{0}点）"),
+  ("stocktake.lent_item", "{0}：{1}に貸出中"),
+  ("stocktake.retired_title", "読み取ったが紛失・使用終了の記録になっている品（{0}点）"),
+  ("stocktake.unknown_title", "設定ファイルに無い番号（{0}件）"),
+  ("stocktake.none", "なし"),
+  ("stocktake.saved", "棚卸しの結果を{0}に保存しました"),
+  ("stocktake.write_failed", "{0}に棚卸しの結果を書き込めませんでした"),
+  ("arg.note_missing", "'--'の後に備考を与えてください"),
+  ("csv.missing_field", "「{0}」の欄がありません"),
+  ("csv.invalid_field", "「{0}」の欄の値「{1}」が読み取れません"),
+  ("csv.unknown_type", "「{0}」という種類の操作はありません"),
+  ("invalid_row", "!  CSVファイルの{0}行目が読み取れません（{1}）\n   その行を直すか、'backup restore'でバックアップを復元してください\n   'verify'で記録された後に書き換えられた行を調べられます\n"),
+  ("invalid_row_write", "!  CSVファイルに読み取れない行があるため、CSVファイルを書き換える操作は行えません\n"),
+  ("verify.invalid_row", "{0}行目の操作が読み取れません（{1}）"),
+  ("cli.about", "電気係の使用する貸出管理ソフトウェア"),
+  ("cli.data_file", "貸出先や貸出時刻などのデータを記録したファイル（CSV形式）"),
+  ("cli.operator", "操作を行う担当者の名前（'login'コマンドで後から変更することもできます）"),
+  ("cli.read_only", "CSVファイルを書き換えずに、表示や検査だけを行う"),
+  ("cli.backup_dir", "CSVファイルのバックアップを保存するディレクトリ"),
+  ("cli.lang", "表示に使う言語（ja・en）"),
+  ("cli.config", "資材や参団の名前の対応ファイル（JSON形式）"),
+  ("data_file_write_failed", "CSVファイル{0}に書き込めませんでした（{1}）\nCSVファイルは書き込む前のままで、この操作は記録されていません"),
+];
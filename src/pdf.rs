@@ -0,0 +1,129 @@
+use crate::get_char_len;
+use std::fmt::Write;
+
+// 用紙に描く図形（単位はmmで、原点は用紙の左上）
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Shape {
+  // 黒く塗りつぶす長方形
+  Rect(f64, f64, f64, f64),
+  // 中央揃えの文字列：中心のx座標、ベースラインのy座標、文字の大きさ、文字列
+  Text(f64, f64, f64, String),
+  // 左揃えの文字列：左端のx座標、ベースラインのy座標、文字の大きさ、文字列
+  LeftText(f64, f64, f64, String),
+}
+
+// mmからPDFの単位（1/72インチ）に直す
+fn mm_to_pt(mm: f64) -> f64 {
+  mm * 72.0 / 25.4
+}
+
+// 文字列をUTF-16BEの16進数表記にする
+// 基本多言語面に無い文字は'?'にする
+fn pdf_hex_string(s: &str) -> String {
+  s.chars()
+    .map(|c| {
+      let n = if (c as u32) <= 0xffff {
+        c as u32
+      } else {
+        '?' as u32
+      };
+      format!("{:04X}", n)
+    })
+    .collect()
+}
+
+// 図形を並べたページのリストを、用紙の大きさ（mm）を指定してPDFにする
+// 日本語の名前を書けるように、埋め込まないCIDフォント（小塚明朝）をAdobe-Japan1で指定する
+// ASCII文字は半角の字形を使い、幅を全角の半分として中央揃えにする
+pub(crate) fn write_pdf(page_lst: &[Vec<Shape>], paper_width: f64, paper_height: f64) -> Vec<u8> {
+  let paper_w = mm_to_pt(paper_width);
+  let paper_h = mm_to_pt(paper_height);
+  // 1: カタログ、2: ページの一覧、3〜5: フォント、6以降: ページと内容
+  let mut object_lst = vec![
+    "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+    {
+      let kids: Vec<String> = (0..page_lst.len())
+        .map(|i| format!("{} 0 R", 6 + i * 2))
+        .collect();
+      format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        page_lst.len()
+      )
+    },
+    "<< /Type /Font /Subtype /Type0 /BaseFont /KozMinPr6N-Regular /Encoding /UniJIS-UCS2-HW-H /DescendantFonts [4 0 R] >>".to_string(),
+    "<< /Type /Font /Subtype /CIDFontType0 /BaseFont /KozMinPr6N-Regular /CIDSystemInfo << /Registry (Adobe) /Ordering (Japan1) /Supplement 6 >> /FontDescriptor 5 0 R /DW 1000 /W [231 325 500] >>".to_string(),
+    "<< /Type /FontDescriptor /FontName /KozMinPr6N-Regular /Flags 6 /FontBBox [-437 -340 1147 1317] /ItalicAngle 0 /Ascent 1317 /Descent -349 /CapHeight 742 /StemV 80 >>".to_string(),
+  ];
+  for (i, page) in page_lst.iter().enumerate() {
+    let mut content = String::new();
+    for shape in page.iter() {
+      match shape {
+        Shape::Rect(x, y, w, h) => {
+          let _ = writeln!(
+            content,
+            "{:.3} {:.3} {:.3} {:.3} re f",
+            mm_to_pt(*x),
+            paper_h - mm_to_pt(y + h),
+            mm_to_pt(*w),
+            mm_to_pt(*h)
+          );
+        }
+        Shape::Text(x, y, size, text) => {
+          let width = get_char_len(text) as f64 / 2.0 * size;
+          let _ = writeln!(
+            content,
+            "BT /F1 {:.3} Tf {:.3} {:.3} Td <{}> Tj ET",
+            mm_to_pt(*size),
+            mm_to_pt(x - width / 2.0),
+            paper_h - mm_to_pt(*y),
+            pdf_hex_string(text)
+          );
+        }
+        Shape::LeftText(x, y, size, text) => {
+          let _ = writeln!(
+            content,
+            "BT /F1 {:.3} Tf {:.3} {:.3} Td <{}> Tj ET",
+            mm_to_pt(*size),
+            mm_to_pt(*x),
+            paper_h - mm_to_pt(*y),
+            pdf_hex_string(text)
+          );
+        }
+      }
+    }
+    object_lst.push(format!(
+      "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.3} {:.3}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+      paper_w,
+      paper_h,
+      7 + i * 2
+    ));
+    object_lst.push(format!(
+      "<< /Length {} >>\nstream\n{}endstream",
+      content.len(),
+      content
+    ));
+  }
+  let mut pdf = String::from("%PDF-1.4\n");
+  let mut offset_lst = Vec::new();
+  for (i, object) in object_lst.iter().enumerate() {
+    offset_lst.push(pdf.len());
+    let _ = write!(pdf, "{} 0 obj\n{}\nendobj\n", i + 1, object);
+  }
+  let xref = pdf.len();
+  let _ = write!(
+    pdf,
+    "xref\n0 {}\n0000000000 65535 f \n",
+    object_lst.len() + 1
+  );
+  for offset in offset_lst.iter() {
+    let _ = writeln!(pdf, "{:010} 00000 n ", offset);
+  }
+  let _ = write!(
+    pdf,
+    "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+    object_lst.len() + 1,
+    xref
+  );
+  pdf.into_bytes()
+}
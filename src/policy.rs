@@ -0,0 +1,377 @@
+use crate::catalog::matches_category;
+use crate::message::fill;
+use crate::{destination_num_to_str, product_num_to_str, ConfigData, LendData, LendType};
+use std::collections::BTreeMap;
+
+// 団体ごとの貸出の制限
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GroupLimit {
+  // 同時に借りられる品の数
+  pub max_items: Option<usize>,
+  // 種類（またはタグ）ごとの、同時に借りられる品の数
+  pub max_per_category: BTreeMap<String, usize>,
+}
+
+// 設定ファイルの"policy"から読み取った貸出の決まり
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Policy {
+  // 全ての団体に適用する制限
+  pub default_limit: GroupLimit,
+  // 団体ごとに個別に与えた制限（与えた項目だけdefault_limitを上書きする）
+  pub group_limit_map: BTreeMap<String, GroupLimit>,
+  // 決められた団体だけが借りられる種類（またはタグ）と、その団体のリスト
+  pub restricted_map: BTreeMap<String, Vec<String>>,
+}
+
+// 制限に引っかかった内容
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyViolation {
+  // 同時に借りられる品の数を超える
+  TooManyItems {
+    holder: String,
+    limit: usize,
+    count: usize,
+  },
+  // 種類ごとの同時に借りられる品の数を超える
+  TooManyInCategory {
+    holder: String,
+    category: String,
+    limit: usize,
+    count: usize,
+  },
+  // その団体は借りられない種類の品
+  NotAllowed {
+    holder: String,
+    item: String,
+    category: String,
+  },
+}
+
+fn json_to_group_limit(json: &serde_json::Value) -> GroupLimit {
+  let max_per_category = match json["max_per_category"].as_object() {
+    None => BTreeMap::new(),
+    Some(map) => map
+      .iter()
+      .filter_map(|(category, limit)| Some((category.clone(), limit.as_u64()? as usize)))
+      .collect(),
+  };
+  GroupLimit {
+    max_items: json["max_items"].as_u64().map(|n| n as usize),
+    max_per_category,
+  }
+}
+
+// 設定ファイルの"policy"を読み取る
+// 与えられなかった項目は制限しない
+pub fn make_policy(json: &serde_json::Value) -> Policy {
+  let group_limit_map = match json["groups"].as_object() {
+    None => BTreeMap::new(),
+    Some(map) => map
+      .iter()
+      .map(|(destination_num, json)| (destination_num.clone(), json_to_group_limit(json)))
+      .collect(),
+  };
+  let restricted_map = match json["restricted"].as_object() {
+    None => BTreeMap::new(),
+    Some(map) => map
+      .iter()
+      .map(|(category, group_lst)| {
+        let group_lst = group_lst
+          .as_array()
+          .map(|lst| {
+            lst
+              .iter()
+              .filter_map(|v| v.as_str().map(|s| s.to_string()))
+              .collect()
+          })
+          .unwrap_or_default();
+        (category.clone(), group_lst)
+      })
+      .collect(),
+  };
+  Policy {
+    default_limit: json_to_group_limit(json),
+    group_limit_map,
+    restricted_map,
+  }
+}
+
+impl Policy {
+  // 団体に適用する制限
+  // 個別に与えた項目を優先し、無い項目は全ての団体に適用する制限を使う
+  fn group_limit(&self, destination_num: &str) -> GroupLimit {
+    let mut limit = self.default_limit.clone();
+    if let Some(group_limit) = self.group_limit_map.get(destination_num) {
+      if group_limit.max_items.is_some() {
+        limit.max_items = group_limit.max_items
+      }
+      for (category, max) in group_limit.max_per_category.iter() {
+        limit.max_per_category.insert(category.clone(), *max);
+      }
+    }
+    limit
+  }
+
+  // 品を新しく貸し出したときに制限を超えないかを調べる
+  // 既に借りている品と、新しく借りる品を合わせて数える
+  pub fn check_lend(
+    &self,
+    now_lend_data_lst: &[LendData],
+    config_data: &ConfigData,
+    product_num_lst: &[String],
+    destination_num: &str,
+  ) -> Vec<PolicyViolation> {
+    let mut violation_lst = Vec::new();
+    for product_num in product_num_lst.iter() {
+      for (category, group_lst) in self.restricted_map.iter() {
+        if matches_category(product_num, config_data, category)
+          && !group_lst.iter().any(|group| group == destination_num)
+        {
+          violation_lst.push(PolicyViolation::NotAllowed {
+            holder: destination_num.to_string(),
+            item: product_num.clone(),
+            category: category.clone(),
+          })
+        }
+      }
+    }
+    let held_lst: Vec<&String> = now_lend_data_lst
+      .iter()
+      .filter_map(|lend_data| match &lend_data.lend_type {
+        LendType::Lend(product_num, holder) if holder == destination_num => Some(product_num),
+        _ => None,
+      })
+      .chain(product_num_lst.iter())
+      .collect();
+    let limit = self.group_limit(destination_num);
+    if let Some(max_items) = limit.max_items {
+      if held_lst.len() > max_items {
+        violation_lst.push(PolicyViolation::TooManyItems {
+          holder: destination_num.to_string(),
+          limit: max_items,
+          count: held_lst.len(),
+        })
+      }
+    }
+    for (category, max) in limit.max_per_category.iter() {
+      // 新しく借りる品に関係の無い種類は、既に超えていても今回は問題にしない
+      if !product_num_lst
+        .iter()
+        .any(|product_num| matches_category(product_num, config_data, category))
+      {
+        continue;
+      }
+      let count = held_lst
+        .iter()
+        .filter(|product_num| matches_category(product_num, config_data, category))
+        .count();
+      if count > *max {
+        violation_lst.push(PolicyViolation::TooManyInCategory {
+          holder: destination_num.to_string(),
+          category: category.clone(),
+          limit: *max,
+          count,
+        })
+      }
+    }
+    violation_lst
+  }
+}
+
+// 制限に引っかかった内容を、品名や団体名を付けた文字列にする
+pub fn violation_to_message(violation: &PolicyViolation, config_data: &ConfigData) -> String {
+  match violation {
+    PolicyViolation::TooManyItems {
+      holder,
+      limit,
+      count,
+    } => fill(
+      "policy.too_many_items",
+      &[
+        &destination_num_to_str(holder, Some(config_data)),
+        limit,
+        count,
+      ],
+    ),
+    PolicyViolation::TooManyInCategory {
+      holder,
+      category,
+      limit,
+      count,
+    } => fill(
+      "policy.too_many_in_category",
+      &[
+        &destination_num_to_str(holder, Some(config_data)),
+        category,
+        limit,
+        count,
+      ],
+    ),
+    PolicyViolation::NotAllowed {
+      holder,
+      item,
+      category,
+    } => fill(
+      "policy.not_allowed",
+      &[
+        &destination_num_to_str(holder, Some(config_data)),
+        &product_num_to_str(item, Some(config_data)),
+        category,
+      ],
+    ),
+  }
+}
+
+#[test]
+fn check_policy() {
+  let config_data = crate::make_config_data(
+    serde_json::json!({
+      "0001": "内リール1",
+      "0002": "内リール2",
+      "0003": "内リール3",
+      "0301": {"name": "スモークマシン1", "tags": ["舞台"]}
+    }),
+    serde_json::json!({}),
+    serde_json::json!({}),
+  );
+  let policy = make_policy(&serde_json::json!({
+    "max_items": 3,
+    "max_per_category": {"内リール": 2},
+    "groups": {"12": {"max_per_category": {"内リール": 3}}},
+    "restricted": {"舞台": ["12"]}
+  }));
+  let items = |lst: &[&str]| lst.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+  let now_lend_data_lst = vec![crate::make_test_lend_data(
+    1,
+    "2020-11-23T09:00:00+09:00",
+    LendType::Lend("0001".to_string(), "13".to_string()),
+  )];
+  assert_eq!(
+    policy.check_lend(&now_lend_data_lst, &config_data, &items(&["0002"]), "13"),
+    Vec::new()
+  );
+  assert_eq!(
+    policy.check_lend(
+      &now_lend_data_lst,
+      &config_data,
+      &items(&["0002", "0003"]),
+      "13"
+    ),
+    vec![PolicyViolation::TooManyInCategory {
+      holder: "13".to_string(),
+      category: "内リール".to_string(),
+      limit: 2,
+      count: 3
+    }]
+  );
+  // 団体12は内リールを3つまで借りられ、舞台の品も借りられる
+  assert_eq!(
+    policy.check_lend(
+      &now_lend_data_lst,
+      &config_data,
+      &items(&["0001", "0002", "0003"]),
+      "12"
+    ),
+    Vec::new()
+  );
+  assert_eq!(
+    policy.check_lend(
+      &now_lend_data_lst,
+      &config_data,
+      &items(&["0002", "0003", "0301"]),
+      "13"
+    ),
+    vec![
+      PolicyViolation::NotAllowed {
+        holder: "13".to_string(),
+        item: "0301".to_string(),
+        category: "舞台".to_string()
+      },
+      PolicyViolation::TooManyItems {
+        holder: "13".to_string(),
+        limit: 3,
+        count: 4
+      },
+      PolicyViolation::TooManyInCategory {
+        holder: "13".to_string(),
+        category: "内リール".to_string(),
+        limit: 2,
+        count: 3
+      }
+    ]
+  );
+}
+
+#[test]
+fn check_policy_group_override() {
+  let config_data = crate::make_config_data(
+    serde_json::json!({
+      "0001": "内リール1",
+      "0002": "内リール2",
+      "0003": "内リール3",
+      "0301": {"name": "スモークマシン1", "tags": ["舞台"]},
+      "0302": {"name": "照明1", "category": "照明", "tags": ["舞台"]}
+    }),
+    serde_json::json!({}),
+    serde_json::json!({}),
+  );
+  let policy = make_policy(&serde_json::json!({
+    "max_items": 2,
+    "max_per_category": {"内リール": 2},
+    "groups": {"12": {"max_items": 4}, "13": {"max_items": 1}},
+    "restricted": {"舞台": ["12"]}
+  }));
+  let items = |lst: &[&str]| lst.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+  let now_lend_data_lst = vec![crate::make_test_lend_data(
+    1,
+    "2020-11-23T09:00:00+09:00",
+    LendType::Lend("0001".to_string(), "12".to_string()),
+  )];
+  // 団体12はmax_itemsだけを上書きしているので、種類ごとの制限は全体のものがそのまま残る
+  assert_eq!(
+    policy.check_lend(
+      &now_lend_data_lst,
+      &config_data,
+      &items(&["0002", "0301"]),
+      "12"
+    ),
+    Vec::new()
+  );
+  assert_eq!(
+    policy.check_lend(
+      &now_lend_data_lst,
+      &config_data,
+      &items(&["0002", "0003"]),
+      "12"
+    ),
+    vec![PolicyViolation::TooManyInCategory {
+      holder: "12".to_string(),
+      category: "内リール".to_string(),
+      limit: 2,
+      count: 3
+    }]
+  );
+  // 団体13は全体より少ない数しか借りられない
+  assert_eq!(
+    policy.check_lend(
+      &now_lend_data_lst,
+      &config_data,
+      &items(&["0002", "0003"]),
+      "13"
+    ),
+    vec![PolicyViolation::TooManyItems {
+      holder: "13".to_string(),
+      limit: 1,
+      count: 2
+    }]
+  );
+  // 種類が違っても、タグで制限された品は決められた団体しか借りられない
+  assert_eq!(
+    policy.check_lend(&now_lend_data_lst, &config_data, &items(&["0302"]), "14"),
+    vec![PolicyViolation::NotAllowed {
+      holder: "14".to_string(),
+      item: "0302".to_string(),
+      category: "舞台".to_string()
+    }]
+  );
+}
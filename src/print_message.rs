@@ -1,128 +1,467 @@
+use dlm::catalog::CategorySummary;
+use dlm::lend_type_to_message_with_config_data;
+use dlm::message::{fill, text};
+use dlm::policy::{violation_to_message, PolicyViolation};
+use dlm::receipt::ReceiptError;
+use dlm::stocktake::StocktakeCode;
+use dlm::{check_error_to_message_with_config_data, destination_num_to_str};
+use dlm::{lend_data_to_message_with_config_data, product_num_to_str};
+use dlm::{ConfigData, HistoryEntry, LedgerError, LendData, LendType};
+
 pub fn print_start() {
-  println!(
-    "
-このソフトウェアは開成学園文化祭準備員会電気係で使用した、貸出品の管理ソフトウェアです。
-作成は2021年1月に創立149周年記念開成祭を開催した準備員会電気係サブチーフが行いました。
-使用についての保証は一切ありません。
-リポジトリ：https://github.com/puripuri2100/dlm
-  "
-  )
+  println!("\n{}\n  ", text("start"))
 }
 
 pub fn print_restart() {
-  println!("\n操作を行ってください\n操作方法がわからない場合は help と入力してください\n>")
+  println!("{}", text("restart"))
+}
+
+pub fn print_operator(operator: &str) {
+  println!("{}", fill("operator_banner", &[&operator]))
+}
+
+pub fn print_backfill_mode(time: &str) {
+  println!("{}", fill("backfill_banner", &[&time]))
 }
 
 pub fn print_help() {
+  println!("\n{}", text("help"))
+}
+
+pub fn print_not_found_command_name(name: String) {
+  eprintln!("{}", fill("not_found_command_name", &[&name]))
+}
+
+pub fn print_missing_argument(msg: String) {
+  eprintln!("{}", fill("missing_argument", &[&msg]));
+}
+
+// 直近range件の入力を、'!<n>'で使う番号と一緒に表示する
+// failed_onlyの場合はエラーになった入力の中から直近range件を表示する
+pub fn print_history(history_lst: &[HistoryEntry], range: usize, failed_only: bool) {
+  let lst: Vec<(usize, &HistoryEntry)> = history_lst
+    .iter()
+    .enumerate()
+    .filter(|(_, entry)| !failed_only || entry.failed)
+    .collect();
+  for (i, entry) in lst.iter().skip(lst.len().saturating_sub(range)) {
+    println!("{}: {}", i + 1, entry.command)
+  }
+}
+
+pub fn print_history_not_found(msg: &str) {
+  eprintln!("!  {}", msg)
+}
+
+pub fn print_lend_success(product_num: &str, destination_num: &str, lend_num: &isize) {
+  println!(
+    "{}",
+    fill("lend_success", &[&product_num, &destination_num, lend_num])
+  );
+}
+
+pub fn print_return_success(product_num: &str, destination_num: &str, lend_num: &isize) {
+  println!(
+    "{}",
+    fill(
+      "return_success",
+      &[&product_num, &destination_num, lend_num]
+    )
+  );
+}
+
+pub fn print_needs_repair_notice() {
+  println!("{}", text("return.needs_repair_notice"))
+}
+
+pub fn print_repair_success(product_num: &str, lend_num: &isize) {
+  println!("{}", fill("repair_success", &[&product_num, lend_num]));
+}
+
+// 紛失・使用終了を記録したことを、そのときの貸出先と共に表示する
+pub fn print_out_of_circulation_success(lend_data: &LendData, config_data: &ConfigData) {
+  println!(
+    "{}",
+    fill(
+      "out_of_circulation_success",
+      &[
+        &lend_data.num,
+        &lend_type_to_message_with_config_data(&lend_data.lend_type, config_data)
+      ]
+    )
+  );
+}
+
+pub fn print_backfill_success(time: &str) {
+  println!("{}", fill("backfill_success", &[&time]));
+}
+
+pub fn print_edit_success(
+  num: &isize,
+  new_product_num: Option<&str>,
+  new_destination_num: Option<&str>,
+  new_time: Option<&str>,
+  lend_num: &isize,
+) {
+  let mut v = Vec::new();
+  if let Some(new_product_num) = new_product_num {
+    v.push(fill("edit_success.product", &[&new_product_num]))
+  }
+  if let Some(new_destination_num) = new_destination_num {
+    v.push(fill("edit_success.destination", &[&new_destination_num]))
+  }
+  if let Some(new_time) = new_time {
+    v.push(fill("edit_success.time", &[&new_time]))
+  }
   println!(
-    "
-このソフトウェア上で使うことのできるコマンドとその役割は以下の通りです。
+    "{}",
+    fill(
+      "edit_success",
+      &[num, &v.join(text("list_separator")), lend_num]
+    )
+  );
+}
+
+pub fn print_old_format(version: usize, current_version: usize) {
+  println!("{}", fill("old_format", &[&version, &current_version]))
+}
+
+pub fn print_read_only() {
+  eprintln!("{}", text("read_only"));
+}
+
+pub fn print_invalid_row(line: u64, reason: &str) {
+  eprintln!("{}", fill("invalid_row", &[&line, &reason]));
+}
 
-  help      : 入力できる内容と役割（これです）を表示します
+pub fn print_invalid_row_write() {
+  eprintln!("{}", text("invalid_row_write"));
+}
 
-  exit      : 終了します
+pub fn print_data_file_locked() {
+  eprintln!("{}", text("data_file_locked"));
+}
 
-  lend      : 'lend <貸出品の番号1> <貸出品の番号2> .. <貸出品の番号n> <貸出先の番号>' で貸出を登録します
+pub fn print_data_file_write_failed(path: &str, err: &std::io::Error) {
+  eprintln!("{}", fill("data_file_write_failed", &[&path, err]))
+}
 
-  l         : 'lend' の省略形です
-              使い方は'lend'と変わりません
+pub fn print_data_file_changed() {
+  println!("{}", text("data_file_changed"));
+}
 
-  return    : 'return <返却品の番号1> <返却品の番号2> .. <返却品の番号n> <返却元の番号>' で返却を登録します
+pub fn print_target_changed(num: &isize) {
+  eprintln!("{}", fill("target_changed", &[num]));
+}
 
-  r         : 'return' の省略形です
-              使い方は'return'と変わりません
+pub fn print_migrate_success(version: usize, current_version: usize, backup_path: &str) {
+  println!(
+    "{}",
+    fill(
+      "migrate_success",
+      &[&version, &current_version, &backup_path]
+    )
+  );
+}
 
-  edit      : 'edit <編集対象に付けられた通し番号> <編集後の品名の番号> <編集後の貸出先の番号>'
-              で以前に行った操作を改変できます
-              'remove'と'edit'で行った操作を編集することは出来ません
+pub fn print_backup_success(id: &str, path: &str) {
+  println!("{}", fill("backup_success", &[&id, &path]));
+}
 
-  remove    : 'remove <編集対象に付けられた通し番号>'
-              で以前に行った操作を無かったことにできます
+pub fn print_remove_success(num: &isize, lend_num: &isize) {
+  println!("{}", fill("remove_success", &[num, lend_num]));
+}
 
-  show      : 現在貸し出されているものと貸出先を表示します
-              品名と貸出先の番号は実行時に与えたJSONファイルに基づいて変換されます
-              'show <品名の検索> <貸出先の検索>'で条件にあうもののみ表示することができます
-              検索の条件指定は正規表現で行います
-              例えば、'show 0\\d{{3}} \\d' で貸し出しているリールが分かります
-              '\\d'で「任意の数字列」、'\\d{{<数字>}}'で「指定した桁数の数字列」です
-              '^0'で「0から始まる文字列」、'0$'で「0で終わる文字列」です
-              組み合わせて'^0$'のようにすると、「0」を表すことができます
+// ライブラリから返ってきたエラーを、品名や団体名を付けて表示する
+pub fn print_ledger_error(err: &LedgerError, config_data: &ConfigData) {
+  let item_str = |item: &str| product_num_to_str(item, Some(config_data));
+  let destination_str = |num: &str| destination_num_to_str(num, Some(config_data));
+  let s = match err {
+    LedgerError::FutureTime(_) => text("error.future_time").to_string(),
+    LedgerError::AlreadyLent { item, holder } => fill(
+      "error.already_lent",
+      &[&item_str(item), &destination_str(holder)],
+    ),
+    LedgerError::NotLent { item } => fill("error.not_lent", &[&item_str(item)]),
+    LedgerError::WrongReturner {
+      item,
+      expected,
+      actual,
+    } => fill(
+      "error.wrong_returner",
+      &[
+        &item_str(item),
+        &destination_str(expected),
+        &destination_str(actual),
+      ],
+    ),
+    LedgerError::ConflictsWithLater { item, next } => fill(
+      "error.conflicts_with_later",
+      &[
+        &item_str(item),
+        &lend_data_to_message_with_config_data(next, config_data),
+      ],
+    ),
+    LedgerError::UnknownOperation(num) => fill("error.unknown_operation", &[num]),
+    LedgerError::FutureOperation(num) => fill("error.future_operation", &[num]),
+    LedgerError::CannotEditEdit(num) => fill("error.cannot_edit_edit", &[num]),
+    LedgerError::HasDependent { dependent, .. } => fill(
+      "error.has_dependent",
+      &[&lend_data_to_message_with_config_data(
+        dependent,
+        config_data,
+      )],
+    ),
+    LedgerError::NeedsRepair { item, returned } => fill(
+      "error.needs_repair",
+      &[
+        &item_str(item),
+        &lend_data_to_message_with_config_data(returned, config_data),
+      ],
+    ),
+    LedgerError::NotDamaged { item } => fill("error.not_damaged", &[&item_str(item)]),
+    LedgerError::OutOfCirculation { item, closed } => fill(
+      "error.out_of_circulation",
+      &[
+        &item_str(item),
+        &lend_data_to_message_with_config_data(closed, config_data),
+      ],
+    ),
+  };
+  eprintln!("!  {}", s)
+}
 
-  all       : 全ての操作を表示します
+pub fn print_lend_aborted() {
+  eprintln!("{}\n", text("lend_aborted"))
+}
 
-  check     : 貸出と返却が食い違っているものが無いかをチェックします
+// 団体ごとの貸出の制限に引っかかった内容を表示する
+pub fn print_policy_violation(violation: &PolicyViolation, config_data: &ConfigData) {
+  eprintln!("!  {}", violation_to_message(violation, config_data))
+}
 
-  history   : 'history' 単体では直近10件の入力を表示します
-              'history <n>' と、数字を与えるとその分だけ直近の入力を表示します
-"
-  )
+pub fn print_policy_override_hint() {
+  println!("{}", text("policy.override_hint"))
 }
 
-pub fn print_not_found_command_name(name: String) {
+pub fn print_policy_overridden() {
+  println!("{}", text("policy.overridden"))
+}
+
+// 'check'で見つかった間違いを表示する
+pub fn print_check_error(err: &LedgerError, config_data: &ConfigData) {
   eprintln!(
-    "
-  {}というコマンド名は見つかりませんでした。
-  使うことのできるコマンド名は help を見てください。
-  ",
-    name
+    "- {}\n",
+    check_error_to_message_with_config_data(err, config_data)
   )
 }
 
-pub fn print_missing_argument(msg: String) {
-  print!(
-    "
-  引数を間違えています。
-  {}
-  helpを入力して使い方を確認してください
-",
-    msg
-  );
+// 'check'で、修理を待っている品を返却した団体と状態と共に表示する
+pub fn print_damaged_item(lend_data: &LendData, config_data: &ConfigData) {
+  if let LendType::Return(product_num, destination_num) = &lend_data.lend_type {
+    let condition_str = match &lend_data.condition {
+      None => String::new(),
+      Some(condition) => match &condition.note {
+        None => condition.kind.to_message().to_string(),
+        Some(note) => format!("{} / {}", condition.kind.to_message(), note),
+      },
+    };
+    println!(
+      "- {}\n",
+      fill(
+        "check.damaged_item",
+        &[
+          &product_num_to_str(product_num, Some(config_data)),
+          &destination_num_to_str(destination_num, Some(config_data)),
+          &condition_str,
+          &lend_data.num,
+        ]
+      )
+    )
+  }
+}
+
+// 'check'で、紛失・使用終了した品をその操作と共に表示する
+pub fn print_retired_item(lend_data: &LendData, config_data: &ConfigData) {
+  println!(
+    "- {}\n",
+    lend_data_to_message_with_config_data(lend_data, config_data)
+  )
+}
+
+pub fn print_stocktake_start() {
+  println!("{}", text("stocktake.start"))
+}
+
+pub fn print_stocktake_duplicate(code: &str) {
+  println!("{}", fill("stocktake.duplicate", &[&code]))
 }
 
-pub fn print_history(command_history: &[String], range: usize) {
-  let vec_len = command_history.len();
-  if vec_len <= range {
-    for (i, item) in command_history.iter().enumerate().take(vec_len) {
-      println!("{}: {}", i + 1, item)
+// 棚卸しで読み取った番号を、貸出中などの場合は警告と共に表示する
+pub fn print_stocktake_code(code: &StocktakeCode, config_data: &ConfigData) {
+  match code {
+    StocktakeCode::Present(product_num) => println!(
+      "{}",
+      fill(
+        "stocktake.present",
+        &[&product_num_to_str(product_num, Some(config_data))]
+      )
+    ),
+    StocktakeCode::Lent(product_num, holder) => {
+      println!(
+        "!  {}",
+        fill(
+          "stocktake.lent",
+          &[
+            &product_num_to_str(product_num, Some(config_data)),
+            &destination_num_to_str(holder, Some(config_data))
+          ]
+        )
+      );
+      print_beep()
     }
-  } else {
-    for (i, item) in command_history
-      .iter()
-      .enumerate()
-      .take(vec_len)
-      .skip(vec_len - range)
-    {
-      println!("{}: {}", i, item)
+    StocktakeCode::Retired(product_num) => {
+      println!(
+        "!  {}",
+        fill(
+          "stocktake.retired",
+          &[&product_num_to_str(product_num, Some(config_data))]
+        )
+      );
+      print_beep()
+    }
+    StocktakeCode::Unknown(code) => {
+      println!("!  {}", fill("scan.unknown", &[code]));
+      print_beep()
     }
   }
 }
 
-pub fn print_lend_success(product_num: &str, destination_num: &str, lend_num: &isize) {
+pub fn print_stocktake_success(path: &str) {
+  println!("{}", fill("stocktake.saved", &[&path]))
+}
+
+pub fn print_stocktake_write_failed(path: &str) {
+  eprintln!("!  {}", fill("stocktake.write_failed", &[&path]))
+}
+
+pub fn print_undo_limit(n: usize) {
+  eprintln!("!  {}", fill("undo_limit", &[&n]));
+}
+
+pub fn print_redo_limit(n: usize) {
+  eprintln!("!  {}", fill("redo_limit", &[&n]));
+}
+
+pub fn print_backup_failed() {
+  eprintln!("!  {}", text("backup.failed"))
+}
+
+pub fn print_backup_not_found(id: &str) {
+  eprintln!("!  {}", fill("backup.not_found", &[&id]))
+}
+
+pub fn print_restore_failed() {
+  eprintln!("!  {}", text("backup.restore_failed"))
+}
+
+pub fn print_scan_start_lend() {
+  println!("{}", text("scan.start_lend"))
+}
+
+pub fn print_scan_start_return() {
+  println!("{}", text("scan.start_return"))
+}
+
+pub fn print_scan_destination(destination_num: &str, is_return: bool, config_data: &ConfigData) {
+  let key = if is_return {
+    "scan.destination_return"
+  } else {
+    "scan.destination_lend"
+  };
   println!(
-    "({}): {}を{}に貸し出しました",
-    lend_num, product_num, destination_num
-  );
+    "{}",
+    fill(
+      key,
+      &[&destination_num_to_str(destination_num, Some(config_data))]
+    )
+  )
 }
 
-pub fn print_return_success(product_num: &str, destination_num: &str, lend_num: &isize) {
+pub fn print_scan_need_destination() {
+  eprintln!("!  {}", text("scan.need_destination"))
+}
+
+pub fn print_scan_unknown(code: &str) {
+  eprintln!("!  {}", fill("scan.unknown", &[&code]))
+}
+
+// スキャンを受け付けなかったことを音で知らせる
+pub fn print_beep() {
+  eprint!("\x07")
+}
+
+pub fn print_scan_end(ok_count: usize, err_count: usize) {
+  println!("{}", fill("scan.end", &[&ok_count, &err_count]))
+}
+
+pub fn print_labels_success(label_count: usize, file_name_lst: &[String]) {
   println!(
-    "({}): {}が{}から返却されました",
-    lend_num, product_num, destination_num
-  );
+    "{}",
+    fill("labels.success", &[&label_count, &file_name_lst.join(", ")])
+  )
 }
 
-pub fn print_edit_success(
-  num: &isize,
-  new_product_num: &str,
-  new_destination_num: &str,
-  lend_num: &isize,
-) {
+pub fn print_labels_empty() {
+  eprintln!("!  {}", text("labels.empty"))
+}
+
+pub fn print_labels_unencodable(code: &str) {
+  eprintln!("!  {}", fill("labels.unencodable", &[&code]))
+}
+
+pub fn print_labels_write_failed(file_name: &str) {
+  eprintln!("!  {}", fill("labels.write_failed", &[&file_name]))
+}
+
+pub fn print_receipt_success(path: &str) {
+  println!("{}", fill("receipt.success", &[&path]))
+}
+
+pub fn print_receipt_error(err: &ReceiptError) {
+  let s = match err {
+    ReceiptError::NotFound(num) => fill("receipt.not_found", &[num]),
+    ReceiptError::Mixed => text("receipt.mixed").to_string(),
+  };
+  eprintln!("!  {}", s)
+}
+
+pub fn print_receipt_write_failed(path: &str) {
+  eprintln!("!  {}", fill("receipt.write_failed", &[&path]))
+}
+
+pub fn print_report_success(file_name: &str) {
+  println!("{}", fill("report.success", &[&file_name]))
+}
+
+pub fn print_report_write_failed(file_name: &str) {
+  eprintln!("!  {}", fill("report.write_failed", &[&file_name]))
+}
+
+pub fn print_category_summary(summary: &CategorySummary) {
   println!(
-    "({}): 操作番号{}の品名を「{}」に、貸出先を「{}」に書き換えました",
-    lend_num, num, new_product_num, new_destination_num
-  );
+    "{}",
+    fill(
+      "show.category_summary",
+      &[&summary.category, &summary.total, &summary.out]
+    )
+  )
 }
 
-pub fn print_remove_success(num: &isize, lend_num: &isize) {
-  println!("({}): 操作番号{}を削除しました", lend_num, num);
+pub fn print_stats_success(file_name: &str) {
+  println!("{}", fill("stats.success", &[&file_name]))
+}
+
+pub fn print_stats_write_failed(file_name: &str) {
+  eprintln!("!  {}", fill("stats.write_failed", &[&file_name]))
 }
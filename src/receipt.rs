@@ -0,0 +1,383 @@
+use crate::label::escape_xml;
+use crate::message::{fill, text};
+use crate::pdf::{write_pdf, Shape};
+use crate::{destination_num_to_str, organize_lend_data, product_num_to_str};
+use crate::{ConfigData, LendData, LendType};
+use chrono::{DateTime, FixedOffset};
+use std::path::PathBuf;
+
+// 貸出票・返却票の形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptFormat {
+  Text,
+  Html,
+  Pdf,
+}
+
+impl ReceiptFormat {
+  pub fn from_name(name: &str) -> Option<ReceiptFormat> {
+    match name.to_ascii_lowercase().as_str() {
+      "text" | "txt" => Some(ReceiptFormat::Text),
+      "html" => Some(ReceiptFormat::Html),
+      "pdf" => Some(ReceiptFormat::Pdf),
+      _ => None,
+    }
+  }
+
+  pub fn extension(self) -> &'static str {
+    match self {
+      ReceiptFormat::Text => "txt",
+      ReceiptFormat::Html => "html",
+      ReceiptFormat::Pdf => "pdf",
+    }
+  }
+}
+
+// 貸出票・返却票の設定
+#[derive(Debug, Clone)]
+pub struct ReceiptConfig {
+  // 貸出票を保存するディレクトリ
+  pub dir: PathBuf,
+  pub format: ReceiptFormat,
+  // '--receipt'を付けなくても、貸出と返却のたびに作るかどうか
+  pub auto: bool,
+}
+
+// 設定用のJSONファイルの"receipt"タグから貸出票の設定を作る
+// "receipt" : { "dir" : "<ディレクトリ>", "format" : "text|html|pdf", "auto" : <true|false> }
+// ディレクトリが無い場合は'<CSVファイル名>.receipts'とする
+pub fn make_receipt_config(
+  data_file_name: &str,
+  receipt_json_data: &serde_json::Value,
+) -> ReceiptConfig {
+  let dir = match receipt_json_data["dir"].as_str() {
+    Some(dir) => PathBuf::from(dir),
+    None => PathBuf::from(format!("{}.receipts", data_file_name)),
+  };
+  let format = receipt_json_data["format"]
+    .as_str()
+    .and_then(ReceiptFormat::from_name)
+    .unwrap_or(ReceiptFormat::Text);
+  let auto = receipt_json_data["auto"].as_bool().unwrap_or(false);
+  ReceiptConfig { dir, format, auto }
+}
+
+// 一枚の貸出票・返却票に載せる操作
+#[derive(Debug, Clone, PartialEq)]
+pub struct Receipt {
+  // 返却票かどうか
+  pub is_return: bool,
+  pub destination_num: String,
+  // 編集を反映させた貸出・返却の操作（操作番号順）
+  pub lend_data_lst: Vec<LendData>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReceiptError {
+  // 貸出・返却の操作として見つからない操作番号（削除された操作や、編集・削除の操作も含む）
+  NotFound(isize),
+  // 貸出と返却、または別々の団体の操作が混ざっている
+  Mixed,
+}
+
+// 操作番号のリストから貸出票を作る
+// 編集された操作は編集後の内容で載せる
+pub fn make_receipt(
+  lend_data_lst: &[LendData],
+  num_lst: &[isize],
+) -> Result<Receipt, ReceiptError> {
+  let organized_lst = organize_lend_data(lend_data_lst);
+  let mut receipt_data_lst = Vec::new();
+  for num in num_lst.iter() {
+    match organized_lst.iter().find(|data| data.num == *num) {
+      None => return Err(ReceiptError::NotFound(*num)),
+      Some(data) => receipt_data_lst.push(data.clone()),
+    }
+  }
+  receipt_data_lst.sort_by_key(|data| data.num);
+  receipt_data_lst.dedup_by_key(|data| data.num);
+  let kind = |data: &LendData| match &data.lend_type {
+    LendType::Lend(_, destination_num) => Some((false, destination_num.clone())),
+    LendType::Return(_, destination_num) => Some((true, destination_num.clone())),
+    _ => None,
+  };
+  let (is_return, destination_num) = receipt_data_lst
+    .first()
+    .and_then(kind)
+    .ok_or(ReceiptError::Mixed)?;
+  if receipt_data_lst
+    .iter()
+    .any(|data| kind(data) != Some((is_return, destination_num.clone())))
+  {
+    return Err(ReceiptError::Mixed);
+  }
+  Ok(Receipt {
+    is_return,
+    destination_num,
+    lend_data_lst: receipt_data_lst,
+  })
+}
+
+// 保存するファイル名
+// 最初の操作番号から作るので、同じ操作を再発行すると上書きされる
+pub fn receipt_file_name(receipt: &Receipt, format: ReceiptFormat) -> String {
+  let num = receipt
+    .lend_data_lst
+    .first()
+    .map(|data| data.num)
+    .unwrap_or(0);
+  let kind = if receipt.is_return { "return" } else { "lend" };
+  format!("{}-{:04}.{}", kind, num, format.extension())
+}
+
+// 貸出票の各部分の文字列
+struct ReceiptText {
+  title: String,
+  // 団体・場所・担当者・発行時刻
+  header_lst: Vec<String>,
+  // 操作番号・時刻・品名
+  row_lst: Vec<(String, String, String)>,
+  sign_lst: Vec<String>,
+}
+
+fn make_receipt_text(
+  receipt: &Receipt,
+  config_data: &ConfigData,
+  issued: DateTime<FixedOffset>,
+) -> ReceiptText {
+  let title = if receipt.is_return {
+    text("receipt.title_return")
+  } else {
+    text("receipt.title_lend")
+  };
+  let mut header_lst = vec![fill(
+    "receipt.group",
+    &[&destination_num_to_str(
+      &receipt.destination_num,
+      Some(config_data),
+    )],
+  )];
+  if let Some(room) = config_data.room[&receipt.destination_num].as_str() {
+    header_lst.push(fill("receipt.room", &[&room]))
+  }
+  let mut operator_lst: Vec<&str> = receipt
+    .lend_data_lst
+    .iter()
+    .filter_map(|data| data.operator.as_deref())
+    .collect();
+  operator_lst.dedup();
+  if !operator_lst.is_empty() {
+    header_lst.push(fill("receipt.operator", &[&operator_lst.join(", ")]))
+  }
+  header_lst.push(fill("receipt.issued", &[&issued.format("%Y/%m/%d %H:%M")]));
+  let row_lst = receipt
+    .lend_data_lst
+    .iter()
+    .filter_map(|data| match &data.lend_type {
+      LendType::Lend(product_num, _) | LendType::Return(product_num, _) => Some((
+        format!("({})", data.num),
+        data.time.format("%Y/%m/%d %H:%M").to_string(),
+        product_num_to_str(product_num, Some(config_data)),
+      )),
+      _ => None,
+    })
+    .collect();
+  let sign_lst = vec![
+    if receipt.is_return {
+      text("receipt.sign_return").to_string()
+    } else {
+      text("receipt.sign_lend").to_string()
+    },
+    text("receipt.sign_operator").to_string(),
+  ];
+  ReceiptText {
+    title: title.to_string(),
+    header_lst,
+    row_lst,
+    sign_lst,
+  }
+}
+
+// 署名を書く欄の下線
+const SIGN_LINE: &str = "______________________________";
+
+pub fn render_text(
+  receipt: &Receipt,
+  config_data: &ConfigData,
+  issued: DateTime<FixedOffset>,
+) -> String {
+  let receipt_text = make_receipt_text(receipt, config_data, issued);
+  let mut s = format!("==== {} ====\n", receipt_text.title);
+  for header in receipt_text.header_lst.iter() {
+    s.push_str(&format!("{}\n", header));
+  }
+  s.push('\n');
+  for (num, time, product) in receipt_text.row_lst.iter() {
+    s.push_str(&format!("  {:<6} {}  {}\n", num, time, product));
+  }
+  s.push_str(&format!(
+    "\n{}\n",
+    fill("receipt.count", &[&receipt_text.row_lst.len()])
+  ));
+  for sign in receipt_text.sign_lst.iter() {
+    s.push_str(&format!("\n{}：{}\n", sign, SIGN_LINE));
+  }
+  s
+}
+
+pub fn render_html(
+  receipt: &Receipt,
+  config_data: &ConfigData,
+  issued: DateTime<FixedOffset>,
+) -> String {
+  let receipt_text = make_receipt_text(receipt, config_data, issued);
+  let mut s = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+  s.push_str(&format!(
+    "<title>{}</title>\n",
+    escape_xml(&receipt_text.title)
+  ));
+  s.push_str("<style>table { border-collapse: collapse; } th, td { border: 1px solid #000; padding: 4px 8px; } .sign { margin-top: 2em; }</style>\n");
+  s.push_str("</head>\n<body>\n");
+  s.push_str(&format!("<h1>{}</h1>\n", escape_xml(&receipt_text.title)));
+  for header in receipt_text.header_lst.iter() {
+    s.push_str(&format!("<p>{}</p>\n", escape_xml(header)));
+  }
+  s.push_str(&format!(
+    "<table>\n<tr><th>{}</th><th>{}</th><th>{}</th></tr>\n",
+    escape_xml(text("receipt.column_num")),
+    escape_xml(text("receipt.column_time")),
+    escape_xml(text("receipt.column_item"))
+  ));
+  for (num, time, product) in receipt_text.row_lst.iter() {
+    s.push_str(&format!(
+      "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+      escape_xml(num),
+      escape_xml(time),
+      escape_xml(product)
+    ));
+  }
+  s.push_str("</table>\n");
+  s.push_str(&format!(
+    "<p>{}</p>\n",
+    escape_xml(&fill("receipt.count", &[&receipt_text.row_lst.len()]))
+  ));
+  for sign in receipt_text.sign_lst.iter() {
+    s.push_str(&format!(
+      "<p class=\"sign\">{}：{}</p>\n",
+      escape_xml(sign),
+      SIGN_LINE
+    ));
+  }
+  s.push_str("</body>\n</html>\n");
+  s
+}
+
+// A4用紙に上から一行ずつ書く（単位はmm）
+const PAPER_WIDTH: f64 = 210.0;
+const PAPER_HEIGHT: f64 = 297.0;
+const MARGIN: f64 = 20.0;
+const LINE_HEIGHT: f64 = 7.0;
+const TEXT_SIZE: f64 = 4.0;
+
+pub fn render_pdf(
+  receipt: &Receipt,
+  config_data: &ConfigData,
+  issued: DateTime<FixedOffset>,
+) -> Vec<u8> {
+  let receipt_text = make_receipt_text(receipt, config_data, issued);
+  let mut page_lst = Vec::new();
+  let mut page = vec![Shape::Text(
+    PAPER_WIDTH / 2.0,
+    MARGIN + TEXT_SIZE * 1.5,
+    TEXT_SIZE * 1.5,
+    receipt_text.title.clone(),
+  )];
+  let mut y = MARGIN + TEXT_SIZE * 1.5 + LINE_HEIGHT * 1.5;
+  for header in receipt_text.header_lst.iter() {
+    page.push(Shape::LeftText(MARGIN, y, TEXT_SIZE, header.clone()));
+    y += LINE_HEIGHT;
+  }
+  y += LINE_HEIGHT / 2.0;
+  // 操作の数が多い場合は次のページに続ける
+  for (num, time, product) in receipt_text.row_lst.iter() {
+    if y > PAPER_HEIGHT - MARGIN - LINE_HEIGHT * 5.0 {
+      page_lst.push(page);
+      page = Vec::new();
+      y = MARGIN + TEXT_SIZE;
+    }
+    page.push(Shape::LeftText(MARGIN, y, TEXT_SIZE, num.clone()));
+    page.push(Shape::LeftText(MARGIN + 15.0, y, TEXT_SIZE, time.clone()));
+    page.push(Shape::LeftText(
+      MARGIN + 55.0,
+      y,
+      TEXT_SIZE,
+      product.clone(),
+    ));
+    y += LINE_HEIGHT;
+  }
+  page.push(Shape::LeftText(
+    MARGIN,
+    y,
+    TEXT_SIZE,
+    fill("receipt.count", &[&receipt_text.row_lst.len()]),
+  ));
+  y += LINE_HEIGHT;
+  for sign in receipt_text.sign_lst.iter() {
+    y += LINE_HEIGHT * 1.5;
+    page.push(Shape::LeftText(MARGIN, y, TEXT_SIZE, format!("{}：", sign)));
+    page.push(Shape::Rect(MARGIN + 50.0, y + 0.5, 80.0, 0.2));
+  }
+  page_lst.push(page);
+  write_pdf(&page_lst, PAPER_WIDTH, PAPER_HEIGHT)
+}
+
+#[test]
+fn check_make_receipt() {
+  let time = chrono::DateTime::parse_from_rfc3339("2020-11-23T17:40:00+09:00").unwrap();
+  let data = |num: isize, lend_type: LendType| LendData {
+    operator: Some("佐藤".to_string()),
+    ..crate::make_test_lend_data(num, "2020-11-23T17:40:00+09:00", lend_type)
+  };
+  let lend_data_lst = vec![
+    data(1, LendType::Lend("0001".to_string(), "12".to_string())),
+    data(2, LendType::Lend("0002".to_string(), "12".to_string())),
+    data(3, LendType::Return("0001".to_string(), "12".to_string())),
+    data(
+      4,
+      LendType::Edit(
+        2,
+        crate::EditData {
+          product_num: Some("0003".to_string()),
+          ..crate::EditData::default()
+        },
+      ),
+    ),
+  ];
+  let receipt = make_receipt(&lend_data_lst, &[2, 1]).unwrap();
+  assert!(!receipt.is_return);
+  // 編集後の品名で載る
+  assert_eq!(
+    receipt.lend_data_lst[1].lend_type,
+    LendType::Lend("0003".to_string(), "12".to_string())
+  );
+  assert_eq!(
+    receipt_file_name(&receipt, ReceiptFormat::Html),
+    "lend-0001.html"
+  );
+  assert_eq!(
+    make_receipt(&lend_data_lst, &[1, 3]),
+    Err(ReceiptError::Mixed)
+  );
+  assert_eq!(
+    make_receipt(&lend_data_lst, &[4]),
+    Err(ReceiptError::NotFound(4))
+  );
+  let config_data = crate::make_config_data(
+    serde_json::json!({"0001": "内リール1"}),
+    serde_json::json!({"12": "物理部"}),
+    serde_json::json!({"12": "1-1"}),
+  );
+  let s = render_text(&receipt, &config_data, time);
+  assert!(s.contains("0001（内リール1）"));
+  assert!(s.contains("12（物理部）"));
+}
@@ -0,0 +1,566 @@
+use crate::label::escape_xml;
+use crate::message::{fill, text};
+use crate::{check_error_to_message_with_config_data, lend_type_to_message_with_config_data};
+use crate::{destination_num_to_str, organize_lend_data, product_num_to_str};
+use crate::{ConfigData, Ledger, LedgerError, LendData, LendType};
+use chrono::{DateTime, FixedOffset};
+use std::collections::{BTreeMap, BTreeSet};
+
+// 報告書の形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+  Markdown,
+  Html,
+  Csv,
+}
+
+impl ReportFormat {
+  // ファイル名の拡張子から形式を決める
+  pub fn from_file_name(file_name: &str) -> Option<ReportFormat> {
+    let extension = std::path::Path::new(file_name)
+      .extension()?
+      .to_str()?
+      .to_ascii_lowercase();
+    match extension.as_str() {
+      "md" | "markdown" => Some(ReportFormat::Markdown),
+      "html" | "htm" => Some(ReportFormat::Html),
+      "csv" => Some(ReportFormat::Csv),
+      _ => None,
+    }
+  }
+}
+
+// 団体ごとの貸出と返却の数
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupTotal {
+  pub destination_num: String,
+  pub lend_count: usize,
+  pub return_count: usize,
+  // まだ返されていない数
+  pub outstanding_count: usize,
+  // 借りている間に紛失した数
+  pub lost_count: usize,
+}
+
+// 'report final'で作る、行事の終わりの突き合わせの結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct FinalReport {
+  // 貸出のあった団体と、設定ファイルにある団体（番号順）
+  pub group_total_lst: Vec<GroupTotal>,
+  // 返されていない貸出（貸し出された時刻順）
+  pub unreturned_lst: Vec<LendData>,
+  // 壊れた状態などで返され、まだ修理されていない品の返却（品名順）
+  pub damaged_lst: Vec<LendData>,
+  // 紛失・使用終了した品の操作（品名順）
+  pub retired_lst: Vec<LendData>,
+  // 設定ファイルに無いのに貸し出された品名と、その貸出の回数
+  pub uncatalogued_lst: Vec<(String, usize)>,
+  // 設定ファイルにあるのに一度も貸し出されなかった品名
+  pub unused_lst: Vec<String>,
+  // 編集と削除の操作（記録された順）
+  pub correction_lst: Vec<LendData>,
+  // 団体ごとの貸出の制限を超えて行った貸出（貸し出された時刻順）
+  pub overridden_lst: Vec<LendData>,
+  // 'check'で見つかった問題
+  pub finding_lst: Vec<LedgerError>,
+}
+
+// 編集と削除を反映させた操作と設定ファイルから報告書の内容を作る
+pub fn make_final_report(ledger: &Ledger, config_data: &ConfigData) -> FinalReport {
+  let organized_lst = organize_lend_data(ledger.lend_data_lst());
+  let unreturned_lst = ledger.outstanding();
+  let damaged_lst = ledger.damaged();
+  let retired_lst = ledger.retired();
+  let mut group_map: BTreeMap<String, GroupTotal> = BTreeMap::new();
+  let new_total = |destination_num: &str| GroupTotal {
+    destination_num: destination_num.to_string(),
+    lend_count: 0,
+    return_count: 0,
+    outstanding_count: 0,
+    lost_count: 0,
+  };
+  if let Some(map) = config_data.sandan.as_object() {
+    for destination_num in map.keys() {
+      group_map.insert(destination_num.clone(), new_total(destination_num));
+    }
+  }
+  let mut lent_map: BTreeMap<String, usize> = BTreeMap::new();
+  for data in organized_lst.iter() {
+    match &data.lend_type {
+      LendType::Lend(product_num, destination_num) => {
+        *lent_map.entry(product_num.clone()).or_insert(0) += 1;
+        group_map
+          .entry(destination_num.clone())
+          .or_insert_with(|| new_total(destination_num))
+          .lend_count += 1
+      }
+      LendType::Return(_, destination_num) => {
+        group_map
+          .entry(destination_num.clone())
+          .or_insert_with(|| new_total(destination_num))
+          .return_count += 1
+      }
+      // 紛失は、そのとき借りていた団体の分として数える
+      LendType::Lost(_, Some(destination_num)) => {
+        group_map
+          .entry(destination_num.clone())
+          .or_insert_with(|| new_total(destination_num))
+          .lost_count += 1
+      }
+      _ => (),
+    }
+  }
+  for data in unreturned_lst.iter() {
+    if let LendType::Lend(_, destination_num) = &data.lend_type {
+      if let Some(total) = group_map.get_mut(destination_num) {
+        total.outstanding_count += 1
+      }
+    }
+  }
+  let catalog: BTreeSet<String> = match config_data.sizai.as_object() {
+    None => BTreeSet::new(),
+    Some(map) => map.keys().cloned().collect(),
+  };
+  let uncatalogued_lst = lent_map
+    .iter()
+    .filter(|(product_num, _)| !catalog.contains(*product_num))
+    .map(|(product_num, count)| (product_num.clone(), *count))
+    .collect();
+  let unused_lst = catalog
+    .iter()
+    .filter(|product_num| !lent_map.contains_key(*product_num))
+    .cloned()
+    .collect();
+  let correction_lst = ledger
+    .lend_data_lst()
+    .iter()
+    .filter(|data| matches!(data.lend_type, LendType::Edit(_, _) | LendType::Remove(_)))
+    .cloned()
+    .collect();
+  let overridden_lst = organized_lst
+    .iter()
+    .filter(|data| data.overridden)
+    .cloned()
+    .collect();
+  let finding_lst = ledger.check().err().unwrap_or_default();
+  FinalReport {
+    group_total_lst: group_map.into_values().collect(),
+    unreturned_lst,
+    damaged_lst,
+    retired_lst,
+    uncatalogued_lst,
+    unused_lst,
+    correction_lst,
+    overridden_lst,
+    finding_lst,
+  }
+}
+
+// 報告書の一つの節
+// どの形式でも同じ表として書き出す
+struct Section {
+  // CSVの1列目に書く名前
+  key: &'static str,
+  title: String,
+  header: Vec<String>,
+  row_lst: Vec<Vec<String>>,
+}
+
+fn time_to_str(time: DateTime<FixedOffset>) -> String {
+  time.format("%Y/%m/%d %H:%M").to_string()
+}
+
+fn make_section_lst(report: &FinalReport, config_data: &ConfigData) -> Vec<Section> {
+  let room = |destination_num: &str| {
+    config_data.room[destination_num]
+      .as_str()
+      .unwrap_or("")
+      .to_string()
+  };
+  let group_total = Section {
+    key: "group",
+    title: text("report.group_title").to_string(),
+    header: vec![
+      text("report.column_group").to_string(),
+      text("report.column_room").to_string(),
+      text("report.column_lend").to_string(),
+      text("report.column_return").to_string(),
+      text("report.column_outstanding").to_string(),
+      text("report.column_lost").to_string(),
+    ],
+    row_lst: report
+      .group_total_lst
+      .iter()
+      .map(|total| {
+        vec![
+          destination_num_to_str(&total.destination_num, Some(config_data)),
+          room(&total.destination_num),
+          total.lend_count.to_string(),
+          total.return_count.to_string(),
+          total.outstanding_count.to_string(),
+          total.lost_count.to_string(),
+        ]
+      })
+      .collect(),
+  };
+  let unreturned = Section {
+    key: "unreturned",
+    title: text("report.unreturned_title").to_string(),
+    header: vec![
+      text("report.column_item").to_string(),
+      text("report.column_holder").to_string(),
+      text("report.column_room").to_string(),
+      text("report.column_time").to_string(),
+      text("report.column_num").to_string(),
+      text("report.column_operator").to_string(),
+    ],
+    row_lst: report
+      .unreturned_lst
+      .iter()
+      .filter_map(|data| match &data.lend_type {
+        LendType::Lend(product_num, destination_num) => Some(vec![
+          product_num_to_str(product_num, Some(config_data)),
+          destination_num_to_str(destination_num, Some(config_data)),
+          room(destination_num),
+          time_to_str(data.time),
+          data.num.to_string(),
+          data.operator.clone().unwrap_or_default(),
+        ]),
+        _ => None,
+      })
+      .collect(),
+  };
+  let damaged = Section {
+    key: "damaged",
+    title: text("report.damaged_title").to_string(),
+    header: vec![
+      text("report.column_item").to_string(),
+      text("report.column_group").to_string(),
+      text("report.column_condition").to_string(),
+      text("report.column_note").to_string(),
+      text("report.column_time").to_string(),
+      text("report.column_num").to_string(),
+    ],
+    row_lst: report
+      .damaged_lst
+      .iter()
+      .filter_map(|data| match (&data.lend_type, &data.condition) {
+        (LendType::Return(product_num, destination_num), Some(condition)) => Some(vec![
+          product_num_to_str(product_num, Some(config_data)),
+          destination_num_to_str(destination_num, Some(config_data)),
+          condition.kind.to_message().to_string(),
+          condition.note.clone().unwrap_or_default(),
+          time_to_str(data.time),
+          data.num.to_string(),
+        ]),
+        _ => None,
+      })
+      .collect(),
+  };
+  let retired = Section {
+    key: "retired",
+    title: text("report.retired_title").to_string(),
+    header: vec![
+      text("report.column_item").to_string(),
+      text("report.column_kind").to_string(),
+      text("report.column_group").to_string(),
+      text("report.column_note").to_string(),
+      text("report.column_time").to_string(),
+      text("report.column_num").to_string(),
+      text("report.column_operator").to_string(),
+    ],
+    row_lst: report
+      .retired_lst
+      .iter()
+      .filter_map(|data| {
+        let (product_num, holder, kind) = match &data.lend_type {
+          LendType::Lost(product_num, holder) => (product_num, holder, text("report.lost")),
+          LendType::Retire(product_num, holder) => (product_num, holder, text("report.retire")),
+          _ => return None,
+        };
+        Some(vec![
+          product_num_to_str(product_num, Some(config_data)),
+          kind.to_string(),
+          holder
+            .as_ref()
+            .map(|holder| destination_num_to_str(holder, Some(config_data)))
+            .unwrap_or_default(),
+          data.note.clone().unwrap_or_default(),
+          time_to_str(data.time),
+          data.num.to_string(),
+          data.operator.clone().unwrap_or_default(),
+        ])
+      })
+      .collect(),
+  };
+  let uncatalogued = Section {
+    key: "uncatalogued",
+    title: text("report.uncatalogued_title").to_string(),
+    header: vec![
+      text("report.column_item").to_string(),
+      text("report.column_lend").to_string(),
+    ],
+    row_lst: report
+      .uncatalogued_lst
+      .iter()
+      .map(|(product_num, count)| vec![product_num.clone(), count.to_string()])
+      .collect(),
+  };
+  let unused = Section {
+    key: "unused",
+    title: text("report.unused_title").to_string(),
+    header: vec![text("report.column_item").to_string()],
+    row_lst: report
+      .unused_lst
+      .iter()
+      .map(|product_num| vec![product_num_to_str(product_num, Some(config_data))])
+      .collect(),
+  };
+  let correction = Section {
+    key: "correction",
+    title: text("report.correction_title").to_string(),
+    header: vec![
+      text("report.column_num").to_string(),
+      text("report.column_time").to_string(),
+      text("report.column_operator").to_string(),
+      text("report.column_detail").to_string(),
+    ],
+    row_lst: report
+      .correction_lst
+      .iter()
+      .map(|data| {
+        vec![
+          data.num.to_string(),
+          time_to_str(data.time),
+          data.operator.clone().unwrap_or_default(),
+          lend_type_to_message_with_config_data(&data.lend_type, config_data),
+        ]
+      })
+      .collect(),
+  };
+  let overridden = Section {
+    key: "overridden",
+    title: text("report.overridden_title").to_string(),
+    header: vec![
+      text("report.column_item").to_string(),
+      text("report.column_group").to_string(),
+      text("report.column_time").to_string(),
+      text("report.column_num").to_string(),
+      text("report.column_operator").to_string(),
+    ],
+    row_lst: report
+      .overridden_lst
+      .iter()
+      .filter_map(|data| match &data.lend_type {
+        LendType::Lend(product_num, destination_num) => Some(vec![
+          product_num_to_str(product_num, Some(config_data)),
+          destination_num_to_str(destination_num, Some(config_data)),
+          time_to_str(data.time),
+          data.num.to_string(),
+          data.operator.clone().unwrap_or_default(),
+        ]),
+        _ => None,
+      })
+      .collect(),
+  };
+  let finding = Section {
+    key: "finding",
+    title: text("report.finding_title").to_string(),
+    header: vec![text("report.column_detail").to_string()],
+    row_lst: report
+      .finding_lst
+      .iter()
+      .map(|err| vec![check_error_to_message_with_config_data(err, config_data)])
+      .collect(),
+  };
+  vec![
+    group_total,
+    unreturned,
+    damaged,
+    retired,
+    uncatalogued,
+    unused,
+    correction,
+    overridden,
+    finding,
+  ]
+}
+
+// 報告書の冒頭に書く、作成時刻と未返却の数のまとめ
+fn summary_str(report: &FinalReport, generated: DateTime<FixedOffset>) -> String {
+  fill(
+    "report.summary",
+    &[
+      &time_to_str(generated),
+      &report
+        .group_total_lst
+        .iter()
+        .map(|total| total.lend_count)
+        .sum::<usize>(),
+      &report.unreturned_lst.len(),
+      &report.finding_lst.len(),
+    ],
+  )
+}
+
+fn escape_markdown(s: &str) -> String {
+  s.replace('|', "\\|")
+}
+
+pub fn render_markdown(
+  report: &FinalReport,
+  config_data: &ConfigData,
+  generated: DateTime<FixedOffset>,
+) -> String {
+  let mut s = format!(
+    "# {}\n\n{}\n",
+    text("report.title"),
+    summary_str(report, generated)
+  );
+  for section in make_section_lst(report, config_data).iter() {
+    s.push_str(&format!("\n## {}\n\n", section.title));
+    if section.row_lst.is_empty() {
+      s.push_str(&format!("{}\n", text("report.none")));
+      continue;
+    }
+    let line = |cell_lst: &[String]| {
+      let cell_lst: Vec<String> = cell_lst.iter().map(|cell| escape_markdown(cell)).collect();
+      format!("| {} |\n", cell_lst.join(" | "))
+    };
+    s.push_str(&line(&section.header));
+    s.push_str(&format!("|{}\n", "---|".repeat(section.header.len())));
+    for row in section.row_lst.iter() {
+      s.push_str(&line(row));
+    }
+  }
+  s
+}
+
+pub fn render_html(
+  report: &FinalReport,
+  config_data: &ConfigData,
+  generated: DateTime<FixedOffset>,
+) -> String {
+  let title = escape_xml(text("report.title"));
+  let mut s = format!(
+    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n",
+    title
+  );
+  s.push_str("<style>table { border-collapse: collapse; } th, td { border: 1px solid #000; padding: 4px 8px; }</style>\n");
+  s.push_str(&format!(
+    "</head>\n<body>\n<h1>{}</h1>\n<p>{}</p>\n",
+    title,
+    escape_xml(&summary_str(report, generated))
+  ));
+  for section in make_section_lst(report, config_data).iter() {
+    s.push_str(&format!("<h2>{}</h2>\n", escape_xml(&section.title)));
+    if section.row_lst.is_empty() {
+      s.push_str(&format!("<p>{}</p>\n", escape_xml(text("report.none"))));
+      continue;
+    }
+    let line = |tag: &str, cell_lst: &[String]| {
+      let cell_lst: Vec<String> = cell_lst
+        .iter()
+        .map(|cell| format!("<{tag}>{}</{tag}>", escape_xml(cell), tag = tag))
+        .collect();
+      format!("<tr>{}</tr>\n", cell_lst.concat())
+    };
+    s.push_str("<table>\n");
+    s.push_str(&line("th", &section.header));
+    for row in section.row_lst.iter() {
+      s.push_str(&line("td", row));
+    }
+    s.push_str("</table>\n");
+  }
+  s.push_str("</body>\n</html>\n");
+  s
+}
+
+// 節ごとに見出しの行と内容の行を書き出す
+// 1列目は節の名前（"group"・"unreturned"など）にして、表計算ソフトで絞り込めるようにする
+pub fn render_csv(report: &FinalReport, config_data: &ConfigData) -> String {
+  let mut wtr = csv::WriterBuilder::new()
+    .flexible(true)
+    .from_writer(Vec::new());
+  for section in make_section_lst(report, config_data).iter() {
+    let _ = wtr.write_record(
+      std::iter::once(format!("#{}", section.key)).chain(section.header.iter().cloned()),
+    );
+    for row in section.row_lst.iter() {
+      let _ = wtr.write_record(std::iter::once(section.key.to_string()).chain(row.iter().cloned()));
+    }
+  }
+  wtr
+    .into_inner()
+    .ok()
+    .and_then(|bytes| String::from_utf8(bytes).ok())
+    .unwrap_or_default()
+}
+
+#[test]
+fn check_make_final_report() {
+  let data = |num: isize, lend_type: LendType| {
+    crate::make_test_lend_data(num, "2020-11-23T17:40:00+09:00", lend_type)
+  };
+  let ledger = Ledger::new(vec![
+    data(1, LendType::Lend("0001".to_string(), "12".to_string())),
+    data(2, LendType::Lend("9999".to_string(), "13".to_string())),
+    data(3, LendType::Return("0001".to_string(), "12".to_string())),
+    data(4, LendType::Lend("0001".to_string(), "14".to_string())),
+    data(5, LendType::Remove(4)),
+    data(6, LendType::Lend("0002".to_string(), "15".to_string())),
+    data(
+      7,
+      LendType::Lost("0002".to_string(), Some("15".to_string())),
+    ),
+  ]);
+  let config_data = crate::make_config_data(
+    serde_json::json!({"0001": "内リール1", "0002": "内リール2"}),
+    serde_json::json!({"12": "物理部", "13": "化学部", "15": "生物部"}),
+    serde_json::json!({"13": "2-1"}),
+  );
+  let report = make_final_report(&ledger, &config_data);
+  assert_eq!(
+    report
+      .group_total_lst
+      .iter()
+      .map(|total| (
+        total.destination_num.as_str(),
+        total.lend_count,
+        total.outstanding_count
+      ))
+      .collect::<Vec<_>>(),
+    vec![("12", 1, 0), ("13", 1, 1), ("15", 1, 0)]
+  );
+  assert_eq!(report.unreturned_lst.len(), 1);
+  assert_eq!(report.uncatalogued_lst, vec![("9999".to_string(), 1)]);
+  assert!(report.unused_lst.is_empty());
+  assert_eq!(report.group_total_lst[2].lost_count, 1);
+  assert_eq!(report.retired_lst.len(), 1);
+  assert_eq!(report.correction_lst.len(), 1);
+  assert!(report.finding_lst.is_empty());
+  let csv = render_csv(&report, &config_data);
+  assert!(csv.contains("unreturned,9999,13（化学部）,2-1,"));
+}
+
+#[test]
+fn check_make_final_report_empty() {
+  let config_data = crate::make_config_data(
+    serde_json::json!({"0001": "内リール1"}),
+    serde_json::json!({"12": "物理部"}),
+    serde_json::json!({}),
+  );
+  let report = make_final_report(&Ledger::new(Vec::new()), &config_data);
+  // 一度も貸し出していなくても、設定ファイルにある団体と品名は載る
+  assert_eq!(report.group_total_lst.len(), 1);
+  assert_eq!(report.group_total_lst[0].lend_count, 0);
+  assert_eq!(report.unused_lst, vec!["0001".to_string()]);
+  assert!(report.unreturned_lst.is_empty());
+  assert!(report.uncatalogued_lst.is_empty());
+  assert!(report.correction_lst.is_empty());
+  assert!(report.finding_lst.is_empty());
+  let generated = chrono::DateTime::parse_from_rfc3339("2020-11-23T17:40:00+09:00").unwrap();
+  assert!(render_markdown(&report, &config_data, generated).contains("0001（内リール1）"));
+  assert!(render_html(&report, &config_data, generated).contains("0001（内リール1）"));
+  assert!(render_csv(&report, &config_data).contains("unused,0001"));
+}
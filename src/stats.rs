@@ -0,0 +1,480 @@
+use crate::catalog::{item_category, item_num_lst, matches_category};
+use crate::message::{fill, text};
+use crate::{destination_num_to_str, organize_lend_data, product_num_to_str};
+use crate::{ConfigData, LendData, LendType};
+use chrono::{DateTime, Duration, FixedOffset, Timelike};
+use std::collections::{BTreeMap, HashMap};
+
+// 集計する期間（どちらも無い場合は全ての期間）
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StatsWindow {
+  pub from: Option<DateTime<FixedOffset>>,
+  pub to: Option<DateTime<FixedOffset>>,
+}
+
+impl StatsWindow {
+  fn contains(&self, time: DateTime<FixedOffset>) -> bool {
+    self.from.is_none_or(|from| from <= time) && self.to.is_none_or(|to| time < to)
+  }
+
+  // 期間と重なる部分を返す（重ならない場合はNone）
+  fn clip(
+    &self,
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+  ) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+    let start = self.from.map_or(start, |from| start.max(from));
+    let end = self.to.map_or(end, |to| end.min(to));
+    if start < end {
+      Some((start, end))
+    } else {
+      None
+    }
+  }
+}
+
+// 書き出す形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsFormat {
+  Csv,
+  Json,
+}
+
+impl StatsFormat {
+  // ファイル名の拡張子から形式を決める
+  pub fn from_file_name(file_name: &str) -> Option<StatsFormat> {
+    let extension = std::path::Path::new(file_name)
+      .extension()?
+      .to_str()?
+      .to_ascii_lowercase();
+    match extension.as_str() {
+      "csv" => Some(StatsFormat::Csv),
+      "json" => Some(StatsFormat::Json),
+      _ => None,
+    }
+  }
+}
+
+// 品名ごとの集計
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemStats {
+  pub product_num: String,
+  // 期間と重なる貸出の回数
+  pub loan_count: usize,
+  // 期間内で貸し出されていた時間の合計
+  pub total_lent: Duration,
+}
+
+impl ItemStats {
+  pub fn average(&self) -> Duration {
+    if self.loan_count == 0 {
+      Duration::zero()
+    } else {
+      self.total_lent / self.loan_count as i32
+    }
+  }
+}
+
+// 種類ごとの、同時に貸し出されていた数の最大
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryPeak {
+  pub category: String,
+  // 種類に含まれる品名の数
+  pub item_count: usize,
+  pub peak: usize,
+  // 最大になった最初の時刻
+  pub at: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+  pub window: StatsWindow,
+  // 貸し出されていた時間の長い順
+  pub item_lst: Vec<ItemStats>,
+  pub category_lst: Vec<CategoryPeak>,
+  // 0時から23時までの、時間帯ごとの貸出と返却の操作の数
+  pub hour_lst: Vec<usize>,
+  // 団体ごとの貸出の回数（多い順）
+  pub group_lst: Vec<(String, usize)>,
+}
+
+// 貸出と返却を組にして、(品名, 貸出先, 貸出時刻, 返却時刻)のリストにする
+// 返却されていない貸出はnowまで貸し出されていたものとする
+fn make_loan_lst(
+  lend_data_lst: &[LendData],
+  now: DateTime<FixedOffset>,
+) -> Vec<(String, String, DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+  let mut open_map: HashMap<String, (String, DateTime<FixedOffset>)> = HashMap::new();
+  let mut loan_lst = Vec::new();
+  for data in organize_lend_data(lend_data_lst).iter() {
+    match &data.lend_type {
+      LendType::Lend(product_num, destination_num) => {
+        // 2重に貸し出されていた場合は、前の貸出をここで終わったものとする
+        if let Some((holder, start)) =
+          open_map.insert(product_num.clone(), (destination_num.clone(), data.time))
+        {
+          loan_lst.push((product_num.clone(), holder, start, data.time))
+        }
+      }
+      // 紛失・使用終了した品は、その時点で貸出が終わったものとする
+      LendType::Return(product_num, _)
+      | LendType::Lost(product_num, _)
+      | LendType::Retire(product_num, _) => {
+        if let Some((holder, start)) = open_map.remove(product_num) {
+          loan_lst.push((product_num.clone(), holder, start, data.time))
+        }
+      }
+      _ => (),
+    }
+  }
+  for (product_num, (holder, start)) in open_map.into_iter() {
+    loan_lst.push((product_num, holder, start, now.max(start)))
+  }
+  loan_lst.sort_by(|a, b| (a.2, &a.0).cmp(&(b.2, &b.0)));
+  loan_lst
+}
+
+// 編集と削除を反映させた貸出と返却から、期間内の利用状況を集計する
+// 種類が与えられた場合は、その種類かタグに当てはまる品名だけを集計する
+pub fn make_stats(
+  lend_data_lst: &[LendData],
+  config_data: &ConfigData,
+  window: StatsWindow,
+  category_opt: Option<&str>,
+  now: DateTime<FixedOffset>,
+) -> Stats {
+  let is_target = |product_num: &str| {
+    category_opt.is_none_or(|category| matches_category(product_num, config_data, category))
+  };
+  let loan_lst: Vec<_> = make_loan_lst(lend_data_lst, now)
+    .into_iter()
+    .filter(|(product_num, _, _, _)| is_target(product_num))
+    .collect();
+  let mut item_map: BTreeMap<String, ItemStats> = BTreeMap::new();
+  let mut group_map: BTreeMap<String, usize> = BTreeMap::new();
+  // 種類ごとの(時刻, 増減)
+  let mut event_map: BTreeMap<String, Vec<(DateTime<FixedOffset>, i64)>> = BTreeMap::new();
+  for (product_num, holder, start, end) in loan_lst.iter() {
+    if let Some((start, end)) = window.clip(*start, *end) {
+      let item = item_map
+        .entry(product_num.clone())
+        .or_insert_with(|| ItemStats {
+          product_num: product_num.clone(),
+          loan_count: 0,
+          total_lent: Duration::zero(),
+        });
+      item.loan_count += 1;
+      item.total_lent = item.total_lent + (end - start);
+      *group_map.entry(holder.clone()).or_insert(0) += 1;
+      let event_lst = event_map
+        .entry(item_category(product_num, config_data))
+        .or_default();
+      event_lst.push((start, 1));
+      event_lst.push((end, -1));
+    }
+  }
+  let mut item_lst: Vec<ItemStats> = item_map.into_values().collect();
+  item_lst.sort_by_key(|item| std::cmp::Reverse(item.total_lent));
+  // 設定ファイルにある品名を種類ごとに数える
+  let mut item_count_map: BTreeMap<String, usize> = BTreeMap::new();
+  for product_num in item_num_lst(config_data).iter() {
+    if is_target(product_num) {
+      *item_count_map
+        .entry(item_category(product_num, config_data))
+        .or_insert(0) += 1;
+    }
+  }
+  let category_lst = event_map
+    .into_iter()
+    .map(|(category, mut event_lst)| {
+      // 同じ時刻に返却と貸出がある場合は、返却を先に数える
+      event_lst.sort();
+      let mut count = 0;
+      let mut peak = 0;
+      let mut at = None;
+      for (time, diff) in event_lst.iter() {
+        count += diff;
+        if count as usize > peak {
+          peak = count as usize;
+          at = Some(*time);
+        }
+      }
+      CategoryPeak {
+        item_count: item_count_map.get(&category).cloned().unwrap_or(0),
+        category,
+        peak,
+        at,
+      }
+    })
+    .collect();
+  let mut hour_lst = vec![0; 24];
+  for data in organize_lend_data(lend_data_lst).iter() {
+    if matches!(
+      data.lend_type,
+      LendType::Lend(_, _) | LendType::Return(_, _)
+    ) && window.contains(data.time)
+    {
+      hour_lst[data.time.hour() as usize] += 1
+    }
+  }
+  let mut group_lst: Vec<(String, usize)> = group_map.into_iter().collect();
+  group_lst.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+  Stats {
+    window,
+    item_lst,
+    category_lst,
+    hour_lst,
+    group_lst,
+  }
+}
+
+fn time_to_str(time: DateTime<FixedOffset>) -> String {
+  time.format("%Y/%m/%d %H:%M").to_string()
+}
+
+// 「3時間25分」のような表示
+fn duration_to_str(duration: Duration) -> String {
+  fill(
+    "stats.duration",
+    &[&duration.num_hours(), &(duration.num_minutes() % 60)],
+  )
+}
+
+// 画面に表示する文字列
+// 時間帯は操作の多い順に上位5つを表示する
+pub fn render_text(stats: &Stats, config_data: &ConfigData) -> String {
+  let mut s = String::new();
+  let window_str = |time_opt: Option<DateTime<FixedOffset>>| match time_opt {
+    None => "-".to_string(),
+    Some(time) => time_to_str(time),
+  };
+  s.push_str(&format!(
+    "{}\n",
+    fill(
+      "stats.window",
+      &[&window_str(stats.window.from), &window_str(stats.window.to)]
+    )
+  ));
+  s.push_str(&format!("\n{}\n", text("stats.item_title")));
+  if stats.item_lst.is_empty() {
+    s.push_str(&format!("  {}\n", text("report.none")));
+  }
+  for item in stats.item_lst.iter() {
+    s.push_str(&format!(
+      "  {}\n",
+      fill(
+        "stats.item",
+        &[
+          &product_num_to_str(&item.product_num, Some(config_data)),
+          &item.loan_count,
+          &duration_to_str(item.total_lent),
+          &duration_to_str(item.average()),
+        ]
+      )
+    ));
+  }
+  s.push_str(&format!("\n{}\n", text("stats.category_title")));
+  if stats.category_lst.is_empty() {
+    s.push_str(&format!("  {}\n", text("report.none")));
+  }
+  for category in stats.category_lst.iter() {
+    s.push_str(&format!(
+      "  {}\n",
+      fill(
+        "stats.category",
+        &[
+          &category.category,
+          &category.peak,
+          &category.item_count,
+          &category.at.map(time_to_str).unwrap_or_default(),
+        ]
+      )
+    ));
+  }
+  s.push_str(&format!("\n{}\n", text("stats.hour_title")));
+  let mut hour_lst: Vec<(usize, usize)> = stats
+    .hour_lst
+    .iter()
+    .cloned()
+    .enumerate()
+    .filter(|(_, count)| *count > 0)
+    .collect();
+  if hour_lst.is_empty() {
+    s.push_str(&format!("  {}\n", text("report.none")));
+  }
+  hour_lst.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+  for (hour, count) in hour_lst.iter().take(5) {
+    s.push_str(&format!("  {}\n", fill("stats.hour", &[hour, count])));
+  }
+  s.push_str(&format!("\n{}\n", text("stats.group_title")));
+  if stats.group_lst.is_empty() {
+    s.push_str(&format!("  {}\n", text("report.none")));
+  }
+  for (destination_num, count) in stats.group_lst.iter() {
+    s.push_str(&format!(
+      "  {}\n",
+      fill(
+        "stats.group",
+        &[
+          &destination_num_to_str(destination_num, Some(config_data)),
+          count
+        ]
+      )
+    ));
+  }
+  s
+}
+
+// 節ごとに見出しの行と内容の行を書き出す
+// 時間は分単位の数で書き出す
+pub fn render_csv(stats: &Stats) -> String {
+  let mut wtr = csv::WriterBuilder::new()
+    .flexible(true)
+    .from_writer(Vec::new());
+  let _ = wtr.write_record([
+    "#item",
+    "product_num",
+    "loan_count",
+    "total_minutes",
+    "average_minutes",
+  ]);
+  for item in stats.item_lst.iter() {
+    let _ = wtr.write_record(&[
+      "item".to_string(),
+      item.product_num.clone(),
+      item.loan_count.to_string(),
+      item.total_lent.num_minutes().to_string(),
+      item.average().num_minutes().to_string(),
+    ]);
+  }
+  let _ = wtr.write_record(["#category", "category", "item_count", "peak", "peak_at"]);
+  for category in stats.category_lst.iter() {
+    let _ = wtr.write_record(&[
+      "category".to_string(),
+      category.category.clone(),
+      category.item_count.to_string(),
+      category.peak.to_string(),
+      category
+        .at
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default(),
+    ]);
+  }
+  let _ = wtr.write_record(["#hour", "hour", "operation_count"]);
+  for (hour, count) in stats.hour_lst.iter().enumerate() {
+    let _ = wtr.write_record(&["hour".to_string(), hour.to_string(), count.to_string()]);
+  }
+  let _ = wtr.write_record(["#group", "destination_num", "loan_count"]);
+  for (destination_num, count) in stats.group_lst.iter() {
+    let _ = wtr.write_record(&[
+      "group".to_string(),
+      destination_num.clone(),
+      count.to_string(),
+    ]);
+  }
+  wtr
+    .into_inner()
+    .ok()
+    .and_then(|bytes| String::from_utf8(bytes).ok())
+    .unwrap_or_default()
+}
+
+pub fn render_json(stats: &Stats) -> String {
+  let json = serde_json::json!({
+    "from": stats.window.from.map(|time| time.to_rfc3339()),
+    "to": stats.window.to.map(|time| time.to_rfc3339()),
+    "items": stats.item_lst.iter().map(|item| serde_json::json!({
+      "product_num": item.product_num,
+      "loan_count": item.loan_count,
+      "total_minutes": item.total_lent.num_minutes(),
+      "average_minutes": item.average().num_minutes(),
+    })).collect::<Vec<_>>(),
+    "categories": stats.category_lst.iter().map(|category| serde_json::json!({
+      "category": category.category,
+      "item_count": category.item_count,
+      "peak": category.peak,
+      "peak_at": category.at.map(|time| time.to_rfc3339()),
+    })).collect::<Vec<_>>(),
+    "hours": stats.hour_lst,
+    "groups": stats.group_lst.iter().map(|(destination_num, count)| serde_json::json!({
+      "destination_num": destination_num,
+      "loan_count": count,
+    })).collect::<Vec<_>>(),
+  });
+  serde_json::to_string_pretty(&json).unwrap_or_default()
+}
+
+#[test]
+fn check_make_stats() {
+  let time = |s: &str| chrono::DateTime::parse_from_rfc3339(s).unwrap();
+  let data = crate::make_test_lend_data;
+  let lend = |item: &str, group: &str| LendType::Lend(item.to_string(), group.to_string());
+  let ret = |item: &str, group: &str| LendType::Return(item.to_string(), group.to_string());
+  let lend_data_lst = vec![
+    data(1, "2020-11-23T09:00:00+09:00", lend("0001", "12")),
+    data(2, "2020-11-23T09:30:00+09:00", lend("0002", "13")),
+    data(3, "2020-11-23T10:00:00+09:00", ret("0001", "12")),
+    data(4, "2020-11-23T10:00:00+09:00", lend("0001", "13")),
+    data(5, "2020-11-23T11:00:00+09:00", ret("0001", "13")),
+  ];
+  let config_data = crate::make_config_data(
+    serde_json::json!({"0001": "内リール1", "0002": "内リール2", "0003": "延長コード"}),
+    serde_json::json!(null),
+    serde_json::json!(null),
+  );
+  let now = time("2020-11-23T12:00:00+09:00");
+  let stats = make_stats(
+    &lend_data_lst,
+    &config_data,
+    StatsWindow::default(),
+    None,
+    now,
+  );
+  // 0002は返却されていないので12時まで貸し出されていたことになる
+  assert_eq!(stats.item_lst[0].product_num, "0002");
+  assert_eq!(stats.item_lst[0].total_lent, Duration::minutes(150));
+  assert_eq!(stats.item_lst[1].loan_count, 2);
+  assert_eq!(stats.item_lst[1].average(), Duration::minutes(60));
+  // 10時の返却と貸出は同時に貸し出されていたことにはならない
+  assert_eq!(stats.category_lst[0].category, "内リール");
+  assert_eq!(stats.category_lst[0].peak, 2);
+  assert_eq!(stats.category_lst[0].item_count, 2);
+  assert_eq!(stats.hour_lst[10], 2);
+  assert_eq!(
+    stats.group_lst,
+    vec![("13".to_string(), 2), ("12".to_string(), 1)]
+  );
+  let window = StatsWindow {
+    from: Some(time("2020-11-23T10:30:00+09:00")),
+    to: None,
+  };
+  let stats = make_stats(&lend_data_lst, &config_data, window, None, now);
+  assert_eq!(stats.item_lst[0].total_lent, Duration::minutes(90));
+  assert_eq!(stats.item_lst[1].total_lent, Duration::minutes(30));
+  assert_eq!(stats.hour_lst[10], 0);
+}
+
+#[test]
+fn check_make_stats_empty() {
+  let config_data = crate::make_config_data(
+    serde_json::json!({"0001": "内リール1"}),
+    serde_json::json!(null),
+    serde_json::json!(null),
+  );
+  let now = chrono::DateTime::parse_from_rfc3339("2020-11-23T12:00:00+09:00").unwrap();
+  let stats = make_stats(&[], &config_data, StatsWindow::default(), None, now);
+  assert!(stats.item_lst.is_empty());
+  assert!(stats.category_lst.is_empty());
+  assert_eq!(stats.hour_lst, vec![0; 24]);
+  assert!(stats.group_lst.is_empty());
+  // 何も無い項目は「なし」と表示し、CSVはヘッダーと24時間分の行だけにする
+  assert_eq!(
+    render_text(&stats, &config_data)
+      .matches(text("report.none"))
+      .count(),
+    4
+  );
+  assert_eq!(render_csv(&stats).lines().count(), 4 + 24);
+  let json: serde_json::Value = serde_json::from_str(&render_json(&stats)).unwrap();
+  assert_eq!(json["items"], serde_json::json!([]));
+}
@@ -0,0 +1,285 @@
+use crate::catalog::make_holder_map;
+use crate::message::{fill, text};
+use crate::{destination_num_to_str, product_num_to_str, ConfigData, LendData, LendType};
+use chrono::{DateTime, FixedOffset};
+use std::collections::{HashMap, HashSet};
+
+// 棚卸しで読み取った番号の種類
+#[derive(Debug, Clone, PartialEq)]
+pub enum StocktakeCode {
+  // 手元にあるはずの品名
+  Present(String),
+  // 記録では貸し出されている品名と、その貸出先
+  Lent(String, String),
+  // 記録では紛失・使用終了とされている品名
+  Retired(String),
+  // 設定ファイルに無く、貸し出されてもいない番号
+  Unknown(String),
+}
+
+// 棚卸しの結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stocktake {
+  pub time: DateTime<FixedOffset>,
+  pub operator: Option<String>,
+  // 読み取った番号（読み取った順、重複無し）
+  pub scanned_lst: Vec<String>,
+  // 手元にあるはずなのに読み取られなかった品名（品名順）
+  pub missing_lst: Vec<String>,
+  // 読み取ったが、記録では貸し出されている品名と、その貸出先
+  pub lent_lst: Vec<(String, String)>,
+  // 読み取ったが、記録では紛失・使用終了とされている品名
+  pub retired_lst: Vec<String>,
+  // 設定ファイルに無い番号
+  pub unknown_lst: Vec<String>,
+}
+
+// 紛失・使用終了した品名
+fn make_retired_set(retired_lend_data_lst: &[LendData]) -> HashSet<&str> {
+  retired_lend_data_lst
+    .iter()
+    .filter_map(|lend_data| match &lend_data.lend_type {
+      LendType::Lost(product_num, _) | LendType::Retire(product_num, _) => {
+        Some(product_num.as_str())
+      }
+      _ => None,
+    })
+    .collect()
+}
+
+// 読み取った番号を、設定ファイルと現在の貸出状況を使って見分ける
+// 設定ファイルに無い番号でも、貸し出されている記録があれば品名として扱う
+pub fn classify_code(
+  code: &str,
+  now_lend_data_lst: &[LendData],
+  retired_lend_data_lst: &[LendData],
+  config_data: &ConfigData,
+) -> StocktakeCode {
+  classify_code_with(
+    code,
+    &make_holder_map(now_lend_data_lst),
+    &make_retired_set(retired_lend_data_lst),
+    config_data,
+  )
+}
+
+// 貸出先と紛失・使用終了の対応を一度だけ作って、複数の番号を見分けるときに使う
+fn classify_code_with(
+  code: &str,
+  holder_map: &HashMap<&str, &str>,
+  retired_set: &HashSet<&str>,
+  config_data: &ConfigData,
+) -> StocktakeCode {
+  let code = code.trim().to_string();
+  if let Some(holder) = holder_map.get(code.as_str()) {
+    StocktakeCode::Lent(code.clone(), holder.to_string())
+  } else if retired_set.contains(code.as_str()) {
+    StocktakeCode::Retired(code)
+  } else if config_data.sizai.get(&code).is_some() {
+    StocktakeCode::Present(code)
+  } else {
+    StocktakeCode::Unknown(code)
+  }
+}
+
+// 読み取った番号と、設定ファイルにある品名から貸出中と紛失・使用終了のものを除いたものを突き合わせる
+pub fn make_stocktake(
+  scanned_lst: &[String],
+  now_lend_data_lst: &[LendData],
+  retired_lend_data_lst: &[LendData],
+  config_data: &ConfigData,
+  time: DateTime<FixedOffset>,
+  operator: Option<String>,
+) -> Stocktake {
+  let mut present_lst = Vec::new();
+  let mut lent_lst = Vec::new();
+  let mut retired_lst = Vec::new();
+  let mut unknown_lst = Vec::new();
+  let holder_map = make_holder_map(now_lend_data_lst);
+  let retired_set = make_retired_set(retired_lend_data_lst);
+  for code in scanned_lst.iter() {
+    match classify_code_with(code, &holder_map, &retired_set, config_data) {
+      StocktakeCode::Present(product_num) => present_lst.push(product_num),
+      StocktakeCode::Lent(product_num, holder) => lent_lst.push((product_num, holder)),
+      StocktakeCode::Retired(product_num) => retired_lst.push(product_num),
+      StocktakeCode::Unknown(code) => unknown_lst.push(code),
+    }
+  }
+  let present_set: HashSet<&str> = present_lst
+    .iter()
+    .map(|product_num| product_num.as_str())
+    .collect();
+  let mut missing_lst: Vec<String> = crate::catalog::item_num_lst(config_data)
+    .into_iter()
+    .filter(|product_num| {
+      !holder_map.contains_key(product_num.as_str())
+        && !retired_set.contains(product_num.as_str())
+        && !present_set.contains(product_num.as_str())
+    })
+    .collect();
+  missing_lst.sort();
+  Stocktake {
+    time,
+    operator,
+    scanned_lst: scanned_lst.to_vec(),
+    missing_lst,
+    lent_lst,
+    retired_lst,
+    unknown_lst,
+  }
+}
+
+// 保存するファイルの名前
+pub fn stocktake_file_name(stocktake: &Stocktake) -> String {
+  format!("stocktake-{}.txt", stocktake.time.format("%Y%m%d-%H%M%S"))
+}
+
+// 棚卸しの結果を表示・保存する文字列にする
+// 見出しの後に一行ずつ品名を並べ、該当するものが無い場合はそのことを書く
+pub fn render_text(stocktake: &Stocktake, config_data: &ConfigData) -> String {
+  let time_str = stocktake.time.format("%Y/%m/%d %H:%M").to_string();
+  let mut s = match &stocktake.operator {
+    None => fill("stocktake.title", &[&time_str]),
+    Some(operator) => fill("stocktake.title_with_operator", &[&time_str, operator]),
+  };
+  s.push('\n');
+  s.push_str(&fill("stocktake.scanned", &[&stocktake.scanned_lst.len()]));
+  s.push('\n');
+  let section_lst = [
+    (
+      fill("stocktake.missing_title", &[&stocktake.missing_lst.len()]),
+      stocktake
+        .missing_lst
+        .iter()
+        .map(|product_num| product_num_to_str(product_num, Some(config_data)))
+        .collect::<Vec<String>>(),
+    ),
+    (
+      fill("stocktake.lent_title", &[&stocktake.lent_lst.len()]),
+      stocktake
+        .lent_lst
+        .iter()
+        .map(|(product_num, holder)| {
+          fill(
+            "stocktake.lent_item",
+            &[
+              &product_num_to_str(product_num, Some(config_data)),
+              &destination_num_to_str(holder, Some(config_data)),
+            ],
+          )
+        })
+        .collect(),
+    ),
+    (
+      fill("stocktake.retired_title", &[&stocktake.retired_lst.len()]),
+      stocktake
+        .retired_lst
+        .iter()
+        .map(|product_num| product_num_to_str(product_num, Some(config_data)))
+        .collect(),
+    ),
+    (
+      fill("stocktake.unknown_title", &[&stocktake.unknown_lst.len()]),
+      stocktake.unknown_lst.clone(),
+    ),
+  ];
+  for (title, line_lst) in section_lst.iter() {
+    s.push_str(&format!("\n{}\n", title));
+    if line_lst.is_empty() {
+      s.push_str(&format!("  {}\n", text("stocktake.none")));
+    }
+    for line in line_lst.iter() {
+      s.push_str(&format!("  - {}\n", line));
+    }
+  }
+  s
+}
+
+#[test]
+fn check_make_stocktake() {
+  let time = chrono::DateTime::parse_from_rfc3339("2020-11-23T09:00:00+09:00").unwrap();
+  let data = |num: isize, lend_type: LendType| {
+    crate::make_test_lend_data(num, "2020-11-23T09:00:00+09:00", lend_type)
+  };
+  let config_data = crate::make_config_data(
+    serde_json::json!({"0001": "内リール1", "0002": "内リール2", "0003": "内リール3", "0004": "内リール4"}),
+    serde_json::json!({"12": "物理部"}),
+    serde_json::json!({}),
+  );
+  let now_lend_data_lst = vec![data(
+    1,
+    LendType::Lend("0002".to_string(), "12".to_string()),
+  )];
+  let retired_lend_data_lst = vec![data(2, LendType::Lost("0004".to_string(), None))];
+  let scanned_lst: Vec<String> = ["0001", "0002", "9999"]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+  let stocktake = make_stocktake(
+    &scanned_lst,
+    &now_lend_data_lst,
+    &retired_lend_data_lst,
+    &config_data,
+    time,
+    None,
+  );
+  // 貸出中の0002と紛失した0004は、手元に無くてもよい
+  assert_eq!(stocktake.missing_lst, vec!["0003".to_string()]);
+  assert_eq!(
+    stocktake.lent_lst,
+    vec![("0002".to_string(), "12".to_string())]
+  );
+  assert!(stocktake.retired_lst.is_empty());
+  assert_eq!(stocktake.unknown_lst, vec!["9999".to_string()]);
+}
+
+#[test]
+fn check_make_stocktake_unknown() {
+  let time = chrono::DateTime::parse_from_rfc3339("2020-11-23T09:00:00+09:00").unwrap();
+  let config_data = crate::make_config_data(
+    serde_json::json!({"0001": "内リール1", "0002": "内リール2"}),
+    serde_json::json!({"12": "物理部"}),
+    serde_json::json!({}),
+  );
+  // 設定ファイルに無い品名でも、貸し出されている記録があれば品名として扱う
+  let now_lend_data_lst = vec![crate::make_test_lend_data(
+    1,
+    "2020-11-23T09:00:00+09:00",
+    LendType::Lend("8888".to_string(), "12".to_string()),
+  )];
+  assert_eq!(
+    classify_code(" 9999 ", &now_lend_data_lst, &[], &config_data),
+    StocktakeCode::Unknown("9999".to_string())
+  );
+  assert_eq!(
+    classify_code("8888", &now_lend_data_lst, &[], &config_data),
+    StocktakeCode::Lent("8888".to_string(), "12".to_string())
+  );
+  let scanned_lst: Vec<String> = ["9999", "0001", "abc", "8888"]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+  let stocktake = make_stocktake(
+    &scanned_lst,
+    &now_lend_data_lst,
+    &[],
+    &config_data,
+    time,
+    None,
+  );
+  // 設定ファイルに無い番号は読み取った順に並べ、手元にあるはずの品名の突き合わせには影響しない
+  assert_eq!(
+    stocktake.unknown_lst,
+    vec!["9999".to_string(), "abc".to_string()]
+  );
+  assert_eq!(stocktake.missing_lst, vec!["0002".to_string()]);
+  assert_eq!(
+    stocktake.lent_lst,
+    vec![("8888".to_string(), "12".to_string())]
+  );
+  let s = render_text(&stocktake, &config_data);
+  assert!(s.contains(&format!(
+    "{}\n  - 9999\n  - abc\n",
+    fill("stocktake.unknown_title", &[&2])
+  )));
+}